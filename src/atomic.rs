@@ -0,0 +1,68 @@
+use crate::{ArchiveSummary, Result, ZipArchive, ZipArchiveBuilder};
+use std::fs::File;
+use std::path::Path;
+
+impl<'a> ZipArchive<'a, File> {
+    /// Build an archive by `build` and rename it to `path` after it is finished.
+    ///
+    /// See [`ZipArchiveBuilder::create_atomic`].
+    pub fn create_atomic<P, F>(path: P, build: F) -> Result<ArchiveSummary>
+    where
+        P: AsRef<Path>,
+        F: FnOnce(ZipArchive<File>) -> Result<ZipArchive<File>>,
+    {
+        ZipArchiveBuilder::new().create_atomic(path, build)
+    }
+
+    /// Finish the archive like [`finish`](ZipArchive::finish) and sync the file
+    /// to the storage device.
+    ///
+    /// The directory entry of a new file is durable only after its directory is synced too.
+    pub fn finish_durable(mut self) -> Result<ArchiveSummary> {
+        self.check_poisoned()?;
+        let summary = self.write_ending()?;
+        self.output.sync_all()?;
+        Ok(summary)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{verify, Error, Level, ZipArchive, ZipArchiveBuilder};
+
+    #[test]
+    fn it_works() {
+        let dir = std::env::temp_dir().join(format!("zip-builder-atomic-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("archive.zip");
+        std::fs::write(&path, b"old").unwrap();
+        let result = ZipArchive::create_atomic(&path, |zip| {
+            zip.add_entry("a.txt", b"a", Level::Default)?;
+            Err(Error::Poisoned)
+        });
+        assert!(matches!(result, Err(Error::Poisoned)));
+        assert_eq!(std::fs::read(&path).unwrap(), b"old");
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+        let summary =
+            ZipArchive::create_atomic(&path, |zip| zip.add_entry("a.txt", b"a", Level::Default))
+                .unwrap();
+        let written = std::fs::read(&path).unwrap();
+        assert_eq!(written.len() as u64, summary.size);
+        assert_eq!(verify(written.as_slice()).unwrap(), vec![]);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+        ZipArchiveBuilder::new()
+            .durable()
+            .create_atomic(&path, |zip| zip.add_entry("b.txt", b"b", Level::Default))
+            .unwrap();
+        let mut file = std::fs::File::create(dir.join("direct.zip")).unwrap();
+        let summary = ZipArchive::new(&mut file)
+            .add_entry("c.txt", b"c", Level::Default)
+            .unwrap()
+            .finish_durable()
+            .unwrap();
+        assert_eq!(file.metadata().unwrap().len(), summary.size);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
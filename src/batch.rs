@@ -0,0 +1,123 @@
+use crate::{EntryOptions, Metadata, Result, ZipArchive, ZipState};
+use std::io::Write;
+
+/// Size of buffered entries to write at once.
+const BATCH_SIZE: usize = 1 << 16;
+
+impl<'a, T: Write + ?Sized + 'a> ZipArchive<'a, T> {
+    /// Add many entries, writing them to the output in batches.
+    ///
+    /// Headers and data of entries are collected up to 64 KiB and written by one call,
+    /// which saves per-entry overhead of many small writes for tiny files.
+    /// If an entry fails, the archive is poisoned.
+    ///
+    /// ```rust
+    /// use zip_builder::{Level, ZipArchive};
+    ///
+    /// let names: Vec<String> = (0..100).map(|n| format!("{}.txt", n)).collect();
+    /// let mut buf = Vec::new();
+    /// ZipArchive::new(&mut buf)
+    ///     .add_entries(names.iter().map(|name| (&name[..], name.as_bytes(), Level::Default)))?
+    ///     .finish()?;
+    /// # Ok::<(), zip_builder::Error>(())
+    /// ```
+    pub fn add_entries<'e, I, O>(mut self, entries: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = (&'e str, &'e [u8], O)>,
+        O: Into<EntryOptions>,
+    {
+        let result = self.push_entries(entries);
+        self.or_poison(result)
+    }
+
+    fn push_entries<'e, I, O>(&mut self, entries: I) -> Result<()>
+    where
+        I: IntoIterator<Item = (&'e str, &'e [u8], O)>,
+        O: Into<EntryOptions>,
+    {
+        self.check_poisoned()?;
+        self.batch = Some(Vec::with_capacity(BATCH_SIZE));
+        let result = self.write_entries(entries);
+        let batch = self.batch.take().unwrap_or_default();
+        result?;
+        self.output.write_all(&batch)?;
+        if self.config.auto_flush {
+            self.output.flush()?;
+        }
+        self.state = ZipState::Breathe;
+        Ok(())
+    }
+
+    fn write_entries<'e, I, O>(&mut self, entries: I) -> Result<()>
+    where
+        I: IntoIterator<Item = (&'e str, &'e [u8], O)>,
+        O: Into<EntryOptions>,
+    {
+        for (name, content, options) in entries {
+            let options = options.into();
+            if self.overdue(name) || self.unchanged(name, content) {
+                continue;
+            }
+            self.check_limits(name, content.len(), None)?;
+            self.check_options(name, options)?;
+            if self.defer(name, content, options, &Metadata::default()) {
+                continue;
+            }
+            self.state = ZipState::Processing;
+            self.write_entry(name, content, options, &Metadata::default())?;
+            self.place(name)?;
+            if let Some(batch) = self
+                .batch
+                .as_mut()
+                .filter(|batch| batch.len() >= BATCH_SIZE)
+            {
+                self.output.write_all(batch)?;
+                batch.clear();
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{verify, Level, ZipArchive};
+    use std::io::{Result, Write};
+
+    struct Writes(Vec<u8>, usize);
+
+    impl Write for Writes {
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            self.1 += 1;
+            self.0.write(buf)
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn it_works() {
+        let names: Vec<String> = (0..1000).map(|n| format!("{}.txt", n)).collect();
+        let entries = || {
+            names
+                .iter()
+                .map(|name| (&name[..], name.as_bytes(), Level::Default))
+        };
+        let mut batched = Writes(Vec::new(), 0);
+        ZipArchive::new(&mut batched)
+            .add_entries(entries())
+            .unwrap()
+            .finish()
+            .unwrap();
+        let mut each = Writes(Vec::new(), 0);
+        let mut zip = ZipArchive::new(&mut each);
+        for (name, content, level) in entries() {
+            zip = zip.add_entry(name, content, level).unwrap();
+        }
+        zip.finish().unwrap();
+        assert!(batched.1 < each.1 / 2);
+        assert_eq!(verify(batched.0.as_slice()).unwrap(), vec![]);
+    }
+}
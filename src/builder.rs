@@ -0,0 +1,1087 @@
+use crate::spool::TempFile;
+#[cfg(feature = "unicode-normalization")]
+use crate::Normalization;
+#[cfg(feature = "aes")]
+use crate::{encryption::Password, KeyDerivation, RandomSource};
+use crate::{
+    ArchiveSummary, Checkpoint, CompressionPolicy, EntryInfo, EntryOptions, Level, Observer,
+    Profile, Result, TimeOverflow, TimeRounding, Unicode, ZipArchive, ZipEntry,
+};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Instant, SystemTime};
+
+/// Order of records in the central directory.
+///
+/// It is independent of the order of entry data.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum DirectoryOrder {
+    /// Order in which entries are added.
+    #[default]
+    Insertion,
+    /// Order of names by byte.
+    Name,
+    /// Order by the comparator.
+    Custom(fn(&EntryInfo, &EntryInfo) -> Ordering),
+}
+
+impl DirectoryOrder {
+    pub(crate) fn sort(&self, entries: Vec<ZipEntry>) -> Vec<ZipEntry> {
+        match self {
+            DirectoryOrder::Insertion => entries,
+            DirectoryOrder::Name => {
+                let mut entries = entries;
+                entries.sort_by(|a, b| a.filename.cmp(&b.filename));
+                entries
+            }
+            DirectoryOrder::Custom(compare) => {
+                let mut pairs: Vec<_> = entries
+                    .into_iter()
+                    .map(|entry| (entry.info(), entry))
+                    .collect();
+                pairs.sort_by(|a, b| compare(&a.0, &b.0));
+                pairs.into_iter().map(|(_, entry)| entry).collect()
+            }
+        }
+    }
+}
+
+/// Action for entries of poor compression.
+///
+/// See [`ZipArchiveBuilder::compression_ratio_threshold`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PoorCompression {
+    /// Only report to the observer.
+    Report,
+    /// Report to the observer and store the entry without compression.
+    Store,
+}
+
+/// Action for names which differ from another entry only by case.
+///
+/// See [`ZipArchiveBuilder::case_collision`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CaseCollision {
+    /// Only report to the observer.
+    Report,
+    /// Report to the observer and fail with [`Error::CaseCollision`](crate::Error::CaseCollision).
+    Reject,
+}
+
+/// Origin of offsets in the central directory of an archive after leading bytes.
+///
+/// See [`ZipArchiveBuilder::base_offset`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Offsets {
+    /// Offsets from the start of the file including the leading bytes, as the specification
+    /// expects and `zip -A` writes. Most extractors and self-extracting stubs read these.
+    Absolute,
+    /// Offsets from the start of the archive, as if the leading bytes were not there.
+    /// Some extractors find the archive by the end records and expect these.
+    Relative,
+}
+
+/// Compression work is divided into quanta of input bytes with the callback between them.
+#[derive(Clone)]
+pub(crate) struct TimeSlice {
+    quantum: usize,
+    callback: Arc<dyn Fn() + Send + Sync>,
+}
+
+impl TimeSlice {
+    /// Write `content` to the compressor `writer` slice by slice.
+    pub(crate) fn write_all<W: Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        content: &[u8],
+    ) -> io::Result<()> {
+        for (index, chunk) in content.chunks(self.quantum).enumerate() {
+            if index > 0 {
+                (self.callback)();
+            }
+            writer.write_all(chunk)?;
+        }
+        Ok(())
+    }
+}
+
+/// Hook choosing the level of an entry from its name and leading bytes.
+pub(crate) type Sniffer = Arc<dyn Fn(&str, &[u8]) -> Level + Send + Sync>;
+
+/// Number of leading bytes of the content passed to the sniffer.
+pub(crate) const SNIFF_LENGTH: usize = 512;
+
+#[derive(Clone)]
+pub(crate) struct Config {
+    pub(crate) defaults: EntryOptions,
+    pub(crate) comment: String,
+    pub(crate) root_prefix: String,
+    pub(crate) place_first: Vec<String>,
+    pub(crate) previous: HashMap<String, (u32, u64)>,
+    #[cfg(feature = "json-manifest")]
+    pub(crate) json_manifest: bool,
+    pub(crate) deduplicate: bool,
+    pub(crate) digest: bool,
+    pub(crate) auto_flush: bool,
+    pub(crate) order: DirectoryOrder,
+    pub(crate) zip64_threshold: u64,
+    pub(crate) base_offset: Option<(u64, Offsets)>,
+    pub(crate) limits: Limits,
+    pub(crate) time_slice: Option<TimeSlice>,
+    pub(crate) deadline: Option<Instant>,
+    pub(crate) compression_policy: Option<CompressionPolicy>,
+    pub(crate) sniffer: Option<Sniffer>,
+    pub(crate) observer: Option<Arc<dyn Observer + Send + Sync>>,
+    pub(crate) share_hard_links: bool,
+    pub(crate) profile: Option<Profile>,
+    pub(crate) ratio_threshold: Option<(f64, PoorCompression)>,
+    pub(crate) case_collision: Option<CaseCollision>,
+    pub(crate) memory_budget: Option<usize>,
+    pub(crate) spool: Option<(usize, PathBuf)>,
+    pub(crate) durable: bool,
+    #[cfg(feature = "aes")]
+    pub(crate) password: Option<Password>,
+    #[cfg(feature = "aes")]
+    pub(crate) key_derivation: Option<Arc<dyn KeyDerivation + Send + Sync>>,
+    #[cfg(feature = "aes")]
+    pub(crate) random_source: Option<Arc<dyn RandomSource + Send + Sync>>,
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct Limits {
+    pub(crate) entries: Option<usize>,
+    pub(crate) total_size: Option<u64>,
+    pub(crate) entry_size: Option<u64>,
+    pub(crate) name_length: Option<usize>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            defaults: EntryOptions::default(),
+            comment: String::new(),
+            root_prefix: String::new(),
+            place_first: Vec::new(),
+            previous: HashMap::new(),
+            #[cfg(feature = "json-manifest")]
+            json_manifest: false,
+            deduplicate: false,
+            digest: false,
+            auto_flush: false,
+            order: DirectoryOrder::default(),
+            zip64_threshold: u32::MAX as u64,
+            base_offset: None,
+            limits: Limits::default(),
+            time_slice: None,
+            deadline: None,
+            compression_policy: None,
+            sniffer: None,
+            observer: None,
+            share_hard_links: false,
+            profile: None,
+            ratio_threshold: None,
+            case_collision: None,
+            memory_budget: None,
+            spool: None,
+            durable: false,
+            #[cfg(feature = "aes")]
+            password: None,
+            #[cfg(feature = "aes")]
+            key_derivation: None,
+            #[cfg(feature = "aes")]
+            random_source: None,
+        }
+    }
+}
+
+/// Builder of [`ZipArchive`] with archive-wide options.
+///
+/// Options of entries set here are defaults which each entry inherits
+/// unless the [`EntryOptions`] given to [`add_entry`](ZipArchive::add_entry) overrides.
+///
+/// ```rust
+/// use zip_builder::{EntryOptions, Level, ZipArchiveBuilder};
+///
+/// let mut buf = Vec::new();
+/// ZipArchiveBuilder::new()
+///     .level(Level::High)
+///     .comment("generated by zip-builder")
+///     .build(&mut buf)
+///     .add_entry("high.txt", b"content", EntryOptions::default())?
+///     .add_entry("raw.txt", b"content", Level::Raw)?
+///     .finish()?;
+/// # Ok::<(), zip_builder::Error>(())
+/// ```
+#[derive(Clone, Default)]
+pub struct ZipArchiveBuilder {
+    config: Config,
+}
+
+/// Archive-level configuration which can be shared to build many archives.
+///
+/// This is [`ZipArchiveBuilder`] itself. It is `Clone + Send + Sync`
+/// and [`build`](ZipArchiveBuilder::build) borrows it, so one configuration
+/// can be kept in an [`Arc`] and used from concurrent requests.
+pub type ZipConfig = ZipArchiveBuilder;
+
+impl ZipArchiveBuilder {
+    /// Create a builder with default options.
+    pub fn new() -> ZipArchiveBuilder {
+        ZipArchiveBuilder::default()
+    }
+
+    /// Set default options of entries.
+    pub fn options(mut self, options: EntryOptions) -> Self {
+        self.config.defaults = options;
+        self
+    }
+
+    /// Set default compression level.
+    pub fn level(mut self, level: Level) -> Self {
+        self.config.defaults = self.config.defaults.level(level);
+        self
+    }
+
+    /// Set default modification time of entries.
+    pub fn modified(mut self, time: SystemTime) -> Self {
+        self.config.defaults = self.config.defaults.modified(time);
+        self
+    }
+
+    /// Set default rounding of modification time.
+    pub fn time_rounding(mut self, rounding: TimeRounding) -> Self {
+        self.config.defaults = self.config.defaults.time_rounding(rounding);
+        self
+    }
+
+    /// Set default handling of modification time after 2107.
+    pub fn time_overflow(mut self, overflow: TimeOverflow) -> Self {
+        self.config.defaults = self.config.defaults.time_overflow(overflow);
+        self
+    }
+
+    /// Set default policy of UTF-8 flag.
+    pub fn unicode(mut self, unicode: Unicode) -> Self {
+        self.config.defaults = self.config.defaults.unicode(unicode);
+        self
+    }
+
+    /// Set default Unicode normalization form of entry names.
+    #[cfg(feature = "unicode-normalization")]
+    pub fn normalization(mut self, normalization: Normalization) -> Self {
+        self.config.defaults = self.config.defaults.normalization(normalization);
+        self
+    }
+
+    /// Choose methods of entries by compressing samples first by default.
+    ///
+    /// See [`EntryOptions::two_pass`].
+    pub fn two_pass(mut self) -> Self {
+        self.config.defaults = self.config.defaults.two_pass(true);
+        self
+    }
+
+    /// Store incompressible contents of entries by estimating their entropy by default.
+    ///
+    /// See [`EntryOptions::entropy_probe`].
+    pub fn entropy_probe(mut self) -> Self {
+        self.config.defaults = self.config.defaults.entropy_probe(true);
+        self
+    }
+
+    /// Set default alignment of stored entries.
+    pub fn alignment(mut self, alignment: u32) -> Self {
+        self.config.defaults = self.config.defaults.alignment(alignment);
+        self
+    }
+
+    /// Set comment of the archive.
+    pub fn comment(mut self, comment: &str) -> Self {
+        self.config.comment = comment.to_string();
+        self
+    }
+
+    /// Prepend `prefix` to names of all entries, like `myapp-1.2.3/` of source archives.
+    ///
+    /// It is prepended as it is, so it usually ends with `/`. Names given to methods
+    /// like [`replace_entry`](ZipArchive::replace_entry) are without the prefix.
+    pub fn root_prefix(mut self, prefix: &str) -> Self {
+        self.config.root_prefix = prefix.to_string();
+        self
+    }
+
+    /// Enable deduplication of entries by content.
+    ///
+    /// See [`ZipArchive::deduplicate`].
+    pub fn deduplicate(mut self) -> Self {
+        self.config.deduplicate = true;
+        self
+    }
+
+    /// Compute SHA-256 of each content.
+    ///
+    /// See [`ZipArchive::digest`].
+    pub fn digest(mut self) -> Self {
+        self.config.digest = true;
+        self
+    }
+
+    /// Flush the output writer at the end of each entry.
+    ///
+    /// Useful for sockets where the peer should see data promptly.
+    /// See also [`ZipArchive::flush_output`].
+    pub fn auto_flush(mut self) -> Self {
+        self.config.auto_flush = true;
+        self
+    }
+
+    /// Set order of records in the central directory.
+    pub fn order(mut self, order: DirectoryOrder) -> Self {
+        self.config.order = order;
+        self
+    }
+
+    /// Place data of entries of `names` first, like an index which readers fetch
+    /// by a range request, in whatever order entries are added.
+    ///
+    /// Other entries are kept in memory until all of `names` are added,
+    /// or until [`finish`](ZipArchive::finish) if some of them are never added.
+    /// Entries added by [`add_lazy_entry`](ZipArchive::add_lazy_entry) or
+    /// [`replace_entry`](ZipArchive::replace_entry) are not deferred,
+    /// and [`checkpoint`](ZipArchive::checkpoint) does not include deferred entries.
+    pub fn place_first<I, S>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.config.place_first = names.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Build an update of a previous archive listed by name, CRC-32 and size of entries,
+    /// such as a manifest or the [`ArchiveSummary`] of the previous archive.
+    ///
+    /// Entries with the same name, CRC-32 and size as in the previous archive are skipped,
+    /// so only changed and added entries are written. Names include the
+    /// [`root_prefix`](ZipArchiveBuilder::root_prefix). Removed entries are not recorded.
+    /// Entries added by [`add_lazy_entry`](ZipArchive::add_lazy_entry) or
+    /// [`replace_entry`](ZipArchive::replace_entry) are always written.
+    ///
+    /// ```
+    /// # use zip_builder::{Level, ZipArchive, ZipArchiveBuilder};
+    /// let mut zip = Vec::new();
+    /// let previous = ZipArchive::new(&mut zip)
+    ///     .add_entry("a.txt", b"a", Level::Default)?
+    ///     .finish()?;
+    /// let mut update = Vec::new();
+    /// let summary = ZipArchiveBuilder::new()
+    ///     .update_of(previous.entries.iter().map(|entry| {
+    ///         (entry.name.clone(), entry.checksum, entry.uncompressed_size)
+    ///     }))
+    ///     .build(&mut update)
+    ///     .add_entry("a.txt", b"a", Level::Default)?
+    ///     .add_entry("b.txt", b"b", Level::Default)?
+    ///     .finish()?;
+    /// assert_eq!(summary.entries.len(), 1);
+    /// # Ok::<(), zip_builder::Error>(())
+    /// ```
+    pub fn update_of<I, S>(mut self, previous: I) -> Self
+    where
+        I: IntoIterator<Item = (S, u32, u64)>,
+        S: Into<String>,
+    {
+        self.config.previous = previous
+            .into_iter()
+            .map(|(name, checksum, size)| (name.into(), (checksum, size)))
+            .collect();
+        self
+    }
+
+    /// Append `MANIFEST.json` listing name, size, CRC-32 and modification time
+    /// of every entry when the archive is finished.
+    ///
+    /// ```json
+    /// {"entries":[{"name":"a.txt","size":1,"crc32":3904355907,"modified":"2020-12-25T14:05:22Z"}]}
+    /// ```
+    #[cfg(feature = "json-manifest")]
+    pub fn json_manifest(mut self) -> Self {
+        self.config.json_manifest = true;
+        self
+    }
+
+    /// Check entries against the rules of `profile`.
+    ///
+    /// Compression and position of entries are checked before they are written, so the
+    /// archive is left as it was on [`Error::NonConforming`](crate::Error::NonConforming).
+    /// Required entries and the order of the central directory are checked in
+    /// [`finish`](ZipArchive::finish).
+    pub fn profile(mut self, profile: Profile) -> Self {
+        self.config.profile = Some(profile);
+        self
+    }
+
+    /// Detect entries whose compressed size divided by uncompressed size is over `threshold`.
+    ///
+    /// They are reported by [`Observer::on_poor_compression`], which helps to find
+    /// data compressed twice. With [`PoorCompression::Store`], they are stored instead.
+    pub fn compression_ratio_threshold(mut self, threshold: f64, action: PoorCompression) -> Self {
+        self.config.ratio_threshold = Some((threshold, action));
+        self
+    }
+
+    /// Detect names which differ from another entry only by case or Unicode normalization.
+    ///
+    /// Extracting them on Windows and macOS overwrites one by the other.
+    /// They are reported by [`Observer::on_case_collision`], and rejected
+    /// with [`CaseCollision::Reject`].
+    pub fn case_collision(mut self, action: CaseCollision) -> Self {
+        self.config.case_collision = Some(action);
+        self
+    }
+
+    /// Limit memory to buffer compressed data of an entry.
+    ///
+    /// Entries larger than `bytes` are compressed directly to the output, and their sizes
+    /// follow the data in data descriptor. [`PoorCompression::Store`] does not apply to them
+    /// because the data is already written when the ratio is known.
+    pub fn memory_budget(mut self, bytes: usize) -> Self {
+        self.config.memory_budget = Some(bytes);
+        self
+    }
+
+    /// Spool compressed data of an entry to a temporary file in `dir` when it exceeds
+    /// `threshold` bytes, and copy it to the output after the local file header.
+    ///
+    /// Memory stays bounded like [`memory_budget`](ZipArchiveBuilder::memory_budget)
+    /// without data descriptor, so this takes precedence over it. Encrypted entries are
+    /// not spooled. The temporary file is removed after the entry is written.
+    pub fn spool<P: Into<PathBuf>>(mut self, threshold: usize, dir: P) -> Self {
+        self.config.spool = Some((threshold, dir.into()));
+        self
+    }
+
+    /// Use Zip64 structures for sizes, offsets and counts from `threshold`.
+    ///
+    /// This is for testing Zip64 handling of extraction pipelines without
+    /// generating 4 GiB of data. Normally Zip64 is used only where required.
+    pub fn zip64_threshold(mut self, threshold: u32) -> Self {
+        self.config.zip64_threshold = threshold as u64;
+        self
+    }
+
+    /// Write the archive after `length` leading bytes of the file, like a self-extracting
+    /// stub or a firmware image, which the caller writes to the output beforehand.
+    ///
+    /// `offsets` chooses whether offsets in the central directory include the leading bytes.
+    /// Stored entries are aligned in the whole file either way. With [`Offsets::Absolute`],
+    /// offsets and the size in [`ArchiveSummary`](crate::ArchiveSummary) are positions
+    /// in the whole file.
+    ///
+    /// ```
+    /// # use zip_builder::{Level, Offsets, ZipArchiveBuilder};
+    /// let stub = b"#!/bin/sh\nexec unzip \"$0\"\n";
+    /// let mut buf = stub.to_vec();
+    /// ZipArchiveBuilder::new()
+    ///     .base_offset(stub.len() as u64, Offsets::Absolute)
+    ///     .build(&mut buf)
+    ///     .add_entry("a.txt", b"a", Level::Default)?
+    ///     .finish()?;
+    /// assert!(buf.starts_with(b"#!/bin/sh"));
+    /// # Ok::<(), zip_builder::Error>(())
+    /// ```
+    pub fn base_offset(mut self, length: u64, offsets: Offsets) -> Self {
+        self.config.base_offset = Some((length, offsets));
+        self
+    }
+
+    /// Limit number of entries.
+    ///
+    /// Adding more entries fails with [`Error::LimitExceeded`](crate::Error::LimitExceeded)
+    /// before anything of the entry is written. Pending lazy entries are counted too.
+    pub fn max_entries(mut self, max: usize) -> Self {
+        self.config.limits.entries = Some(max);
+        self
+    }
+
+    /// Limit total uncompressed size of entries in bytes.
+    ///
+    /// See [`max_entries`](ZipArchiveBuilder::max_entries) for the error.
+    pub fn max_total_size(mut self, max: u64) -> Self {
+        self.config.limits.total_size = Some(max);
+        self
+    }
+
+    /// Limit uncompressed size of each entry in bytes.
+    ///
+    /// See [`max_entries`](ZipArchiveBuilder::max_entries) for the error.
+    pub fn max_entry_size(mut self, max: u64) -> Self {
+        self.config.limits.entry_size = Some(max);
+        self
+    }
+
+    /// Limit length of entry names in bytes, such as 260 for old Windows.
+    ///
+    /// See [`max_entries`](ZipArchiveBuilder::max_entries) for the error. Names longer than
+    /// 65535 bytes always fail with [`Error::NameTooLong`](crate::Error::NameTooLong).
+    pub fn max_name_length(mut self, max: usize) -> Self {
+        self.config.limits.name_length = Some(max);
+        self
+    }
+
+    /// Compress in slices of `quantum` input bytes and call `callback` between them.
+    ///
+    /// For single-threaded schedulers which must not be blocked while a large entry
+    /// is compressed. The callback can yield or poll other work.
+    pub fn time_slice<F: Fn() + Send + Sync + 'static>(
+        mut self,
+        quantum: usize,
+        callback: F,
+    ) -> Self {
+        self.config.time_slice = Some(TimeSlice {
+            quantum: quantum.max(1),
+            callback: Arc::new(callback),
+        });
+        self
+    }
+
+    /// Skip entries added after `deadline`, so that a valid archive of the entries written
+    /// so far can be finished in time.
+    ///
+    /// Skipped entries are not written at all and their names are reported in
+    /// [`ArchiveSummary::skipped`](crate::ArchiveSummary::skipped). Entries already being
+    /// written are completed, [`start_entry`](crate::ZipArchive::start_entry) fails with
+    /// [`Error::DeadlineExceeded`](crate::Error::DeadlineExceeded), and lazy and deferred
+    /// entries are written by [`finish`](crate::ZipArchive::finish) regardless.
+    ///
+    /// ```
+    /// # use zip_builder::{Level, ZipArchiveBuilder};
+    /// # use std::time::Instant;
+    /// let mut zip = Vec::new();
+    /// let summary = ZipArchiveBuilder::new()
+    ///     .deadline(Instant::now())
+    ///     .build(&mut zip)
+    ///     .add_entry("late.txt", b"late", Level::Default)?
+    ///     .finish()?;
+    /// assert_eq!(summary.skipped, ["late.txt"]);
+    /// # Ok::<(), zip_builder::Error>(())
+    /// ```
+    pub fn deadline(mut self, deadline: Instant) -> Self {
+        self.config.deadline = Some(deadline);
+        self
+    }
+
+    /// Choose the level of each entry whose options set no level by `policy`.
+    ///
+    /// Entries matching no rule use the level of the archive defaults.
+    /// [`sniff`](ZipArchiveBuilder::sniff) still takes precedence.
+    ///
+    /// ```
+    /// # use zip_builder::{CompressionPolicy, EntryOptions, Level, ZipArchiveBuilder};
+    /// let mut zip = Vec::new();
+    /// ZipArchiveBuilder::new()
+    ///     .level(Level::High)
+    ///     .compression_policy(CompressionPolicy::new().rule("*.png", Level::Raw))
+    ///     .build(&mut zip)
+    ///     .add_entry("image.png", b"\x89PNG\r\n\x1a\n", EntryOptions::default())?
+    ///     .finish()?;
+    /// # Ok::<(), zip_builder::Error>(())
+    /// ```
+    pub fn compression_policy(mut self, policy: CompressionPolicy) -> Self {
+        self.config.compression_policy = Some(policy);
+        self
+    }
+
+    /// Choose the level of each entry with content by `sniffer` from its name and
+    /// leading bytes (up to 512 bytes), instead of the level of its options.
+    ///
+    /// For policies looking at the content, such as storing encrypted blobs.
+    ///
+    /// ```
+    /// # use zip_builder::{Level, ZipArchiveBuilder};
+    /// let mut zip = Vec::new();
+    /// ZipArchiveBuilder::new()
+    ///     .sniff(|_name, bytes| {
+    ///         if bytes.starts_with(b"\x89PNG") {
+    ///             Level::Raw
+    ///         } else {
+    ///             Level::Default
+    ///         }
+    ///     })
+    ///     .build(&mut zip)
+    ///     .add_entry("image.png", b"\x89PNG\r\n\x1a\n", Level::High)?
+    ///     .finish()?;
+    /// # Ok::<(), zip_builder::Error>(())
+    /// ```
+    pub fn sniff<F: Fn(&str, &[u8]) -> Level + Send + Sync + 'static>(
+        mut self,
+        sniffer: F,
+    ) -> Self {
+        self.config.sniffer = Some(Arc::new(sniffer));
+        self
+    }
+
+    /// Set the observer called at the end of each entry and the archive.
+    pub fn observer<O: Observer + Send + Sync + 'static>(mut self, observer: O) -> Self {
+        self.config.observer = Some(Arc::new(observer));
+        self
+    }
+
+    /// Store hard links found by [`ZipArchive::add_dir_recursive`] once.
+    ///
+    /// Later paths of the same file are listed in the central directory
+    /// pointing at the data of the first one, like [`deduplicate`](ZipArchiveBuilder::deduplicate)
+    /// but without reading the file again. Only on Unix.
+    pub fn share_hard_links(mut self) -> Self {
+        self.config.share_hard_links = true;
+        self
+    }
+
+    /// Set password of entries encrypted by [`EntryOptions::encrypt`].
+    ///
+    /// With feature `zeroize`, the copy of the password is wiped when the options and
+    /// archives having it are dropped, and derived keys are wiped after each entry.
+    #[cfg(feature = "aes")]
+    pub fn password(mut self, password: &str) -> Self {
+        self.config.password = Some(password.to_string().into());
+        self
+    }
+
+    /// Derive keys of encrypted entries by `derivation` instead of
+    /// [`Pbkdf2`](crate::Pbkdf2) of 1000 iterations, such as more iterations
+    /// required by a deployment.
+    ///
+    /// See [`KeyDerivation`] for compatibility with readers.
+    #[cfg(feature = "aes")]
+    pub fn key_derivation<K: KeyDerivation + Send + Sync + 'static>(
+        mut self,
+        derivation: K,
+    ) -> Self {
+        self.config.key_derivation = Some(Arc::new(derivation));
+        self
+    }
+
+    /// Generate salts of encrypted entries by `random` instead of
+    /// [`OsRandom`](crate::OsRandom).
+    ///
+    /// See [`RandomSource`] for the requirement.
+    #[cfg(feature = "aes")]
+    pub fn random_source<R: RandomSource + Send + Sync + 'static>(mut self, random: R) -> Self {
+        self.config.random_source = Some(Arc::new(random));
+        self
+    }
+
+    pub(crate) fn from_config(config: Config) -> ZipArchiveBuilder {
+        ZipArchiveBuilder { config }
+    }
+
+    /// Create a new [`ZipArchive`] with the options.
+    pub fn build<'a, T: Write + ?Sized + 'a>(&self, output: &'a mut T) -> ZipArchive<'a, T> {
+        ZipArchive::with_config(output, self.config.clone())
+    }
+
+    /// Create a [`ZipArchive`] which continues from `checkpoint`.
+    ///
+    /// `output` must contain exactly the first [`Checkpoint::offset`] bytes of the archive,
+    /// positioned at the end. Use the same options as the archive of the checkpoint.
+    pub fn resume<'a, T: Write + ?Sized + 'a>(
+        &self,
+        output: &'a mut T,
+        checkpoint: Checkpoint,
+    ) -> ZipArchive<'a, T> {
+        ZipArchive::with_config(output, self.config.clone()).restore(checkpoint)
+    }
+
+    /// Build an archive by `build` with the options, compressing everything but
+    /// discarding the output, and return the summary of the archive.
+    ///
+    /// This predicts the size of the archive and compressed sizes of entries
+    /// before writing it to a slow output.
+    ///
+    /// ```
+    /// # use zip_builder::{Level, ZipArchive, ZipArchiveBuilder};
+    /// fn build<T: std::io::Write>(zip: ZipArchive<T>) -> zip_builder::Result<ZipArchive<T>> {
+    ///     zip.add_entry("a.txt", &[b'a'; 1000], Level::High)
+    /// }
+    ///
+    /// let builder = ZipArchiveBuilder::new();
+    /// let estimate = builder.dry_run(build)?;
+    /// let mut buf = Vec::new();
+    /// build(builder.build(&mut buf))?.finish()?;
+    /// assert_eq!(estimate.size, buf.len() as u64);
+    /// # Ok::<(), zip_builder::Error>(())
+    /// ```
+    pub fn dry_run<F>(&self, build: F) -> Result<ArchiveSummary>
+    where
+        F: FnOnce(ZipArchive<io::Sink>) -> Result<ZipArchive<io::Sink>>,
+    {
+        let mut sink = io::sink();
+        let zip = build(self.build(&mut sink))?;
+        zip.finish()
+    }
+
+    /// Build an archive by `build` with the options into a temporary file in the directory
+    /// of `path`, and rename it to `path` after the archive is finished.
+    ///
+    /// So `path` never has a truncated archive. The temporary file is removed
+    /// if `build` or finishing fails, and `path` is left as it was.
+    /// With [`durable`](ZipArchiveBuilder::durable), the file is synced before it is renamed
+    /// and the directory is synced after it on Unix, so the archive survives power loss.
+    ///
+    /// ```
+    /// # use zip_builder::{Level, ZipArchiveBuilder};
+    /// let path = std::env::temp_dir().join("zip-builder-atomic-doc.zip");
+    /// ZipArchiveBuilder::new().create_atomic(&path, |zip| {
+    ///     zip.add_entry("a.txt", b"a", Level::Default)
+    /// })?;
+    /// # std::fs::remove_file(path)?;
+    /// # Ok::<(), zip_builder::Error>(())
+    /// ```
+    pub fn create_atomic<P, F>(&self, path: P, build: F) -> Result<ArchiveSummary>
+    where
+        P: AsRef<Path>,
+        F: FnOnce(ZipArchive<File>) -> Result<ZipArchive<File>>,
+    {
+        let path = path.as_ref();
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let mut temp = TempFile::create(dir)?;
+        let summary = build(self.build(&mut temp.file))?.finish()?;
+        if self.config.durable {
+            temp.file.sync_all()?;
+        }
+        temp.persist(path)?;
+        #[cfg(unix)]
+        if self.config.durable {
+            File::open(dir)?.sync_all()?;
+        }
+        Ok(summary)
+    }
+
+    /// Sync the output to the storage device when the archive is finished by
+    /// [`create_atomic`](ZipArchiveBuilder::create_atomic), for transactional jobs.
+    ///
+    /// Archives written to a [`File`] directly are synced by
+    /// [`finish_durable`](ZipArchive::finish_durable) instead.
+    pub fn durable(mut self) -> Self {
+        self.config.durable = true;
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{DirectoryOrder, Offsets, ZipArchiveBuilder, ZipConfig};
+    use crate::{verify, EntryOptions, Error, Level, Limit, Unicode, ZipArchive};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn it_works() {
+        let mut buf = Vec::new();
+        let zip = ZipArchiveBuilder::new()
+            .level(Level::Raw)
+            .modified(UNIX_EPOCH + Duration::from_secs(1608905123))
+            .unicode(Unicode::Auto)
+            .alignment(4)
+            .comment("comment")
+            .build(&mut buf)
+            .add_entry("a", b"aligned", EntryOptions::default())
+            .unwrap()
+            .add_entry("b", &[b'b'; 100], Level::High)
+            .unwrap()
+            .add_entry("ｃ", b"unicode", EntryOptions::default().alignment(1))
+            .unwrap();
+        let offsets: Vec<_> = zip.entries().map(|entry| entry.offset).collect();
+        zip.finish().unwrap();
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+        // Data of "a" starts after header, name and extra field padded by 3 bytes.
+        assert_eq!(&buf[28..30], &9u16.to_le_bytes());
+        assert_eq!(&buf[40..47], b"aligned");
+        // Compressed entry is not aligned.
+        let b = offsets[1] as usize;
+        assert_eq!(&buf[b + 28..b + 30], &[0, 0]);
+        // Flags and timestamp.
+        assert_eq!(&buf[6..8], &[0, 0]);
+        assert_eq!(&buf[10..14], &1369010347u32.to_le_bytes());
+        let c = offsets[2] as usize;
+        assert_eq!(&buf[c + 6..c + 8], &2048u16.to_le_bytes());
+        assert_eq!(&buf[c + 28..c + 30], &[0, 0]);
+        assert!(buf.ends_with(b"\x07\x00comment"));
+    }
+
+    #[test]
+    fn order() {
+        let names = |order| {
+            let mut buf = Vec::new();
+            let summary = ZipArchiveBuilder::new()
+                .order(order)
+                .build(&mut buf)
+                .add_entry("b", b"bb", Level::Raw)
+                .unwrap()
+                .add_entry("c", b"c", Level::Raw)
+                .unwrap()
+                .add_entry("a", b"aaa", Level::Raw)
+                .unwrap()
+                .finish()
+                .unwrap();
+            assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+            summary
+                .entries
+                .into_iter()
+                .map(|entry| entry.name)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(DirectoryOrder::Insertion), ["b", "c", "a"]);
+        assert_eq!(names(DirectoryOrder::Name), ["a", "b", "c"]);
+        assert_eq!(
+            names(DirectoryOrder::Custom(|a, b| {
+                a.uncompressed_size.cmp(&b.uncompressed_size)
+            })),
+            ["c", "b", "a"]
+        );
+    }
+
+    #[test]
+    fn zip64_threshold() {
+        let mut buf = Vec::new();
+        ZipArchiveBuilder::new()
+            .zip64_threshold(1)
+            .build(&mut buf)
+            .add_entry("raw.txt", b"raw content", Level::Raw)
+            .unwrap()
+            .add_entry("deflate.txt", &[b'a'; 1000], Level::High)
+            .unwrap()
+            .finish()
+            .unwrap();
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+        let signature = |sig: u32| buf.windows(4).any(|w| w == sig.to_le_bytes());
+        assert!(signature(0x06064b50));
+        assert!(signature(0x07064b50));
+    }
+
+    #[test]
+    fn alignment_boundary() {
+        // Data of a name of `length` bytes starts at 30 + `length` without padding.
+        let add = |length: usize| {
+            let mut buf = Vec::new();
+            ZipArchiveBuilder::new()
+                .alignment(65535)
+                .build(&mut buf)
+                .add_entry(&"a".repeat(length), b"aligned", Level::Raw)?
+                .finish()?;
+            Ok::<_, Error>(buf)
+        };
+        // The largest padding of 65529 bytes fills the extra field.
+        let buf = add(65505).unwrap();
+        assert_eq!(buf[28..30], 65535u16.to_le_bytes());
+        assert_eq!(&buf[2 * 65535..2 * 65535 + 7], b"aligned");
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+        assert!(matches!(
+            add(65500),
+            Err(Error::ExtraFieldTooLong { length: 65540, .. })
+        ));
+    }
+
+    #[test]
+    fn base_offset() {
+        for offsets in [Offsets::Absolute, Offsets::Relative] {
+            let mut buf = b"stub!".to_vec();
+            let summary = ZipArchiveBuilder::new()
+                .base_offset(5, offsets)
+                .alignment(4)
+                .build(&mut buf)
+                .add_entry("a", b"aligned", Level::Raw)
+                .unwrap()
+                .finish()
+                .unwrap();
+            let data = buf.windows(7).position(|w| w == b"aligned").unwrap();
+            assert_eq!(data % 4, 0);
+            match offsets {
+                Offsets::Absolute => {
+                    assert_eq!(summary.entries[0].offset, 5);
+                    assert_eq!(summary.size, buf.len() as u64);
+                    assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+                }
+                Offsets::Relative => {
+                    assert_eq!(summary.entries[0].offset, 0);
+                    assert_eq!(summary.size, buf.len() as u64 - 5);
+                    assert_eq!(verify(&buf[5..]).unwrap(), vec![]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn limits() {
+        let limit = |builder: ZipArchiveBuilder| {
+            let mut buf = Vec::new();
+            let zip = builder
+                .build(&mut buf)
+                .add_entry("a", b"aaa", Level::Raw)
+                .unwrap()
+                .add_entry("b", b"bb", Level::Raw)
+                .unwrap();
+            let offset = zip.offset as usize;
+            let limit = match zip.add_entry("c", b"cccc", Level::Raw) {
+                Err(Error::LimitExceeded { name, limit }) => {
+                    assert_eq!(name, "c");
+                    limit
+                }
+                _ => panic!("limit is not exceeded"),
+            };
+            assert_eq!(buf.len(), offset);
+            limit
+        };
+        let builder = ZipArchiveBuilder::new();
+        assert_eq!(limit(builder.clone().max_entries(2)), Limit::Entries(2));
+        assert_eq!(
+            limit(builder.clone().max_entry_size(3)),
+            Limit::EntrySize(3)
+        );
+        assert_eq!(limit(builder.max_total_size(8)), Limit::TotalSize(8));
+
+        let mut buf = Vec::new();
+        let mut zip = ZipArchiveBuilder::new().max_name_length(3).build(&mut buf);
+        zip.push_entry("abc", b"", Level::Raw).unwrap();
+        assert!(matches!(
+            zip.push_entry("abcd", b"", Level::Raw),
+            Err(Error::LimitExceeded { name, limit: Limit::NameLength(3) }) if name == "abcd"
+        ));
+        let long = "a".repeat(65536);
+        assert!(matches!(
+            zip.push_entry(&long, b"", Level::Raw),
+            Err(Error::NameTooLong { length: 65536, .. })
+        ));
+        zip.finish().unwrap();
+        let result = ZipArchiveBuilder::new()
+            .comment(&long)
+            .build(&mut Vec::new())
+            .finish();
+        assert!(matches!(
+            result,
+            Err(Error::CommentTooLong { length: 65536 })
+        ));
+
+        let mut buf = Vec::new();
+        ZipArchiveBuilder::new()
+            .max_entries(1)
+            .max_total_size(3)
+            .build(&mut buf)
+            .add_entry("a", b"aa", Level::Raw)
+            .unwrap()
+            .replace_entry("a", b"aaa", Level::Raw)
+            .unwrap()
+            .finish()
+            .unwrap();
+    }
+
+    #[test]
+    fn time_slice() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+        let yields = Arc::new(AtomicUsize::new(0));
+        let counter = yields.clone();
+        let mut buf = Vec::new();
+        ZipArchiveBuilder::new()
+            .time_slice(1000, move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+            })
+            .build(&mut buf)
+            .add_entry("large.txt", &[b'a'; 10000], Level::High)
+            .unwrap()
+            .finish()
+            .unwrap();
+        assert_eq!(yields.load(Ordering::SeqCst), 9);
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn sniff() {
+        use std::sync::{Arc, Mutex};
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let recorder = calls.clone();
+        let mut buf = Vec::new();
+        let summary = ZipArchiveBuilder::new()
+            .sniff(move |name, bytes| {
+                recorder
+                    .lock()
+                    .unwrap()
+                    .push((name.to_string(), bytes.len()));
+                if bytes.starts_with(b"SEALED") {
+                    Level::Raw
+                } else {
+                    Level::High
+                }
+            })
+            .build(&mut buf)
+            .add_entry("blob.bin", &b"SEALED".repeat(200), Level::High)
+            .unwrap()
+            .add_entry("text.txt", &[b'a'; 1000], Level::Raw)
+            .unwrap()
+            .add_entry("empty.txt", b"", Level::Raw)
+            .unwrap()
+            .finish()
+            .unwrap();
+        assert_eq!(
+            *calls.lock().unwrap(),
+            [("blob.bin".to_string(), 512), ("text.txt".to_string(), 512)]
+        );
+        assert_eq!(summary.entries[0].compressed_size, 1200);
+        assert!(summary.entries[1].compressed_size < 1000);
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn shared_config() {
+        use std::sync::Arc;
+        let config: Arc<ZipConfig> = Arc::new(ZipConfig::new().level(Level::Raw).comment("shared"));
+        let archives: Vec<Vec<u8>> = (0..4)
+            .map(|index| {
+                let config = config.clone();
+                std::thread::spawn(move || {
+                    let mut buf = Vec::new();
+                    config
+                        .build(&mut buf)
+                        .add_entry(
+                            &format!("{}.txt", index),
+                            b"content",
+                            EntryOptions::default(),
+                        )
+                        .unwrap()
+                        .finish()
+                        .unwrap();
+                    buf
+                })
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect();
+        for buf in archives {
+            assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+            assert!(buf.ends_with(b"shared"));
+            assert_eq!(&buf[8..10], &0u16.to_le_bytes());
+        }
+
+        let mut buf = Vec::new();
+        let zip = ZipArchive::new(&mut buf).deduplicate();
+        let snapshot = zip.config().comment("snapshot");
+        drop(zip);
+        let mut other = Vec::new();
+        snapshot
+            .build(&mut other)
+            .add_entry("a", b"same", Level::Raw)
+            .unwrap()
+            .add_entry("b", b"same", Level::Raw)
+            .unwrap()
+            .finish()
+            .unwrap();
+        assert_eq!(other.windows(4).filter(|w| w == b"same").count(), 1);
+    }
+}
@@ -0,0 +1,166 @@
+use crate::raw::{
+    CentralDirectoryHeader, EndOfCentralDirectory, Zip64EndOfCentralDirectory,
+    Zip64EndOfCentralDirectoryLocator,
+};
+use crate::{advance, zip64_field, Error, Result};
+use std::io::Write;
+
+/// Writer of the central directory and the end records over entry data already written.
+///
+/// [`ZipArchive`](crate::ZipArchive) finishes archives with it, and tools rebuilding
+/// only the directory of existing entries can use it alone. Zip64 end records are written
+/// when the count, the size or the offset of the directory requires them.
+///
+/// ```rust
+/// use zip_builder::raw::{CentralDirectoryHeader, LocalFileHeader};
+/// use zip_builder::CentralDirectoryWriter;
+///
+/// let mut buf = Vec::new();
+/// let local = LocalFileHeader {
+///     version_needed: 10,
+///     flags: 0,
+///     method: 0,
+///     timestamp: 0x519970AB,
+///     crc32: 0xADF3_F363,
+///     compressed_size: 4,
+///     uncompressed_size: 4,
+///     filename: b"a.txt",
+///     extra: &[],
+/// };
+/// local.write_to(&mut buf)?;
+/// buf.extend_from_slice(b"data");
+/// let top = buf.len() as u64;
+/// let mut directory = CentralDirectoryWriter::new(&mut buf, top);
+/// directory.add(&CentralDirectoryHeader::from_local(&local, 0))?;
+/// let size = directory.finish(b"")?;
+/// assert_eq!(size, buf.len() as u64);
+/// # Ok::<(), zip_builder::Error>(())
+/// ```
+pub struct CentralDirectoryWriter<'w, W: Write> {
+    output: &'w mut W,
+    top: u64,
+    size: u64,
+    count: u64,
+    pub(crate) threshold: u64,
+}
+
+impl<'w, W: Write> CentralDirectoryWriter<'w, W> {
+    /// Start the central directory at offset `top` of the archive, where `output` is.
+    pub fn new(output: &'w mut W, top: u64) -> Self {
+        CentralDirectoryWriter {
+            output,
+            top,
+            size: 0,
+            count: 0,
+            threshold: u32::MAX as u64,
+        }
+    }
+
+    /// Use Zip64 end records from `threshold`,
+    /// like [`ZipArchiveBuilder::zip64_threshold`](crate::ZipArchiveBuilder::zip64_threshold).
+    pub fn zip64_threshold(mut self, threshold: u32) -> Self {
+        self.threshold = threshold as u64;
+        self
+    }
+
+    /// Write the header of the next entry.
+    ///
+    /// Zip64 extended information of the header is the responsibility of the caller.
+    pub fn add(&mut self, header: &CentralDirectoryHeader) -> Result<()> {
+        header.write_to(self.output)?;
+        advance(&mut self.size, header.size() as u64)?;
+        self.count += 1;
+        Ok(())
+    }
+
+    /// Size of the headers written so far.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Write the end records with `comment`, and return the size of the archive.
+    pub fn finish(mut self, comment: &[u8]) -> Result<u64> {
+        if comment.len() > u16::MAX as usize {
+            return Err(Error::CommentTooLong {
+                length: comment.len(),
+            });
+        }
+        let (mut record, offset) = self.end()?;
+        record.comment = comment;
+        record.write_to(self.output)?;
+        let mut size = offset;
+        advance(&mut size, record.size() as u64)?;
+        Ok(size)
+    }
+
+    /// Write Zip64 end records if required, and return the end of central directory record
+    /// without comment and its offset.
+    pub(crate) fn end(&mut self) -> Result<(EndOfCentralDirectory<'static>, u64)> {
+        let threshold = self.threshold;
+        let mut offset = self.top;
+        advance(&mut offset, self.size)?;
+        let count_threshold = threshold.min(u16::MAX as u64);
+        let count = if self.count >= count_threshold {
+            u16::MAX
+        } else {
+            self.count as u16
+        };
+        if count == u16::MAX || self.size >= threshold || self.top >= threshold {
+            let record = Zip64EndOfCentralDirectory {
+                version_made_by: 45,
+                version_needed: 45,
+                entries: self.count,
+                central_directory_size: self.size,
+                central_directory_offset: self.top,
+            };
+            let locator = Zip64EndOfCentralDirectoryLocator { offset };
+            record.write_to(self.output)?;
+            locator.write_to(self.output)?;
+            advance(&mut offset, (record.size() + locator.size()) as u64)?;
+        }
+        let record = EndOfCentralDirectory {
+            entries: count,
+            central_directory_size: zip64_field(self.size, threshold),
+            central_directory_offset: zip64_field(self.top, threshold),
+            comment: &[],
+        };
+        Ok((record, offset))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::CentralDirectoryWriter;
+    use crate::raw::EndOfCentralDirectory;
+    use crate::{verify, Error, Level, ZipArchive};
+
+    #[test]
+    fn it_works() {
+        let mut buf = Vec::new();
+        let summary = ZipArchive::new(&mut buf)
+            .add_entry("a.txt", b"a", Level::Raw)
+            .unwrap()
+            .finish()
+            .unwrap();
+        // Rebuild the directory over the entry data with a comment and Zip64 end records.
+        let top = summary.size - summary.central_directory_size - 22;
+        let directory = buf[top as usize..(summary.size - 22) as usize].to_vec();
+        let header = crate::raw::CentralDirectoryHeader::from_bytes(&directory).unwrap();
+        buf.truncate(top as usize);
+        let mut writer = CentralDirectoryWriter::new(&mut buf, top).zip64_threshold(1);
+        writer.add(&header).unwrap();
+        assert_eq!(writer.size(), summary.central_directory_size);
+        let size = writer.finish(b"rebuilt").unwrap();
+        assert_eq!(size, buf.len() as u64);
+        let end = EndOfCentralDirectory::from_bytes(&buf[buf.len() - 29..]).unwrap();
+        assert_eq!(end.entries, u16::MAX);
+        assert_eq!(end.comment, b"rebuilt");
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+
+        let writer = CentralDirectoryWriter::new(&mut buf, 0);
+        assert!(matches!(
+            writer.finish(&[0; 65536]),
+            Err(Error::CommentTooLong { length: 65536 })
+        ));
+    }
+}
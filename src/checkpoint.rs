@@ -0,0 +1,252 @@
+use crate::{Error, Result, ZipArchive, ZipEntry};
+use std::collections::HashMap;
+use std::io::Write;
+
+const MAGIC: &[u8; 4] = b"ZBCP";
+const VERSION: u8 = 3;
+
+/// Internal state of an unfinished archive, to resume building after restart.
+///
+/// It records the entries written so far and the offset where the next one starts.
+/// Truncate the partially-written output to [`offset`](Checkpoint::offset), then continue
+/// with [`ZipArchiveBuilder::resume`](crate::ZipArchiveBuilder::resume) and the same options.
+/// Lazy entries which are not generated yet are not recorded.
+#[derive(Clone)]
+pub struct Checkpoint {
+    pub(crate) entries: Vec<ZipEntry>,
+    pub(crate) offset: u64,
+    pub(crate) digests: Vec<([u8; 32], usize)>,
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.bytes.len() < len {
+            return Err(Error::InvalidCheckpoint);
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        let b = self.bytes(2)?;
+        Ok(u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let b = self.bytes(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        let mut b = [0; 8];
+        b.copy_from_slice(self.bytes(8)?);
+        Ok(u64::from_le_bytes(b))
+    }
+
+    fn digest(&mut self) -> Result<[u8; 32]> {
+        let mut digest = [0; 32];
+        digest.copy_from_slice(self.bytes(32)?);
+        Ok(digest)
+    }
+}
+
+impl Checkpoint {
+    /// Length of the output which belongs to the checkpoint.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Serialize the checkpoint.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&self.offset.to_le_bytes());
+        bytes.extend_from_slice(&(self.entries.len() as u32).to_le_bytes());
+        for entry in self.entries.iter() {
+            bytes.extend_from_slice(&entry.flags.to_le_bytes());
+            bytes.extend_from_slice(&entry.method.to_le_bytes());
+            bytes.extend_from_slice(&entry.timestamp.to_le_bytes());
+            bytes.extend_from_slice(&entry.checksum.to_le_bytes());
+            bytes.extend_from_slice(&entry.compressed_size.to_le_bytes());
+            bytes.extend_from_slice(&entry.uncompressed_size.to_le_bytes());
+            bytes.extend_from_slice(&entry.offset.to_le_bytes());
+            bytes.extend_from_slice(&entry.data_offset.to_le_bytes());
+            bytes.extend_from_slice(&(entry.name_bytes().len() as u16).to_le_bytes());
+            bytes.extend_from_slice(entry.name_bytes());
+            bytes.extend_from_slice(&entry.external_attributes.to_le_bytes());
+            bytes.push(entry.host);
+            bytes.push(entry.zip64 as u8);
+            bytes.extend_from_slice(&(entry.extra.len() as u16).to_le_bytes());
+            bytes.extend_from_slice(&entry.extra);
+            match entry.sha256 {
+                Some(digest) => {
+                    bytes.push(1);
+                    bytes.extend_from_slice(&digest);
+                }
+                None => bytes.push(0),
+            }
+        }
+        bytes.extend_from_slice(&(self.digests.len() as u32).to_le_bytes());
+        for (digest, index) in self.digests.iter() {
+            bytes.extend_from_slice(digest);
+            bytes.extend_from_slice(&(*index as u32).to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Deserialize a checkpoint made by [`to_bytes`](Checkpoint::to_bytes).
+    pub fn from_bytes(bytes: &[u8]) -> Result<Checkpoint> {
+        let mut reader = Reader { bytes };
+        if reader.bytes(4)? != MAGIC || reader.u8()? != VERSION {
+            return Err(Error::InvalidCheckpoint);
+        }
+        let offset = reader.u64()?;
+        let count = reader.u32()? as usize;
+        let mut entries = Vec::with_capacity(count.min(bytes.len()));
+        for _ in 0..count {
+            let flags = reader.u16()?;
+            let method = reader.u16()?;
+            let timestamp = reader.u32()?;
+            let checksum = reader.u32()?;
+            let compressed_size = reader.u64()?;
+            let uncompressed_size = reader.u64()?;
+            let entry_offset = reader.u64()?;
+            let data_offset = reader.u64()?;
+            let length = reader.u16()? as usize;
+            let name = reader.bytes(length)?;
+            let (filename, raw_name) = match std::str::from_utf8(name) {
+                Ok(filename) => (filename.to_string(), None),
+                Err(_) => (
+                    String::from_utf8_lossy(name).into_owned(),
+                    Some(name.to_vec()),
+                ),
+            };
+            let external_attributes = reader.u32()?;
+            let host = reader.u8()?;
+            let zip64 = reader.u8()? != 0;
+            let length = reader.u16()? as usize;
+            let extra = reader.bytes(length)?.to_vec();
+            let sha256 = match reader.u8()? {
+                0 => None,
+                _ => Some(reader.digest()?),
+            };
+            entries.push(ZipEntry {
+                flags,
+                method,
+                timestamp,
+                checksum,
+                compressed_size,
+                uncompressed_size,
+                offset: entry_offset,
+                data_offset,
+                filename,
+                raw_name,
+                sha256,
+                external_attributes,
+                extra,
+                host,
+                zip64,
+            });
+        }
+        let count = reader.u32()? as usize;
+        let mut digests = Vec::with_capacity(count.min(bytes.len()));
+        for _ in 0..count {
+            let digest = reader.digest()?;
+            let index = reader.u32()? as usize;
+            if index >= entries.len() {
+                return Err(Error::InvalidCheckpoint);
+            }
+            digests.push((digest, index));
+        }
+        if !reader.bytes.is_empty() {
+            return Err(Error::InvalidCheckpoint);
+        }
+        Ok(Checkpoint {
+            entries,
+            offset,
+            digests,
+        })
+    }
+}
+
+impl<'a, T: Write + ?Sized + 'a> ZipArchive<'a, T> {
+    /// Record the state to resume building later.
+    ///
+    /// The output should be flushed before the checkpoint is stored.
+    pub fn checkpoint(&self) -> Result<Checkpoint> {
+        self.check_poisoned()?;
+        Ok(Checkpoint {
+            entries: self.entries.clone(),
+            offset: self.offset,
+            digests: self
+                .digests
+                .iter()
+                .map(|(digest, index)| (*digest, *index))
+                .collect(),
+        })
+    }
+
+    pub(crate) fn restore(mut self, checkpoint: Checkpoint) -> Self {
+        self.total_size = checkpoint
+            .entries
+            .iter()
+            .map(|entry| entry.uncompressed_size)
+            .sum();
+        self.entries = checkpoint.entries;
+        for index in 0..self.entries.len() {
+            let name = self.entries[index].filename.clone();
+            let prefix = &self.config.root_prefix;
+            self.record_case(name.strip_prefix(prefix.as_str()).unwrap_or(&name));
+        }
+        self.offset = checkpoint.offset;
+        self.digests = checkpoint.digests.into_iter().collect::<HashMap<_, _>>();
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Checkpoint;
+    use crate::{verify, Error, Level, ZipArchive, ZipArchiveBuilder};
+
+    #[test]
+    fn it_works() {
+        let mut first = Vec::new();
+        let mut zip = ZipArchive::new(&mut first).deduplicate();
+        zip.push_entry("a.txt", b"same content", Level::Default)
+            .unwrap();
+        zip.push_entry("b.txt", &[b'b'; 100], Level::High).unwrap();
+        let bytes = zip.checkpoint().unwrap().to_bytes();
+        zip.push_entry("lost.txt", b"lost", Level::Raw).unwrap();
+        std::mem::forget(zip);
+
+        let checkpoint = Checkpoint::from_bytes(&bytes).unwrap();
+        let mut resumed = first[..checkpoint.offset() as usize].to_vec();
+        let summary = ZipArchiveBuilder::new()
+            .deduplicate()
+            .resume(&mut resumed, checkpoint)
+            .add_entry("c.txt", b"same content", Level::Default)
+            .unwrap()
+            .finish()
+            .unwrap();
+        let names: Vec<_> = summary.entries.iter().map(|entry| &entry.name).collect();
+        assert_eq!(names, ["a.txt", "b.txt", "c.txt"]);
+        assert_eq!(summary.entries[0].offset, summary.entries[2].offset);
+        assert_eq!(summary.size as usize, resumed.len());
+        assert_eq!(verify(resumed.as_slice()).unwrap(), vec![]);
+
+        assert!(matches!(
+            Checkpoint::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(Error::InvalidCheckpoint)
+        ));
+    }
+}
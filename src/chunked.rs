@@ -0,0 +1,122 @@
+use std::io::{Result, Write};
+
+/// Writer which buffers output into parts of fixed size.
+///
+/// Each completed part is handed to the callback when the next byte is written, which suits
+/// multipart upload of S3 or GCS where an archive never touches disk.
+/// The last part, which may be shorter, is handed by [`finish`](ChunkedOutput::finish).
+///
+/// ```rust
+/// use zip_builder::{ChunkedOutput, Level, ZipArchive};
+///
+/// let mut parts = Vec::new();
+/// // S3 requires parts of at least 5 MiB except the last one.
+/// let mut output = ChunkedOutput::new(8 << 20, |part: &[u8]| {
+///     // Call UploadPart here instead.
+///     parts.push(part.to_vec());
+///     Ok(())
+/// });
+/// ZipArchive::new(&mut output)
+///     .add_entry("file.txt", b"content", Level::Default)?
+///     .finish()?;
+/// output.finish()?;
+/// // Call CompleteMultipartUpload here.
+/// assert_eq!(parts.len(), 1);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct ChunkedOutput<F: FnMut(&[u8]) -> Result<()>> {
+    part_size: usize,
+    buffer: Vec<u8>,
+    callback: F,
+    parts: usize,
+}
+
+impl<F: FnMut(&[u8]) -> Result<()>> ChunkedOutput<F> {
+    /// Create a writer handing parts of `part_size` bytes to `callback`.
+    pub fn new(part_size: usize, callback: F) -> ChunkedOutput<F> {
+        assert!(part_size > 0, "part size must be positive");
+        ChunkedOutput {
+            part_size,
+            buffer: Vec::with_capacity(part_size),
+            callback,
+            parts: 0,
+        }
+    }
+
+    /// Number of parts handed so far.
+    pub fn parts(&self) -> usize {
+        self.parts
+    }
+
+    fn hand(&mut self) -> Result<()> {
+        (self.callback)(&self.buffer)?;
+        self.buffer.clear();
+        self.parts += 1;
+        Ok(())
+    }
+
+    /// Hand the remaining data as the last part and return the number of parts.
+    ///
+    /// If no data has been written at all, an empty part is handed.
+    pub fn finish(mut self) -> Result<usize> {
+        if !self.buffer.is_empty() || self.parts == 0 {
+            self.hand()?;
+        }
+        Ok(self.parts)
+    }
+}
+
+impl<F: FnMut(&[u8]) -> Result<()>> Write for ChunkedOutput<F> {
+    /// A completed part is handed before accepting more bytes, so a failing callback
+    /// fails the write without consuming any byte and the write can be retried.
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        if self.buffer.len() == self.part_size {
+            self.hand()?;
+        }
+        let len = buf.len().min(self.part_size - self.buffer.len());
+        self.buffer.extend_from_slice(&buf[..len]);
+        Ok(len)
+    }
+
+    /// Parts are handed only when completed, so this does nothing.
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ChunkedOutput;
+    use std::io::{ErrorKind, Write};
+
+    #[test]
+    fn it_works() {
+        let mut parts = Vec::new();
+        let mut output = ChunkedOutput::new(4, |part: &[u8]| {
+            parts.push(part.to_vec());
+            Ok(())
+        });
+        output.write_all(b"abcdefghij").unwrap();
+        assert_eq!(output.parts(), 2);
+        assert_eq!(output.finish().unwrap(), 3);
+        assert_eq!(parts, [&b"abcd"[..], b"efgh", b"ij"]);
+    }
+
+    #[test]
+    fn retry() {
+        let mut parts = Vec::new();
+        let mut failed = false;
+        let mut output = ChunkedOutput::new(4, |part: &[u8]| {
+            if !failed {
+                failed = true;
+                return Err(ErrorKind::Interrupted.into());
+            }
+            parts.push(part.to_vec());
+            Ok(())
+        });
+        // `write_all` retries writes which fail with `Interrupted`.
+        output.write_all(b"abcdefghij").unwrap();
+        output.finish().unwrap();
+        assert_eq!(parts, [&b"abcd"[..], b"efgh", b"ij"]);
+    }
+}
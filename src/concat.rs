@@ -0,0 +1,89 @@
+use crate::{EntryOptions, Error, Metadata, Result, Stage, ZipArchive};
+use std::io::{Read, Write};
+
+impl<'a, T: Write + ?Sized + 'a> ZipArchive<'a, T> {
+    /// Add a entry whose content is `sources` one after another, like a combined log.
+    ///
+    /// Sources are slices or readers, which are read in order into the buffer of the entry,
+    /// so callers need not concatenate them first. If reading a source fails, the error is
+    /// returned before anything of the entry is written.
+    ///
+    /// ```rust
+    /// use zip_builder::{Level, ZipArchive};
+    ///
+    /// let logs: [&[u8]; 2] = [b"first\n", b"second\n"];
+    /// let mut buf = Vec::new();
+    /// ZipArchive::new(&mut buf)
+    ///     .add_entry_concat("combined.log", logs.iter().copied(), Level::Default)?
+    ///     .finish()?;
+    /// # Ok::<(), zip_builder::Error>(())
+    /// ```
+    pub fn add_entry_concat<I, R, O>(mut self, name: &str, sources: I, options: O) -> Result<Self>
+    where
+        I: IntoIterator<Item = R>,
+        R: Read,
+        O: Into<EntryOptions>,
+    {
+        let result = self.push_entry_concat(name, sources, options);
+        self.or_poison(result)
+    }
+
+    /// Add a entry of concatenated sources without consuming the archive.
+    ///
+    /// See [`add_entry_concat`](ZipArchive::add_entry_concat) and
+    /// [`push_entry`](ZipArchive::push_entry).
+    pub fn push_entry_concat<I, R, O>(&mut self, name: &str, sources: I, options: O) -> Result<()>
+    where
+        I: IntoIterator<Item = R>,
+        R: Read,
+        O: Into<EntryOptions>,
+    {
+        self.check_poisoned()?;
+        let mut content = Vec::new();
+        for mut source in sources {
+            source
+                .read_to_end(&mut content)
+                .map_err(|error| Error::from(error).in_entry(name, Stage::Source))?;
+        }
+        self.push_entry_with(name, &content, options.into(), &Metadata::default())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{verify, Error, Level, Stage, ZipArchive};
+    use std::io::{Cursor, Read};
+
+    struct Broken;
+
+    impl Read for Broken {
+        fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::other("broken"))
+        }
+    }
+
+    #[test]
+    fn it_works() {
+        let mut buf = Vec::new();
+        let readers = vec![Cursor::new(b"abc".to_vec()), Cursor::new(b"def".to_vec())];
+        let mut zip = ZipArchive::new(&mut buf)
+            .add_entry_concat("slices.txt", [&b"abc"[..], b"", b"def"], Level::Raw)
+            .unwrap()
+            .add_entry_concat("readers.txt", readers, Level::High)
+            .unwrap();
+        let sources: [Box<dyn Read>; 2] = [Box::new(&b"partial"[..]), Box::new(Broken)];
+        assert!(matches!(
+            zip.push_entry_concat("broken.txt", sources, Level::Raw),
+            Err(Error::Entry {
+                stage: Stage::Source,
+                ..
+            })
+        ));
+        let summary = zip.finish().unwrap();
+        assert_eq!(summary.entries.len(), 2);
+        assert_eq!(summary.entries[0].checksum, summary.entries[1].checksum);
+        assert_eq!(summary.entries[0].uncompressed_size, 6);
+        assert_eq!(&buf[30 + 10..30 + 10 + 6], b"abcdef");
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+    }
+}
@@ -0,0 +1,86 @@
+use std::io::{IoSlice, Result, Write};
+
+/// Writer counting bytes written to the inner writer.
+///
+/// Only bytes which the inner writer accepts are counted, so the count is the position
+/// in the output, like offsets of entries streamed by [`EntryWriter`](crate::EntryWriter).
+///
+/// ```rust
+/// use std::io::Write;
+/// use zip_builder::{CountingWriter, Level, ZipArchive};
+///
+/// let mut output = CountingWriter::new(Vec::new());
+/// ZipArchive::new(&mut output)
+///     .add_entry("file.txt", b"content", Level::Default)?
+///     .finish()?;
+/// assert_eq!(output.count(), output.get_ref().len() as u64);
+/// # Ok::<(), zip_builder::Error>(())
+/// ```
+pub struct CountingWriter<W: Write> {
+    inner: W,
+    count: u64,
+}
+
+impl<W: Write> CountingWriter<W> {
+    /// Create a writer over `inner` with the count of zero.
+    pub fn new(inner: W) -> CountingWriter<W> {
+        CountingWriter { inner, count: 0 }
+    }
+
+    /// Number of bytes written so far.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Reference to the inner writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Mutable reference to the inner writer. Bytes written through it are not counted.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Unwrap the inner writer.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.count += written as u64;
+        Ok(written)
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> Result<usize> {
+        let written = self.inner.write_vectored(bufs)?;
+        self.count += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::CountingWriter;
+    use std::io::{Cursor, IoSlice, Write};
+
+    #[test]
+    fn it_works() {
+        let mut small = [0u8; 4];
+        let mut writer = CountingWriter::new(Cursor::new(&mut small[..]));
+        writer.write_all(b"ab").unwrap();
+        let slices = [IoSlice::new(b"c"), IoSlice::new(b"de")];
+        assert_eq!(writer.write_vectored(&slices).unwrap(), 2);
+        assert!(writer.write_all(b"f").is_err());
+        assert_eq!(writer.count(), 4);
+        writer.get_mut().set_position(0);
+        assert_eq!(writer.into_inner().into_inner(), b"abcd");
+    }
+}
@@ -1,60 +1,307 @@
+//! CRC-32 of Zip, to verify checksums of content before or after adding it.
+//!
+//! A table of 1 KiB is used by default. Feature `crc32-slicing-by-8` uses 8 tables of 8 KiB
+//! in total, which is several times faster for large content.
+//!
+//! ```rust
+//! use zip_builder::crc32::CRC32;
+//!
+//! assert_eq!(CRC32::checksum(b"123456789"), 0xcbf43926);
+//! assert_eq!(CRC32::checksum_slices([&b"1234"[..], b"56789"].iter().copied()), 0xcbf43926);
+//! assert_eq!(CRC32::checksum_reader(&b"123456789"[..])?, 0xcbf43926);
+//! # Ok::<(), std::io::Error>(())
+//! ```
+//!
+//! [`CrcReader`] and [`CrcWriter`] compute it while data flows through them, such as by
+//! [`std::io::copy`].
+//!
+//! ```rust
+//! use zip_builder::crc32::{CrcReader, CrcWriter};
+//!
+//! let mut reader = CrcReader::new(&b"123456789"[..]);
+//! let mut writer = CrcWriter::new(Vec::new());
+//! std::io::copy(&mut reader, &mut writer)?;
+//! assert_eq!(reader.crc(), 0xcbf43926);
+//! assert_eq!(writer.crc(), 0xcbf43926);
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
 use std::default::Default;
+use std::io::{Read, Write};
 
+/// Hasher of CRC-32 fed incrementally.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct CRC32(u32);
 
 impl Default for CRC32 {
-  fn default() -> Self {
-    CRC32(0xFFFFFFFFu32)
-  }
+    fn default() -> Self {
+        CRC32(0xFFFFFFFFu32)
+    }
 }
 
 impl CRC32 {
-  pub fn finish(&self) -> u32 {
-    !self.0
-  }
+    /// CRC-32 of `bytes`.
+    pub fn checksum(bytes: &[u8]) -> u32 {
+        CRC32::checksum_slices(std::iter::once(bytes))
+    }
+
+    /// CRC-32 of concatenation of `slices`.
+    pub fn checksum_slices<'s, I: IntoIterator<Item = &'s [u8]>>(slices: I) -> u32 {
+        let mut hasher = CRC32::default();
+        for slice in slices {
+            hasher.write(slice);
+        }
+        hasher.finish()
+    }
+
+    /// CRC-32 of all bytes read from `reader`, read in chunks.
+    pub fn checksum_reader<R: Read>(mut reader: R) -> std::io::Result<u32> {
+        let mut hasher = CRC32::default();
+        let mut buf = [0; 8192];
+        loop {
+            match reader.read(&mut buf) {
+                Ok(0) => return Ok(hasher.finish()),
+                Ok(len) => hasher.write(&buf[..len]),
+                Err(error) if error.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    /// CRC-32 of bytes written so far.
+    pub fn finish(&self) -> u32 {
+        !self.0
+    }
+
+    /// Feed `bytes`.
+    pub fn write(&mut self, bytes: &[u8]) {
+        let rest = self.write_slices(bytes);
+        self.0 = rest.iter().fold(self.0, |crc, &byte| {
+            CRC_TABLES[0][(crc as u8 ^ byte) as usize] ^ (crc >> 8)
+        })
+    }
+
+    /// Feed 8 bytes at a time, and return the rest.
+    #[cfg(feature = "crc32-slicing-by-8")]
+    fn write_slices<'b>(&mut self, bytes: &'b [u8]) -> &'b [u8] {
+        let mut chunks = bytes.chunks_exact(8);
+        for chunk in &mut chunks {
+            let low = self.0 ^ u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+            let high = u32::from_le_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]);
+            self.0 = CRC_TABLES[7][(low & 0xFF) as usize]
+                ^ CRC_TABLES[6][(low >> 8 & 0xFF) as usize]
+                ^ CRC_TABLES[5][(low >> 16 & 0xFF) as usize]
+                ^ CRC_TABLES[4][(low >> 24) as usize]
+                ^ CRC_TABLES[3][(high & 0xFF) as usize]
+                ^ CRC_TABLES[2][(high >> 8 & 0xFF) as usize]
+                ^ CRC_TABLES[1][(high >> 16 & 0xFF) as usize]
+                ^ CRC_TABLES[0][(high >> 24) as usize];
+        }
+        chunks.remainder()
+    }
+
+    #[cfg(not(feature = "crc32-slicing-by-8"))]
+    fn write_slices<'b>(&mut self, bytes: &'b [u8]) -> &'b [u8] {
+        bytes
+    }
+}
+
+/// Reader computing CRC-32 of bytes read from the inner reader.
+pub struct CrcReader<R: Read> {
+    inner: R,
+    hasher: CRC32,
+}
+
+impl<R: Read> CrcReader<R> {
+    /// Create a reader over `inner`.
+    pub fn new(inner: R) -> CrcReader<R> {
+        CrcReader {
+            inner,
+            hasher: CRC32::default(),
+        }
+    }
+
+    /// CRC-32 of bytes read so far.
+    pub fn crc(&self) -> u32 {
+        self.hasher.finish()
+    }
+
+    /// Reference to the inner reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Unwrap the inner reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for CrcReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let length = self.inner.read(buf)?;
+        self.hasher.write(&buf[..length]);
+        Ok(length)
+    }
+}
+
+/// Writer computing CRC-32 of bytes written to the inner writer.
+///
+/// Only bytes which the inner writer accepts are fed.
+pub struct CrcWriter<W: Write> {
+    inner: W,
+    hasher: CRC32,
+}
+
+impl<W: Write> CrcWriter<W> {
+    /// Create a writer over `inner`.
+    pub fn new(inner: W) -> CrcWriter<W> {
+        CrcWriter {
+            inner,
+            hasher: CRC32::default(),
+        }
+    }
+
+    /// CRC-32 of bytes written so far.
+    pub fn crc(&self) -> u32 {
+        self.hasher.finish()
+    }
+
+    /// Reference to the inner writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Unwrap the inner writer.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write> Write for CrcWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.write(&buf[..written]);
+        Ok(written)
+    }
 
-  pub fn write(&mut self, bytes: &[u8]) {
-    self.0 = bytes.iter().fold(self.0, |crc, &byte| {
-      CRC_TABLE[(crc as u8 ^ byte) as usize] ^ (crc >> 8)
-    })
-  }
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
 }
 
 const fn make_crc_table() -> [u32; 256] {
-  let mut table: [u32; 256] = [0; 256];
-  let mut n = 0;
-  while n != 256 {
-    let mut c = n as u32;
-    let mut k = 0;
-    while k != 8 {
-      if c & 1 == 1 {
-        c = 0xedb88320u32 ^ (c >> 1);
-      } else {
-        c = c >> 1;
-      }
-      k += 1;
-    }
-    table[n] = c;
-    n += 1;
-  }
-  table
-}
-
-const CRC_TABLE: [u32; 256] = make_crc_table();
+    let mut table: [u32; 256] = [0; 256];
+    let mut n = 0;
+    while n != 256 {
+        let mut c = n as u32;
+        let mut k = 0;
+        while k != 8 {
+            if c & 1 == 1 {
+                c = 0xedb88320u32 ^ (c >> 1);
+            } else {
+                c = c >> 1;
+            }
+            k += 1;
+        }
+        table[n] = c;
+        n += 1;
+    }
+    table
+}
+
+/// Table `n` is CRC of a byte followed by `n` zero bytes.
+const fn make_crc_tables<const N: usize>() -> [[u32; 256]; N] {
+    let mut tables = [[0; 256]; N];
+    tables[0] = make_crc_table();
+    let mut t = 1;
+    while t != N {
+        let mut n = 0;
+        while n != 256 {
+            let previous = tables[t - 1][n];
+            tables[t][n] = tables[0][(previous & 0xFF) as usize] ^ (previous >> 8);
+            n += 1;
+        }
+        t += 1;
+    }
+    tables
+}
+
+#[cfg(not(feature = "crc32-slicing-by-8"))]
+const TABLES: usize = 1;
+#[cfg(feature = "crc32-slicing-by-8")]
+const TABLES: usize = 8;
+
+const CRC_TABLES: [[u32; 256]; TABLES] = make_crc_tables();
 
 #[cfg(test)]
 mod test {
-  use super::CRC32;
-
-  fn crc_test(s: &str, crc: u32) {
-    let mut hasher = CRC32::default();
-    hasher.write(s.as_bytes());
-    assert_eq!(hasher.finish(), crc);
-  }
-
-  #[test]
-  fn it_works() {
-    crc_test("abcd", 0xed82cd11u32);
-    crc_test("123456789", 0xcbf43926u32);
-  }
+    use super::{CrcReader, CrcWriter, CRC32};
+    use std::io::{Cursor, Read, Write};
+
+    fn crc_test(s: &str, crc: u32) {
+        let mut hasher = CRC32::default();
+        hasher.write(s.as_bytes());
+        assert_eq!(hasher.finish(), crc);
+    }
+
+    #[test]
+    fn it_works() {
+        crc_test("abcd", 0xed82cd11u32);
+        crc_test("123456789", 0xcbf43926u32);
+    }
+
+    #[test]
+    fn input_shapes() {
+        let content: Vec<u8> = (0..20000).map(|n| (n * 7 % 251) as u8).collect();
+        let expected = CRC32::checksum(&content);
+        assert_eq!(CRC32::checksum_slices(content.chunks(333)), expected);
+        assert_eq!(CRC32::checksum_slices(Vec::<&[u8]>::new()), 0);
+        assert_eq!(
+            CRC32::checksum_reader(content.as_slice()).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn adapters() {
+        let content: Vec<u8> = (0..20000).map(|n| (n * 7 % 251) as u8).collect();
+        let expected = CRC32::checksum(&content);
+        let mut reader = CrcReader::new(content.as_slice());
+        let mut read = Vec::new();
+        reader.read_to_end(&mut read).unwrap();
+        assert_eq!(reader.crc(), expected);
+        assert_eq!(read, content);
+
+        let mut small = [0u8; 4];
+        let mut writer = CrcWriter::new(Cursor::new(&mut small[..]));
+        assert!(writer.write_all(b"abcdef").is_err());
+        assert_eq!(writer.crc(), CRC32::checksum(b"abcd"));
+        assert_eq!(writer.get_ref().position(), 4);
+    }
+
+    #[test]
+    fn tables() {
+        // Bitwise computation without tables.
+        let bitwise = |bytes: &[u8]| {
+            !bytes.iter().fold(!0u32, |mut crc, &byte| {
+                crc ^= byte as u32;
+                for _ in 0..8 {
+                    crc = if crc & 1 == 1 {
+                        0xedb88320 ^ (crc >> 1)
+                    } else {
+                        crc >> 1
+                    };
+                }
+                crc
+            })
+        };
+        let content: Vec<u8> = (0..100).map(|n| (n * 37 % 256) as u8).collect();
+        for start in 0..8 {
+            for end in start..content.len() {
+                let bytes = &content[start..end];
+                assert_eq!(CRC32::checksum(bytes), bitwise(bytes));
+            }
+        }
+    }
 }
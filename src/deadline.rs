@@ -0,0 +1,63 @@
+use crate::ZipArchive;
+use std::io::Write;
+use std::time::Instant;
+
+impl<'a, T: Write + ?Sized + 'a> ZipArchive<'a, T> {
+    /// Whether the deadline has passed, recording the entry as skipped if so.
+    pub(crate) fn overdue(&mut self, name: &str) -> bool {
+        match self.config.deadline {
+            Some(deadline) if Instant::now() >= deadline => {
+                let name = self.prefixed(name).into_owned();
+                self.skipped.push(name);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{verify, Error, Level, ZipArchiveBuilder};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn it_works() {
+        let mut buf = Vec::new();
+        let mut zip = ZipArchiveBuilder::new()
+            .root_prefix("root/")
+            .deadline(Instant::now() + Duration::from_millis(100))
+            .build(&mut buf);
+        zip.push_entry("early.txt", b"early", Level::Default)
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(150));
+        zip.push_entry("late.txt", b"late", Level::Default).unwrap();
+        assert!(matches!(
+            zip.start_entry("streamed.txt", Level::Default),
+            Err(Error::DeadlineExceeded { name }) if name == "streamed.txt"
+        ));
+        let summary = zip
+            .add_entries(vec![
+                ("a.txt", &b"a"[..], Level::Raw),
+                ("b.txt", b"b", Level::Raw),
+            ])
+            .unwrap()
+            .add_entries_parallel(vec![("c.txt", &b"c"[..], Level::Raw)], 2)
+            .unwrap()
+            .finish()
+            .unwrap();
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+        assert_eq!(summary.entries.len(), 1);
+        assert_eq!(summary.entries[0].name, "root/early.txt");
+        assert_eq!(
+            summary.skipped,
+            [
+                "root/late.txt",
+                "root/streamed.txt",
+                "root/a.txt",
+                "root/b.txt",
+                "root/c.txt"
+            ]
+        );
+    }
+}
@@ -0,0 +1,261 @@
+use crate::{DosAttributes, EntryOptions, Level, Result, ZipArchive};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+impl<'a, T: Write + ?Sized + 'a> ZipArchive<'a, T> {
+    /// Add the directory of `path` and files under it recursively.
+    ///
+    /// Entries are named `prefix` followed by relative paths separated by `/`,
+    /// and directories are added as entries of the names ending with `/`.
+    /// They are visited in the order of names. Symbolic links are not followed.
+    /// With [`share_hard_links`](crate::ZipArchiveBuilder::share_hard_links), files which are
+    /// hard links of a file added before are written only in the central directory.
+    pub fn add_dir_recursive<P: AsRef<Path>, O: Into<EntryOptions>>(
+        mut self,
+        prefix: &str,
+        path: P,
+        options: O,
+    ) -> Result<Self> {
+        let mut links = HashMap::new();
+        let result = self.push_dir(prefix, path.as_ref(), options.into(), &mut links);
+        self.or_poison(result)
+    }
+
+    fn push_dir(
+        &mut self,
+        prefix: &str,
+        path: &Path,
+        options: EntryOptions,
+        links: &mut HashMap<(u64, u64), String>,
+    ) -> Result<()> {
+        let mut children = fs::read_dir(path)?
+            .map(|child| child.map(|child| child.path()))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        children.sort();
+        for child in children {
+            let name = match child.file_name() {
+                Some(name) => format!("{}{}", prefix, name.to_string_lossy()),
+                None => continue,
+            };
+            let metadata = fs::symlink_metadata(&child)?;
+            if metadata.is_dir() {
+                let name = name + "/";
+                let directory = options
+                    .level(Level::Raw)
+                    .dos_attributes(DosAttributes::DIRECTORY);
+                self.push_entry(&name, &[], directory)?;
+                self.push_dir(&name, &child, options, links)?;
+            } else if metadata.is_file() {
+                let link = if self.config.share_hard_links {
+                    inode(&metadata)
+                } else {
+                    None
+                };
+                match link.and_then(|link| links.get(&link)) {
+                    Some(target) => {
+                        let target = target.clone();
+                        self.push_link(&name, &target, &child, options)?;
+                    }
+                    None => {
+                        if let Some(link) = link {
+                            links.insert(link, name.clone());
+                        }
+                        self.push_file(&name, &child, options)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Add an entry which shares data of the entry of `target` if it is written,
+    /// or the file of `path` otherwise.
+    ///
+    /// The target may be deferred by `place_first` or skipped by `update_of`.
+    fn push_link(
+        &mut self,
+        name: &str,
+        target: &str,
+        path: &Path,
+        options: EntryOptions,
+    ) -> Result<()> {
+        match self.written(target, options) {
+            Some(index) => self.push_shared(name, index, options),
+            None => self.push_file(name, path, options),
+        }
+    }
+
+    /// Index of the entry added as `name` with `options`, if it is written.
+    pub(crate) fn written(&self, name: &str, options: EntryOptions) -> Option<usize> {
+        let filename = self.entry_filename(name, options);
+        self.entries
+            .iter()
+            .position(|entry| entry.filename == filename)
+    }
+
+    /// Add an entry which shares data of the entry at `index`.
+    pub(crate) fn push_shared(
+        &mut self,
+        name: &str,
+        index: usize,
+        options: EntryOptions,
+    ) -> Result<()> {
+        self.check_poisoned()?;
+        if self.overdue(name) {
+            return Ok(());
+        }
+        let size = self.entries[index].uncompressed_size as usize;
+        self.check_limits(name, size, None)?;
+        self.record_case(name);
+        let mut entry = self.entries[index].clone();
+        entry.filename = self.entry_filename(name, options);
+        self.total_size += entry.uncompressed_size;
+        self.entries.push(entry);
+        self.place(name)
+    }
+
+    /// Name in the central directory of the entry added as `name` with `options`.
+    fn entry_filename(&self, name: &str, options: EntryOptions) -> String {
+        options
+            .inherit(&self.config.defaults)
+            .entry_name(&self.prefixed(name))
+            .into_owned()
+    }
+}
+
+/// Device and inode numbers identifying hard links.
+#[cfg(unix)]
+fn inode(metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    if metadata.nlink() > 1 {
+        Some((metadata.dev(), metadata.ino()))
+    } else {
+        None
+    }
+}
+
+#[cfg(not(unix))]
+fn inode(_metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use crate::verify::read_entries;
+    use crate::{verify, Level, ZipArchiveBuilder, CRC32};
+    use std::fs;
+    use std::path::PathBuf;
+
+    /// Directory of `b.txt`, `sub/a.txt`, `sub/empty/` and `sub/link.txt` linked to `b.txt`.
+    fn tree(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("sub/empty")).unwrap();
+        fs::write(root.join("b.txt"), [b'b'; 100]).unwrap();
+        fs::write(root.join("sub/a.txt"), b"a").unwrap();
+        #[cfg(unix)]
+        fs::hard_link(root.join("b.txt"), root.join("sub/link.txt")).unwrap();
+        root
+    }
+
+    #[test]
+    fn it_works() {
+        let root = tree("zip-builder-directory");
+
+        let mut buf = Vec::new();
+        let summary = ZipArchiveBuilder::new()
+            .share_hard_links()
+            .build(&mut buf)
+            .add_dir_recursive("root/", &root, Level::Default)
+            .unwrap()
+            .finish()
+            .unwrap();
+        fs::remove_dir_all(&root).unwrap();
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+        let names: Vec<_> = summary
+            .entries
+            .iter()
+            .map(|entry| &entry.name[..])
+            .collect();
+        #[cfg(unix)]
+        {
+            assert_eq!(
+                names,
+                [
+                    "root/b.txt",
+                    "root/sub/",
+                    "root/sub/a.txt",
+                    "root/sub/empty/",
+                    "root/sub/link.txt"
+                ]
+            );
+            assert_eq!(summary.entries[0].offset, summary.entries[4].offset);
+        }
+        #[cfg(not(unix))]
+        assert_eq!(
+            names,
+            [
+                "root/b.txt",
+                "root/sub/",
+                "root/sub/a.txt",
+                "root/sub/empty/"
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unwritten_link_target() {
+        let root = tree("zip-builder-directory-target");
+        let mut buf = Vec::new();
+        let summary = ZipArchiveBuilder::new()
+            .share_hard_links()
+            .place_first(["root/sub/a.txt"])
+            .build(&mut buf)
+            .add_dir_recursive("root/", &root, Level::Default)
+            .unwrap()
+            .finish()
+            .unwrap();
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+        let position = |name: &str| {
+            summary
+                .entries
+                .iter()
+                .position(|entry| entry.name == name)
+                .unwrap()
+        };
+        let (link, target) = (position("root/sub/link.txt"), position("root/b.txt"));
+        assert_eq!(summary.entries[link].offset, summary.entries[target].offset);
+        assert_eq!(read_entries(&buf).unwrap()[link].1, [b'b'; 100]);
+
+        let mut buf = Vec::new();
+        let summary = ZipArchiveBuilder::new()
+            .share_hard_links()
+            .update_of([("root/b.txt", CRC32::checksum(&[b'b'; 100]), 100)])
+            .build(&mut buf)
+            .add_dir_recursive("root/", &root, Level::Default)
+            .unwrap()
+            .finish()
+            .unwrap();
+        fs::remove_dir_all(&root).unwrap();
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+        let names: Vec<_> = summary
+            .entries
+            .iter()
+            .map(|entry| &entry.name[..])
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "root/sub/",
+                "root/sub/a.txt",
+                "root/sub/empty/",
+                "root/sub/link.txt"
+            ]
+        );
+        let entries = read_entries(&buf).unwrap();
+        assert_eq!(entries[3].1, [b'b'; 100]);
+    }
+}
@@ -0,0 +1,361 @@
+use crate::{ArchiveSummary, Config, EntryOptions, Level, Result, ZipArchiveBuilder, ZipEntry};
+use aes::cipher::{BlockEncrypt, KeyInit};
+use aes::Aes256;
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+use std::io::Write;
+
+const SALT_LENGTH: usize = 16;
+const KEY_LENGTH: usize = 32;
+const ITERATIONS: u32 = 1000;
+
+/// Password of the archive, wiped when dropped with feature `zeroize`.
+#[cfg(feature = "zeroize")]
+pub(crate) type Password = zeroize::Zeroizing<String>;
+#[cfg(not(feature = "zeroize"))]
+pub(crate) type Password = String;
+
+/// Derivation of the keys of an encrypted entry from the password and the salt.
+///
+/// Set it by [`ZipArchiveBuilder::key_derivation`]. Readers of WinZip AE-2 derive keys
+/// by [`Pbkdf2`] of 1000 iterations, so archives encrypted with other derivations
+/// can only be decrypted by readers configured the same way.
+pub trait KeyDerivation {
+    /// Fill `keys` with the AES key, the HMAC key and the password verification value.
+    fn derive(&self, password: &[u8], salt: &[u8], keys: &mut [u8]);
+}
+
+/// PBKDF2-HMAC-SHA1 with the iteration count, which is 1000 by default as AE-2 specifies.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Pbkdf2 {
+    /// Number of iterations.
+    pub iterations: u32,
+}
+
+impl Default for Pbkdf2 {
+    fn default() -> Self {
+        Pbkdf2 {
+            iterations: ITERATIONS,
+        }
+    }
+}
+
+impl KeyDerivation for Pbkdf2 {
+    fn derive(&self, password: &[u8], salt: &[u8], keys: &mut [u8]) {
+        pbkdf2::pbkdf2_hmac::<Sha1>(password, salt, self.iterations, keys);
+    }
+}
+
+/// Source of salts of encrypted entries.
+///
+/// Set it by [`ZipArchiveBuilder::random_source`] for deterministic tests
+/// or approved generators. Salts must not repeat for a password, or the encryption is broken.
+pub trait RandomSource {
+    /// Fill `bytes` with random bytes.
+    fn fill(&self, bytes: &mut [u8]) -> std::io::Result<()>;
+}
+
+/// Random source of the operating system by `getrandom`, which is used by default.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct OsRandom;
+
+impl RandomSource for OsRandom {
+    fn fill(&self, bytes: &mut [u8]) -> std::io::Result<()> {
+        getrandom::getrandom(bytes).map_err(std::io::Error::from)
+    }
+}
+
+/// Encrypt `data` with AES-256 in the manner of WinZip AE-2.
+///
+/// The result is salt, password verification value, encrypted data
+/// and authentication code, which is stored as data of the entry.
+/// The salt is given by [`OsRandom`] and keys are derived by [`Pbkdf2`]
+/// unless `config` sets others.
+pub(crate) fn encrypt(config: &Config, password: &str, data: &[u8]) -> Result<Vec<u8>> {
+    let mut salt = [0u8; SALT_LENGTH];
+    match &config.random_source {
+        Some(random) => random.fill(&mut salt)?,
+        None => OsRandom.fill(&mut salt)?,
+    }
+    let default = Pbkdf2::default();
+    let derivation: &dyn KeyDerivation = match &config.key_derivation {
+        Some(derivation) => derivation.as_ref(),
+        None => &default,
+    };
+    Ok(encrypt_with_salt(password, derivation, &salt, data))
+}
+
+fn encrypt_with_salt(
+    password: &str,
+    derivation: &dyn KeyDerivation,
+    salt: &[u8; SALT_LENGTH],
+    data: &[u8],
+) -> Vec<u8> {
+    // Keys are wiped after the entry with feature `zeroize`, like the key schedule of AES.
+    #[cfg(feature = "zeroize")]
+    let mut keys = zeroize::Zeroizing::new([0u8; KEY_LENGTH * 2 + 2]);
+    #[cfg(not(feature = "zeroize"))]
+    let mut keys = [0u8; KEY_LENGTH * 2 + 2];
+    derivation.derive(password.as_bytes(), salt, &mut keys[..]);
+    let cipher = Aes256::new_from_slice(&keys[..KEY_LENGTH]).unwrap();
+    let mut mac = <Hmac<Sha1> as Mac>::new_from_slice(&keys[KEY_LENGTH..KEY_LENGTH * 2]).unwrap();
+
+    let mut output = Vec::with_capacity(SALT_LENGTH + 2 + data.len() + 10);
+    output.extend_from_slice(salt);
+    output.extend_from_slice(&keys[KEY_LENGTH * 2..]);
+    // CTR mode with little-endian counter from 1.
+    for (index, chunk) in data.chunks(16).enumerate() {
+        let mut block = ((index + 1) as u128).to_le_bytes().into();
+        cipher.encrypt_block(&mut block);
+        let start = output.len();
+        output.extend(chunk.iter().zip(block.iter()).map(|(byte, key)| byte ^ key));
+        mac.update(&output[start..]);
+    }
+    output.extend_from_slice(&mac.finalize().into_bytes()[..10]);
+    output
+}
+
+impl ZipEntry {
+    /// Mark the entry as encrypted by AE-2, keeping the real method in the extra field.
+    pub(crate) fn seal(&mut self) {
+        self.extra.extend_from_slice(&0x9901u16.to_le_bytes());
+        self.extra.extend_from_slice(&7u16.to_le_bytes());
+        self.extra.extend_from_slice(&2u16.to_le_bytes());
+        self.extra.extend_from_slice(b"AE");
+        self.extra.push(3);
+        self.extra.extend_from_slice(&self.method.to_le_bytes());
+        self.method = 99;
+        self.flags |= 1;
+        // AE-2 does not record CRC, so it does not leak information of the content.
+        self.checksum = 0;
+    }
+}
+
+/// Write an archive whose only entry is `inner` encrypted with `password`.
+///
+/// Names of entries in a Zip archive are never encrypted. Give another archive as `inner`
+/// to hide its file listing: the outer archive only exposes `inner_name`.
+///
+/// ```rust
+/// use zip_builder::{wrap_encrypted, Level, ZipArchive};
+///
+/// let mut inner = Vec::new();
+/// ZipArchive::new(&mut inner)
+///     .add_entry("secret-name.txt", b"content", Level::Default)?
+///     .finish()?;
+/// let mut output = Vec::new();
+/// wrap_encrypted(&mut output, "data.zip", "password", &inner)?;
+/// # Ok::<(), zip_builder::Error>(())
+/// ```
+pub fn wrap_encrypted<W: Write>(
+    output: &mut W,
+    inner_name: &str,
+    password: &str,
+    inner: &[u8],
+) -> Result<ArchiveSummary> {
+    ZipArchiveBuilder::new()
+        .password(password)
+        .build(output)
+        .add_entry(
+            inner_name,
+            inner,
+            EntryOptions::new(Level::Raw).encrypt(true),
+        )?
+        .finish()
+}
+
+#[cfg(test)]
+mod test {
+    use super::encrypt_with_salt;
+    use crate::{
+        verify, wrap_encrypted, EntryOptions, Error, KeyDerivation, Level, Pbkdf2, RandomSource,
+        ZipArchive, ZipArchiveBuilder,
+    };
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn it_works() {
+        let data = encrypt_with_salt(
+            "password",
+            &Pbkdf2::default(),
+            &[0; 16],
+            b"content of entry",
+        );
+        assert_eq!(data.len(), 16 + 2 + 16 + 10);
+        assert_ne!(&data[18..34], b"content of entry");
+
+        let mut inner = Vec::new();
+        ZipArchive::new(&mut inner)
+            .add_entry("secret-name.txt", b"content", Level::Default)
+            .unwrap()
+            .finish()
+            .unwrap();
+        let mut output = Vec::new();
+        wrap_encrypted(&mut output, "data.zip", "password", &inner).unwrap();
+        assert!(!output
+            .windows(15)
+            .any(|window| window == b"secret-name.txt"));
+        assert_eq!(verify(output.as_slice()).unwrap(), vec![]);
+
+        let mut buf = Vec::new();
+        let result = ZipArchiveBuilder::new().build(&mut buf).add_entry(
+            "file.txt",
+            b"content",
+            EntryOptions::new(Level::Default).encrypt(true),
+        );
+        assert!(matches!(result, Err(Error::NoPassword)));
+    }
+
+    #[test]
+    fn decrypt() {
+        use aes::cipher::{BlockEncrypt, KeyInit};
+        use hmac::{Hmac, Mac};
+
+        let plain = b"AE-2 counts blocks from one, little-endian.";
+        let salt = [0x5a; 16];
+        let data = encrypt_with_salt("password", &Pbkdf2::default(), &salt, plain);
+        assert_eq!(data.len(), 16 + 2 + plain.len() + 10);
+        assert_eq!(&data[..16], &salt);
+
+        let mut keys = [0u8; 66];
+        pbkdf2::pbkdf2_hmac::<sha1::Sha1>(b"password", &salt, 1000, &mut keys);
+        assert_eq!(&data[16..18], &keys[64..]);
+        let (ciphertext, code) = data[18..].split_at(plain.len());
+
+        let mut mac = <Hmac<sha1::Sha1> as Mac>::new_from_slice(&keys[32..64]).unwrap();
+        mac.update(ciphertext);
+        assert_eq!(code, &mac.finalize().into_bytes()[..10]);
+
+        let cipher = aes::Aes256::new_from_slice(&keys[..32]).unwrap();
+        let mut decrypted = Vec::new();
+        for (index, chunk) in ciphertext.chunks(16).enumerate() {
+            let mut block = ((index + 1) as u128).to_le_bytes().into();
+            cipher.encrypt_block(&mut block);
+            decrypted.extend(chunk.iter().zip(block.iter()).map(|(byte, key)| byte ^ key));
+        }
+        assert_eq!(decrypted, plain);
+    }
+
+    #[test]
+    fn known_answer() {
+        // Computed independently with Python's hashlib and cryptography.
+        let expected = "5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5ab72c38dd1007e3eb3abc78236874df59\
+                        8160ac46f2083a0dfc101aadbca0421f7986dd8b78821f162eebf4a714c2e3d9\
+                        090aea7ef7cc05";
+        let data = encrypt_with_salt(
+            "password",
+            &Pbkdf2::default(),
+            &[0x5a; 16],
+            b"AE-2 counts blocks from one, little-endian.",
+        );
+        let hex: String = data.iter().map(|byte| format!("{:02x}", byte)).collect();
+        assert_eq!(hex, expected);
+    }
+
+    #[test]
+    fn deduplicate() {
+        let content = [b'x'; 100];
+        let encrypted = EntryOptions::new(Level::Raw).encrypt(true);
+        let mut buf = Vec::new();
+        let summary = ZipArchiveBuilder::new()
+            .password("password")
+            .deduplicate()
+            .build(&mut buf)
+            .add_entry("plain.txt", &content, Level::Raw)
+            .unwrap()
+            .add_entry("first.txt", &content, encrypted)
+            .unwrap()
+            .add_entry("second.txt", &content, encrypted)
+            .unwrap()
+            .add_entry("copy.txt", &content, Level::Raw)
+            .unwrap()
+            .finish()
+            .unwrap();
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+        let offsets: Vec<_> = summary.entries.iter().map(|entry| entry.offset).collect();
+        assert_eq!(offsets[3], offsets[0]);
+        assert!(offsets[0] < offsets[1] && offsets[1] < offsets[2]);
+        // The content is written in plain text only once.
+        assert_eq!(
+            buf.windows(content.len())
+                .filter(|window| *window == content)
+                .count(),
+            1
+        );
+    }
+
+    #[derive(Default)]
+    struct Counted(AtomicUsize);
+
+    impl KeyDerivation for Arc<Counted> {
+        fn derive(&self, password: &[u8], salt: &[u8], keys: &mut [u8]) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Pbkdf2 { iterations: 10000 }.derive(password, salt, keys);
+        }
+    }
+
+    #[test]
+    fn key_derivation() {
+        let salt = [0; 16];
+        let encrypt = |derivation: &dyn KeyDerivation| {
+            encrypt_with_salt("password", derivation, &salt, b"content")
+        };
+        assert_eq!(
+            encrypt(&Pbkdf2 { iterations: 1000 }),
+            encrypt(&Pbkdf2::default())
+        );
+        assert_ne!(
+            encrypt(&Pbkdf2 { iterations: 10000 }),
+            encrypt(&Pbkdf2::default())
+        );
+
+        let counted = Arc::new(Counted::default());
+        let mut buf = Vec::new();
+        ZipArchiveBuilder::new()
+            .password("password")
+            .key_derivation(counted.clone())
+            .build(&mut buf)
+            .add_entry("a.txt", b"a", EntryOptions::new(Level::Raw).encrypt(true))
+            .unwrap()
+            .add_entry("b.txt", b"b", EntryOptions::new(Level::Raw).encrypt(true))
+            .unwrap()
+            .finish()
+            .unwrap();
+        assert_eq!(counted.0.load(Ordering::SeqCst), 2);
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+    }
+
+    struct Sequence(AtomicUsize);
+
+    impl RandomSource for Sequence {
+        fn fill(&self, bytes: &mut [u8]) -> std::io::Result<()> {
+            bytes.fill(self.0.fetch_add(1, Ordering::SeqCst) as u8);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn random_source() {
+        let build = || {
+            let mut buf = Vec::new();
+            ZipArchiveBuilder::new()
+                .password("password")
+                .random_source(Sequence(AtomicUsize::new(0)))
+                .build(&mut buf)
+                .add_entry("a.txt", b"a", EntryOptions::new(Level::Raw).encrypt(true))
+                .unwrap()
+                .add_entry("b.txt", b"b", EntryOptions::new(Level::Raw).encrypt(true))
+                .unwrap()
+                .finish()
+                .unwrap();
+            buf
+        };
+        let buf = build();
+        assert_eq!(buf, build());
+        // The first salt follows the local file header, the name and the AE-2 extra field.
+        assert_eq!(&buf[46..62], &[0; 16]);
+        assert!(buf.windows(16).any(|window| window == [1; 16]));
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+    }
+}
@@ -0,0 +1,91 @@
+/// Number of bytes of each block sampled by [`estimate_entropy`].
+const SAMPLE_BLOCK: usize = 4096;
+/// Number of blocks sampled by [`estimate_entropy`] over the content.
+const SAMPLE_BLOCKS: usize = 16;
+
+/// Entropy in bits per byte from which [`EntryOptions::entropy_probe`](crate::EntryOptions::entropy_probe)
+/// stores content without compression.
+pub(crate) const INCOMPRESSIBLE: f64 = 7.8;
+
+/// Estimate Shannon entropy of `content` in bits per byte, from 0 to 8.
+///
+/// Byte frequencies are counted in up to 16 blocks of 4 KiB spread evenly over the content,
+/// so this is fast for any size. Compressed or encrypted data is close to 8 and text is
+/// about 4 to 5, but repetition of longer strings is not seen, so a low estimate does not
+/// promise compression. Empty content is 0.
+///
+/// ```
+/// # use zip_builder::estimate_entropy;
+/// assert_eq!(estimate_entropy(&[b'a'; 1000]), 0.0);
+/// assert!(estimate_entropy(b"Hello, world!") < 4.0);
+/// ```
+pub fn estimate_entropy(content: &[u8]) -> f64 {
+    let mut counts = [0u64; 256];
+    let blocks = content.len().div_ceil(SAMPLE_BLOCK);
+    let step = blocks.div_ceil(SAMPLE_BLOCKS).max(1);
+    for block in content.chunks(SAMPLE_BLOCK).step_by(step) {
+        for &byte in block {
+            counts[byte as usize] += 1;
+        }
+    }
+    let total: u64 = counts.iter().sum();
+    counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let probability = count as f64 / total as f64;
+            -probability * probability.log2()
+        })
+        .sum::<f64>()
+        .max(0.0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::estimate_entropy;
+    use crate::{EntryOptions, Level, ZipArchiveBuilder};
+
+    fn noise(length: usize) -> Vec<u8> {
+        (0..length as u32)
+            .scan(1u32, |state, _| {
+                *state = state.wrapping_mul(1103515245).wrapping_add(12345);
+                Some((*state >> 24) as u8)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn it_works() {
+        assert_eq!(estimate_entropy(&[]), 0.0);
+        let all: Vec<u8> = (0..=255).collect();
+        assert!((estimate_entropy(&all) - 8.0).abs() < 1e-9);
+        assert!(estimate_entropy(&noise(1 << 20)) > 7.9);
+        let text = b"the quick brown fox jumps over the lazy dog ".repeat(1000);
+        assert!(estimate_entropy(&text) < 5.0);
+    }
+
+    #[test]
+    fn entropy_probe() {
+        let noise = noise(10000);
+        let text = b"the quick brown fox jumps over the lazy dog ".repeat(100);
+        let mut buf = Vec::new();
+        let summary = ZipArchiveBuilder::new()
+            .entropy_probe()
+            .build(&mut buf)
+            .add_entry("noise.bin", &noise, Level::High)
+            .unwrap()
+            .add_entry("text.txt", &text, Level::High)
+            .unwrap()
+            .add_entry(
+                "forced.bin",
+                &noise,
+                EntryOptions::new(Level::High).entropy_probe(false),
+            )
+            .unwrap()
+            .finish()
+            .unwrap();
+        assert_eq!(summary.entries[0].compressed_size, 10000);
+        assert!(summary.entries[1].compressed_size < 100);
+        assert!(summary.entries[2].compressed_size > 10000);
+    }
+}
@@ -3,8 +3,14 @@ use std::fmt::Display;
 use std::fmt::Formatter;
 use std::fmt::Result;
 
+use crate::{Profile, Violation};
+
 /// Represent error that occurred during archive generation.
+///
+/// Variants of optional features exist regardless of the enabled features, and more
+/// variants may be added in the future.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum Error {
     /// Writing error.
     IoError(std::io::Error),
@@ -13,6 +19,123 @@ pub enum Error {
     /// There is an upper limit to the size that can be stored in Zip.
     /// You will get this error if you pass too large data.
     IntError(std::num::TryFromIntError),
+    /// A limit set with [`ZipArchiveBuilder`](crate::ZipArchiveBuilder) would be exceeded
+    /// by the entry of the name. Nothing of the entry is written.
+    LimitExceeded { name: String, limit: Limit },
+    /// Name of the entry is longer than 65535 bytes, which Zip can record.
+    NameTooLong { name: String, length: usize },
+    /// Extra fields of the entry of the name are longer than 65535 bytes in total.
+    ExtraFieldTooLong { name: String, length: usize },
+    /// Name of the entry differs from the existing name only by case.
+    CaseCollision { name: String, existing: String },
+    /// Comment of the archive is longer than 65535 bytes.
+    CommentTooLong { length: usize },
+    /// I/O or integer conversion error at the stage of the entry of the name.
+    ///
+    /// Its backtrace field exists only with `backtrace` feature, so its patterns need `..`.
+    #[non_exhaustive]
+    Entry {
+        name: String,
+        stage: Stage,
+        source: Box<Error>,
+        #[cfg(feature = "backtrace")]
+        backtrace: Box<std::backtrace::Backtrace>,
+    },
+    /// Writing an entry failed in the middle before, so the archive is inconsistent
+    /// and no more entries can be added.
+    Poisoned,
+    /// The entry of the name breaks a rule of the profile set with
+    /// [`ZipArchiveBuilder::profile`](crate::ZipArchiveBuilder::profile).
+    NonConforming {
+        name: String,
+        profile: Profile,
+        violation: Violation,
+    },
+    /// Modification time of the entry of the name is after 2107 and
+    /// [`TimeOverflow::Reject`](crate::TimeOverflow::Reject) is set.
+    TimeOutOfRange { name: String },
+    /// Data of the entry of the name can not start at the offset set with
+    /// [`EntryOptions::fixed_offset`](crate::EntryOptions::fixed_offset).
+    OffsetUnreachable { name: String, offset: u64 },
+    /// Offsets of the archive would exceed 2^64 bytes.
+    OffsetOverflow,
+    /// Bytes given to [`Checkpoint::from_bytes`](crate::Checkpoint::from_bytes) are malformed.
+    InvalidCheckpoint,
+    /// The archive given to `ZipArchive::add_archive` of `verify` feature
+    /// is inconsistent, or has encrypted entries or entries of unsupported methods.
+    InvalidArchive,
+    /// An entry of the name is already added and `DuplicateName::Reject` of `verify`
+    /// feature is set.
+    DuplicateName { name: String },
+    /// An entry is to be encrypted but no password is set
+    /// with `ZipArchiveBuilder::password` of `aes` feature.
+    NoPassword,
+    /// The entry of the name is to be encrypted with `aes` feature but started by
+    /// [`ZipArchive::start_entry`](crate::ZipArchive::start_entry), which does not encrypt.
+    EncryptedStream { name: String },
+    /// The entry of the name is started by
+    /// [`ZipArchive::start_entry`](crate::ZipArchive::start_entry) after the
+    /// [`deadline`](crate::ZipArchiveBuilder::deadline).
+    DeadlineExceeded { name: String },
+    /// The certificate given to `ZipArchive::sign_jar` of `jar` feature
+    /// is not X.509 certificate in DER.
+    InvalidCertificate,
+    /// The entry of the name has no SHA-256 digest for the JAR manifest of `jar` feature,
+    /// such as added before `ZipArchive::jar_manifest` enables the digest.
+    MissingDigest { name: String },
+}
+
+/// Stage of writing an entry where an error occurred.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// Reading the source file.
+    Source,
+    /// Compressing or encrypting the content.
+    Compression,
+    /// Writing local file header.
+    LocalHeader,
+    /// Writing the data following local file header.
+    ///
+    /// The header and data descriptor may be written at once with the data.
+    Data,
+    /// Writing central directory header.
+    CentralDirectory,
+}
+
+impl Display for Stage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Self::Source => write!(f, "reading source"),
+            Self::Compression => write!(f, "compressing"),
+            Self::LocalHeader => write!(f, "writing local file header"),
+            Self::Data => write!(f, "writing data"),
+            Self::CentralDirectory => write!(f, "writing central directory header"),
+        }
+    }
+}
+
+/// Kind of limit with its configured value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    /// Maximum number of entries.
+    Entries(usize),
+    /// Maximum total uncompressed size in bytes.
+    TotalSize(u64),
+    /// Maximum uncompressed size of each entry in bytes.
+    EntrySize(u64),
+    /// Maximum length of entry names in bytes.
+    NameLength(usize),
+}
+
+impl Display for Limit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Self::Entries(max) => write!(f, "more than {} entries", max),
+            Self::TotalSize(max) => write!(f, "total size over {} bytes", max),
+            Self::EntrySize(max) => write!(f, "entry size over {} bytes", max),
+            Self::NameLength(max) => write!(f, "name longer than {} bytes", max),
+        }
+    }
 }
 
 impl Display for Error {
@@ -20,11 +143,96 @@ impl Display for Error {
         match self {
             Self::IoError(error) => Display::fmt(error, f),
             Self::IntError(error) => Display::fmt(error, f),
+            Self::LimitExceeded { name, limit } => {
+                write!(f, "adding \"{}\" makes {}", name, limit)
+            }
+            Self::NameTooLong { name, length } => {
+                write!(f, "name \"{}\" of {} bytes is too long", name, length)
+            }
+            Self::ExtraFieldTooLong { name, length } => write!(
+                f,
+                "extra fields of \"{}\" of {} bytes are too long",
+                name, length
+            ),
+            Self::CaseCollision { name, existing } => {
+                write!(f, "name \"{}\" collides with \"{}\"", name, existing)
+            }
+            Self::CommentTooLong { length } => {
+                write!(f, "comment of {} bytes is too long", length)
+            }
+            Self::Entry {
+                name,
+                stage,
+                source,
+                ..
+            } => write!(f, "{} of \"{}\": {}", stage, name, source),
+            Self::Poisoned => write!(f, "archive is poisoned by a failed entry"),
+            Self::NonConforming {
+                name,
+                profile,
+                violation,
+            } => write!(f, "\"{}\" {} in {:?}", name, violation, profile),
+            Self::TimeOutOfRange { name } => {
+                write!(f, "modification time of \"{}\" is after 2107", name)
+            }
+            Self::OffsetUnreachable { name, offset } => {
+                write!(f, "data of \"{}\" can not start at offset {}", name, offset)
+            }
+            Self::OffsetOverflow => write!(f, "offset of the archive overflows"),
+            Self::InvalidCheckpoint => write!(f, "invalid checkpoint"),
+            Self::InvalidArchive => write!(f, "invalid archive"),
+            Self::DuplicateName { name } => write!(f, "duplicate entry \"{}\"", name),
+            Self::NoPassword => write!(f, "no password for encryption"),
+            Self::EncryptedStream { name } => {
+                write!(f, "encrypted entry \"{}\" can not be streamed", name)
+            }
+            Self::InvalidCertificate => write!(f, "invalid certificate"),
+            Self::MissingDigest { name } => write!(f, "no SHA-256 digest of \"{}\"", name),
+            Self::DeadlineExceeded { name } => {
+                write!(f, "\"{}\" is started after the deadline", name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::IoError(error) => Some(error),
+            Self::IntError(error) => Some(error),
+            Self::Entry { source, .. } => Some(source.as_ref()),
+            _ => None,
         }
     }
 }
 
-impl std::error::Error for Error {}
+impl Error {
+    /// Wrap I/O and integer conversion errors with the entry name and the stage.
+    pub(crate) fn in_entry(self, name: &str, stage: Stage) -> Error {
+        match self {
+            Error::IoError(_) | Error::IntError(_) => Error::Entry {
+                name: name.to_string(),
+                stage,
+                source: Box::new(self),
+                #[cfg(feature = "backtrace")]
+                backtrace: Box::new(std::backtrace::Backtrace::capture()),
+            },
+            error => error,
+        }
+    }
+
+    /// Backtrace where the error of an entry occurred.
+    ///
+    /// It is captured only if `RUST_BACKTRACE` or `RUST_LIB_BACKTRACE` is set,
+    /// see [`Backtrace::capture`](std::backtrace::Backtrace::capture).
+    #[cfg(feature = "backtrace")]
+    pub fn backtrace(&self) -> Option<&std::backtrace::Backtrace> {
+        match self {
+            Self::Entry { backtrace, .. } => Some(backtrace),
+            _ => None,
+        }
+    }
+}
 
 impl From<std::io::Error> for Error {
     fn from(error: std::io::Error) -> Self {
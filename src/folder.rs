@@ -0,0 +1,199 @@
+use crate::{DosAttributes, EntryOptions, EntryWriter, Level, Result, ZipArchive};
+use std::io::Write;
+use std::path::Path;
+
+/// Handle adding entries under a folder of the archive, created by [`ZipArchive::folder`].
+///
+/// Names given to the handle are relative to the folder, and options of the entries inherit
+/// the default options of the folder like those of
+/// [`ZipArchiveBuilder::options`](crate::ZipArchiveBuilder::options).
+/// The handle borrows the archive, so it is dropped before the archive is finished.
+pub struct Folder<'f, 'a, T: Write + ?Sized + 'a> {
+    zip: &'f mut ZipArchive<'a, T>,
+    /// Path of the folder ending with `/`, or empty for the root.
+    path: String,
+    options: EntryOptions,
+}
+
+impl<'a, T: Write + ?Sized + 'a> ZipArchive<'a, T> {
+    /// Start adding entries under the folder of `path`, adding its directory entry
+    /// unless it is already added.
+    ///
+    /// A trailing `/` is added to `path` if it lacks one.
+    ///
+    /// ```
+    /// # use zip_builder::{EntryOptions, Level, ZipArchive};
+    /// let mut buf = Vec::new();
+    /// let mut zip = ZipArchive::new(&mut buf);
+    /// let mut docs = zip.folder("docs")?.options(Level::High);
+    /// docs.push_entry("readme.txt", b"read me", EntryOptions::default())?;
+    /// docs.folder("images")?
+    ///     .push_entry("logo.png", b"\x89PNG", Level::Raw)?;
+    /// let summary = zip.finish()?;
+    /// let names: Vec<_> = summary.entries.iter().map(|entry| &entry.name[..]).collect();
+    /// assert_eq!(
+    ///     names,
+    ///     ["docs/", "docs/readme.txt", "docs/images/", "docs/images/logo.png"]
+    /// );
+    /// # Ok::<(), zip_builder::Error>(())
+    /// ```
+    pub fn folder(&mut self, path: &str) -> Result<Folder<'_, 'a, T>> {
+        let mut folder = Folder {
+            zip: self,
+            path: String::new(),
+            options: EntryOptions::default(),
+        };
+        folder.create(path)?;
+        Ok(folder)
+    }
+}
+
+impl<'f, 'a, T: Write + ?Sized + 'a> Folder<'f, 'a, T> {
+    /// Set the default options of entries in the folder, including its subfolders.
+    pub fn options<O: Into<EntryOptions>>(mut self, options: O) -> Self {
+        self.options = options.into().inherit(&self.options);
+        self
+    }
+
+    /// Path of the folder ending with `/`, without the
+    /// [`root_prefix`](crate::ZipArchiveBuilder::root_prefix).
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Name in the archive of the entry `name` in the folder.
+    pub fn name(&self, name: &str) -> String {
+        format!("{}{}", self.path, name)
+    }
+
+    /// Start adding entries under the subfolder of `path`, which inherits the default options.
+    pub fn folder(&mut self, path: &str) -> Result<Folder<'_, 'a, T>> {
+        let mut folder = Folder {
+            zip: &mut *self.zip,
+            path: self.path.clone(),
+            options: self.options,
+        };
+        folder.create(path)?;
+        Ok(folder)
+    }
+
+    /// Add a entry in the folder, see [`ZipArchive::push_entry`].
+    pub fn push_entry<O: Into<EntryOptions>>(
+        &mut self,
+        name: &str,
+        content: &[u8],
+        options: O,
+    ) -> Result<()> {
+        let options = options.into().inherit(&self.options);
+        self.zip.push_entry(&self.name(name), content, options)
+    }
+
+    /// Add a file of `path` as an entry in the folder, see [`ZipArchive::add_file`].
+    pub fn push_file<P: AsRef<Path>, O: Into<EntryOptions>>(
+        &mut self,
+        name: &str,
+        path: P,
+        options: O,
+    ) -> Result<()> {
+        let options = options.into().inherit(&self.options);
+        self.zip.push_file(&self.name(name), path.as_ref(), options)
+    }
+
+    /// Start writing an entry in the folder, see [`ZipArchive::start_entry`].
+    pub fn start_entry<O: Into<EntryOptions>>(
+        &mut self,
+        name: &str,
+        options: O,
+    ) -> Result<EntryWriter<'_, 'a, T>> {
+        let options = options.into().inherit(&self.options);
+        let name = self.name(name);
+        self.zip.start_entry(&name, options)
+    }
+
+    /// Append `path` to the path of the folder, and add the directory entry.
+    fn create(&mut self, path: &str) -> Result<()> {
+        self.path.push_str(path.trim_start_matches('/'));
+        if self.path.is_empty() {
+            return Ok(());
+        }
+        if !self.path.ends_with('/') {
+            self.path.push('/');
+        }
+        let prefixed = self.zip.prefixed(&self.path);
+        if self
+            .zip
+            .entries
+            .iter()
+            .any(|entry| entry.filename == prefixed)
+        {
+            return Ok(());
+        }
+        let directory = self
+            .options
+            .level(Level::Raw)
+            .dos_attributes(DosAttributes::DIRECTORY);
+        self.zip.push_entry(&self.path, &[], directory)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::verify::read_entries;
+    use crate::{verify, EntryOptions, Level, ZipArchiveBuilder};
+    use std::io::Write;
+
+    #[test]
+    fn it_works() {
+        let mut buf = Vec::new();
+        let mut zip = ZipArchiveBuilder::new()
+            .root_prefix("root/")
+            .build(&mut buf);
+        {
+            let mut src = zip.folder("/src").unwrap().options(Level::High);
+            assert_eq!(src.path(), "src/");
+            src.push_entry("lib.rs", &[b'a'; 100], EntryOptions::default())
+                .unwrap();
+            let mut nested = src.folder("a/b/").unwrap();
+            assert_eq!(nested.name("c.rs"), "src/a/b/c.rs");
+            nested
+                .push_entry("d.rs", &[b'd'; 100], EntryOptions::default())
+                .unwrap();
+            let mut writer = nested.start_entry("c.rs", Level::Raw).unwrap();
+            writer.write_all(b"streamed").unwrap();
+            writer.finish().unwrap();
+        }
+        // The directory entry is added once.
+        zip.folder("src")
+            .unwrap()
+            .push_entry("main.rs", b"main", Level::Raw)
+            .unwrap();
+        zip.folder("")
+            .unwrap()
+            .push_entry("top.txt", b"top", Level::Raw)
+            .unwrap();
+        let summary = zip.finish().unwrap();
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+        let names: Vec<_> = summary
+            .entries
+            .iter()
+            .map(|entry| &entry.name[..])
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "root/src/",
+                "root/src/lib.rs",
+                "root/src/a/b/",
+                "root/src/a/b/d.rs",
+                "root/src/a/b/c.rs",
+                "root/src/main.rs",
+                "root/top.txt"
+            ]
+        );
+        assert!(summary.entries[1].compressed_size < 100);
+        assert!(summary.entries[3].compressed_size < 100);
+        assert_eq!(summary.entries[4].compressed_size, 8);
+        let entries = read_entries(&buf).unwrap();
+        assert_eq!(entries[4].1, b"streamed");
+    }
+}
@@ -0,0 +1,340 @@
+use crate::{EntryInfo, Error, Level, Result, ZipArchive, SHA256};
+use std::io::Write;
+
+const CREATED_BY: &str = "zip-builder";
+
+const OID_DATA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x01];
+const OID_SIGNED_DATA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x02];
+const OID_SHA256: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01];
+const OID_RSA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01];
+
+fn base64(bytes: &[u8]) -> String {
+    const TABLE: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let value = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |value, (index, &byte)| {
+                value | (byte as u32) << (16 - index * 8)
+            });
+        for index in 0..4 {
+            if index <= chunk.len() {
+                encoded.push(TABLE[(value >> (18 - index * 6) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+fn sha256(bytes: &[u8]) -> String {
+    let mut hasher = SHA256::default();
+    hasher.write(bytes);
+    base64(&hasher.finish())
+}
+
+/// Header line of manifest, continued by lines starting with a space after 72 bytes.
+fn header(name: &str, value: &str) -> String {
+    let line = format!("{}: {}", name, value);
+    let mut wrapped = String::with_capacity(line.len() + line.len() / 36 + 2);
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 72 {
+            wrapped.push_str("\r\n ");
+            width = 1;
+        }
+        wrapped.push(c);
+        width += c.len_utf8();
+    }
+    wrapped.push_str("\r\n");
+    wrapped
+}
+
+/// Name headers and sections of manifest for the entries except directories and
+/// those in `META-INF/`.
+fn sections(entries: &[EntryInfo]) -> Result<Vec<(String, String)>> {
+    entries
+        .iter()
+        .filter(|entry| !entry.name.ends_with('/') && !entry.name.starts_with("META-INF/"))
+        .map(|entry| {
+            let digest = entry.sha256.ok_or_else(|| Error::MissingDigest {
+                name: entry.name.clone(),
+            })?;
+            let name = header("Name", &entry.name);
+            let section = format!("{}{}\r\n", name, header("SHA-256-Digest", &base64(&digest)));
+            Ok((name, section))
+        })
+        .collect()
+}
+
+fn manifest(entries: &[EntryInfo]) -> Result<String> {
+    let mut manifest = header("Manifest-Version", "1.0");
+    manifest.push_str(&header("Created-By", CREATED_BY));
+    manifest.push_str("\r\n");
+    manifest.extend(sections(entries)?.into_iter().map(|(_, section)| section));
+    Ok(manifest)
+}
+
+fn signature_file(entries: &[EntryInfo]) -> Result<String> {
+    let mut signature_file = header("Signature-Version", "1.0");
+    signature_file.push_str(&header(
+        "SHA-256-Digest-Manifest",
+        &sha256(manifest(entries)?.as_bytes()),
+    ));
+    signature_file.push_str(&header("Created-By", CREATED_BY));
+    signature_file.push_str("\r\n");
+    for (name, section) in sections(entries)? {
+        signature_file.push_str(&name);
+        signature_file.push_str(&header("SHA-256-Digest", &sha256(section.as_bytes())));
+        signature_file.push_str("\r\n");
+    }
+    Ok(signature_file)
+}
+
+fn der(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut element = vec![tag];
+    let length = content.len().to_be_bytes();
+    let skip = length.iter().take_while(|&&byte| byte == 0).count();
+    if content.len() < 0x80 {
+        element.push(content.len() as u8);
+    } else {
+        element.push(0x80 | (length.len() - skip) as u8);
+        element.extend_from_slice(&length[skip..]);
+    }
+    element.extend_from_slice(content);
+    element
+}
+
+/// Algorithm identifier with the null parameter.
+fn algorithm(oid: &[u8]) -> Vec<u8> {
+    der(0x30, &[der(0x06, oid), vec![0x05, 0x00]].concat())
+}
+
+/// Tag, content, the whole element and the rest of DER.
+type Element<'b> = (u8, &'b [u8], &'b [u8], &'b [u8]);
+
+/// Split the first DER element of `bytes`.
+fn element(bytes: &[u8]) -> Option<Element<'_>> {
+    let tag = *bytes.first()?;
+    let first = *bytes.get(1)? as usize;
+    let (length, header) = if first < 0x80 {
+        (first, 2)
+    } else {
+        let count = first & 0x7f;
+        let length = bytes
+            .get(2..2 + count)?
+            .iter()
+            .try_fold(0usize, |length, &byte| {
+                length.checked_mul(256).map(|length| length | byte as usize)
+            })?;
+        (length, 2 + count)
+    };
+    let end = header.checked_add(length)?;
+    let whole = bytes.get(..end)?;
+    Some((tag, &whole[header..], whole, &bytes[end..]))
+}
+
+/// Issuer and serial number of X.509 certificate in DER.
+fn issuer_and_serial_number(certificate: &[u8]) -> Option<Vec<u8>> {
+    let (_, certificate, _, _) = element(certificate)?;
+    let (_, fields, _, _) = element(certificate)?;
+    // Version is optional.
+    let rest = match element(fields)? {
+        (0xa0, _, _, rest) => rest,
+        _ => fields,
+    };
+    let (_, _, serial_number, rest) = element(rest)?;
+    let (_, _, _, rest) = element(rest)?;
+    let (_, _, issuer, _) = element(rest)?;
+    Some(der(0x30, &[issuer, serial_number].concat()))
+}
+
+/// PKCS #7 signed data without content, with the certificate and the signature.
+fn signature_block(certificate: &[u8], signer: &[u8], signature: &[u8]) -> Vec<u8> {
+    let signer_info = der(
+        0x30,
+        &[
+            der(0x02, &[1]),
+            signer.to_vec(),
+            algorithm(OID_SHA256),
+            algorithm(OID_RSA),
+            der(0x04, signature),
+        ]
+        .concat(),
+    );
+    let signed_data = der(
+        0x30,
+        &[
+            der(0x02, &[1]),
+            der(0x31, &algorithm(OID_SHA256)),
+            der(0x30, &der(0x06, OID_DATA)),
+            der(0xa0, certificate),
+            der(0x31, &signer_info),
+        ]
+        .concat(),
+    );
+    der(
+        0x30,
+        &[der(0x06, OID_SIGNED_DATA), der(0xa0, &signed_data)].concat(),
+    )
+}
+
+impl<'a, T: Write + ?Sized + 'a> ZipArchive<'a, T> {
+    /// Add `META-INF/MANIFEST.MF` with SHA-256 digests of the entries.
+    ///
+    /// Call it before adding entries. The manifest is generated during
+    /// [`finish`](ZipArchive::finish) but listed first in the central directory,
+    /// and [`digest`](ZipArchive::digest) is enabled. `finish` fails with
+    /// [`Error::MissingDigest`] if an entry has no digest, such as added before it or merged
+    /// from data of an unsupported method.
+    pub fn jar_manifest(self) -> Self {
+        self.digest()
+            .try_lazy_entry("META-INF/MANIFEST.MF", Level::Default, |entries| {
+                Ok(manifest(entries)?.into_bytes())
+            })
+    }
+
+    /// Add the manifest like [`jar_manifest`](ZipArchive::jar_manifest) and sign it.
+    ///
+    /// `META-INF/<name>.SF` and `META-INF/<name>.RSA` follow the manifest.
+    /// `certificate` is the X.509 certificate of the signer in DER, and `sign` returns
+    /// the RSA signature (PKCS #1 v1.5 with SHA-256) of the given bytes.
+    ///
+    /// Returns an error if the certificate cannot be parsed.
+    pub fn sign_jar<F>(self, name: &str, certificate: &[u8], sign: F) -> Result<Self>
+    where
+        F: FnOnce(&[u8]) -> Vec<u8> + Send + 'a,
+    {
+        let signer = match issuer_and_serial_number(certificate) {
+            Some(signer) => signer,
+            None => return self.or_poison(Err(Error::InvalidCertificate)),
+        };
+        let certificate = certificate.to_vec();
+        Ok(self
+            .jar_manifest()
+            .try_lazy_entry(
+                &format!("META-INF/{}.SF", name),
+                Level::Default,
+                |entries| Ok(signature_file(entries)?.into_bytes()),
+            )
+            .try_lazy_entry(
+                &format!("META-INF/{}.RSA", name),
+                Level::Default,
+                move |entries| {
+                    let signature = sign(signature_file(entries)?.as_bytes());
+                    Ok(signature_block(&certificate, &signer, &signature))
+                },
+            ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{base64, element, header, issuer_and_serial_number, signature_block};
+    use crate::verify::read_entries;
+    use crate::{verify, Error, Level, ZipArchive};
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+
+    /// Certificate with minimal structure: version, serial number 5, algorithm and issuer.
+    fn certificate() -> Vec<u8> {
+        let fields = [
+            &[0xa0, 0x03, 0x02, 0x01, 0x02][..],
+            &[0x02, 0x01, 0x05],
+            &[0x30, 0x00],
+            &[0x30, 0x02, 0x31, 0x00],
+        ]
+        .concat();
+        let tbs = [&[0x30, fields.len() as u8][..], &fields].concat();
+        [&[0x30, tbs.len() as u8][..], &tbs].concat()
+    }
+
+    #[test]
+    fn it_works() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+        let long = header("Name", &"a".repeat(100));
+        assert_eq!(
+            long.split("\r\n").map(str::len).collect::<Vec<_>>(),
+            [72, 35, 0]
+        );
+
+        let signer = issuer_and_serial_number(&certificate()).unwrap();
+        assert_eq!(
+            signer,
+            [0x30, 0x07, 0x30, 0x02, 0x31, 0x00, 0x02, 0x01, 0x05]
+        );
+        let block = signature_block(&certificate(), &signer, &[0xff; 300]);
+        let (tag, content, whole, rest) = element(&block).unwrap();
+        assert_eq!((tag, whole.len(), rest.len()), (0x30, block.len(), 0));
+        assert!(content.windows(300).any(|window| window == [0xff; 300]));
+
+        let signed = Arc::new(Mutex::new(Vec::new()));
+        let captured = signed.clone();
+        let mut buf = Vec::new();
+        let summary = ZipArchive::new(&mut buf)
+            .sign_jar("CERT", &certificate(), move |bytes| {
+                *captured.lock().unwrap() = bytes.to_vec();
+                vec![0; 256]
+            })
+            .unwrap()
+            .add_entry("dir/", b"", Level::Raw)
+            .unwrap()
+            .add_entry("a.txt", b"content", Level::Default)
+            .unwrap()
+            .finish()
+            .unwrap();
+        let names: Vec<_> = summary
+            .entries
+            .iter()
+            .map(|entry| &entry.name[..])
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "META-INF/MANIFEST.MF",
+                "META-INF/CERT.SF",
+                "META-INF/CERT.RSA",
+                "dir/",
+                "a.txt"
+            ]
+        );
+        let signed = String::from_utf8(signed.lock().unwrap().clone()).unwrap();
+        assert!(signed.starts_with("Signature-Version: 1.0\r\nSHA-256-Digest-Manifest: "));
+        assert!(signed.contains("\r\n\r\nName: a.txt\r\nSHA-256-Digest: "));
+        assert!(!signed.contains("dir/"));
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+
+        let mut buf = Vec::new();
+        let result = ZipArchive::new(&mut buf).sign_jar("CERT", b"", |_| Vec::new());
+        assert!(matches!(result, Err(Error::InvalidCertificate)));
+        drop(result);
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn missing_digest() {
+        let mut buf = Vec::new();
+        let mut zip = ZipArchive::new(&mut buf).jar_manifest();
+        let mut writer = zip.start_entry("streamed.txt", Level::Default).unwrap();
+        writer.write_all(b"streamed").unwrap();
+        writer.finish().unwrap();
+        zip.finish().unwrap();
+        let manifest = &read_entries(&buf).unwrap()[0].1;
+        assert!(String::from_utf8_lossy(manifest).contains("Name: streamed.txt\r\n"));
+
+        let result = ZipArchive::new(&mut Vec::new())
+            .add_entry("early.txt", b"early", Level::Default)
+            .unwrap()
+            .jar_manifest()
+            .finish();
+        assert!(matches!(
+            result,
+            Err(Error::MissingDigest { name }) if name == "early.txt"
+        ));
+    }
+}
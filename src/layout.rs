@@ -0,0 +1,207 @@
+use crate::{ArchiveSummary, EntryOptions, Metadata, Result, ZipArchive};
+use std::fmt::Write as _;
+use std::io::Write;
+
+/// Entry kept until the entries to be placed first are written.
+pub(crate) struct Deferred {
+    name: String,
+    content: Vec<u8>,
+    options: EntryOptions,
+    metadata: Metadata,
+}
+
+impl ArchiveSummary {
+    /// Byte ranges of entries in JSON, for clients fetching entries by range requests.
+    ///
+    /// Ranges are `[start, end)` of the local file header and of the data of each entry,
+    /// in the order of the central directory. Data descriptor follows the data range
+    /// if the entry has it.
+    ///
+    /// ```
+    /// # use zip_builder::{Level, ZipArchive};
+    /// let mut buf = Vec::new();
+    /// let summary = ZipArchive::new(&mut buf)
+    ///     .add_entry("a.txt", b"a", Level::Raw)?
+    ///     .finish()?;
+    /// assert_eq!(
+    ///     summary.layout_json(),
+    ///     r#"{"entries":[{"name":"a.txt","header":[0,35],"data":[35,36]}]}"#
+    /// );
+    /// # Ok::<(), zip_builder::Error>(())
+    /// ```
+    pub fn layout_json(&self) -> String {
+        let mut json = String::from("{\"entries\":[");
+        for (index, entry) in self.entries.iter().enumerate() {
+            if index > 0 {
+                json.push(',');
+            }
+            json.push_str("{\"name\":");
+            push_json_string(&mut json, &entry.name);
+            let data_end = entry.data_offset + entry.compressed_size;
+            let _ = write!(
+                json,
+                ",\"header\":[{},{}],\"data\":[{},{}]}}",
+                entry.offset, entry.data_offset, entry.data_offset, data_end
+            );
+        }
+        json.push_str("]}");
+        json
+    }
+}
+
+/// Append `value` as a JSON string.
+fn push_json_string(json: &mut String, value: &str) {
+    json.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(json, "\\u{:04x}", c as u32);
+            }
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+}
+
+impl<'a, T: Write + ?Sized + 'a> ZipArchive<'a, T> {
+    /// Keep the entry if it is to be written after the entries to be placed first.
+    ///
+    /// Returns whether the entry is deferred.
+    pub(crate) fn defer(
+        &mut self,
+        name: &str,
+        content: &[u8],
+        options: EntryOptions,
+        metadata: &Metadata,
+    ) -> bool {
+        if self.first.is_empty() || self.first.iter().any(|first| first == name) {
+            return false;
+        }
+        self.record_case(name);
+        self.deferred_size += content.len() as u64;
+        self.deferred.push(Deferred {
+            name: name.to_string(),
+            content: content.to_vec(),
+            options,
+            metadata: metadata.clone(),
+        });
+        true
+    }
+
+    /// Mark the entry as written, and write deferred entries after the last one to be placed first.
+    pub(crate) fn place(&mut self, name: &str) -> Result<()> {
+        match self.first.iter().position(|first| first == name) {
+            Some(index) => self.first.swap_remove(index),
+            None => return Ok(()),
+        };
+        if self.first.is_empty() {
+            self.write_deferred()?;
+        }
+        Ok(())
+    }
+
+    /// Write deferred entries in the order they are added.
+    pub(crate) fn write_deferred(&mut self) -> Result<()> {
+        self.first.clear();
+        for deferred in std::mem::take(&mut self.deferred) {
+            self.deferred_size -= deferred.content.len() as u64;
+            self.write_entry(
+                &deferred.name,
+                &deferred.content,
+                deferred.options,
+                &deferred.metadata,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{verify, Error, Level, Limit, ZipArchiveBuilder};
+
+    #[test]
+    fn it_works() {
+        let mut buf = Vec::new();
+        let summary = ZipArchiveBuilder::new()
+            .place_first(["index.json", "manifest.txt"])
+            .build(&mut buf)
+            .add_entry("a.txt", b"a", Level::Raw)
+            .unwrap()
+            .add_entry("manifest.txt", b"manifest", Level::Raw)
+            .unwrap()
+            .add_entry("b.txt", b"b", Level::Raw)
+            .unwrap()
+            .add_entry("index.json", b"{}", Level::Raw)
+            .unwrap()
+            .add_entry("c.txt", b"c", Level::Raw)
+            .unwrap()
+            .finish()
+            .unwrap();
+        let names: Vec<_> = summary
+            .entries
+            .iter()
+            .map(|entry| &entry.name[..])
+            .collect();
+        assert_eq!(
+            names,
+            ["manifest.txt", "index.json", "a.txt", "b.txt", "c.txt"]
+        );
+        assert_eq!(&buf[30..42], b"manifest.txt");
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn layout_json() {
+        let mut buf = Vec::new();
+        let summary = ZipArchiveBuilder::new()
+            .memory_budget(100)
+            .build(&mut buf)
+            .add_entry("quote\"\\\n.txt", b"raw", Level::Raw)
+            .unwrap()
+            .add_entry("streamed.txt", &[b'a'; 1000], Level::High)
+            .unwrap()
+            .finish()
+            .unwrap();
+        let second = &summary.entries[1];
+        assert_eq!(second.offset, 30 + 12 + 3);
+        assert_eq!(second.data_offset, second.offset + 30 + 12);
+        assert_eq!(
+            &buf[second.offset as usize + 30..second.data_offset as usize],
+            b"streamed.txt"
+        );
+        let json = format!(
+            r#"{{"entries":[{{"name":"quote\"\\\n.txt","header":[0,42],"data":[42,45]}},{{"name":"streamed.txt","header":[45,87],"data":[87,{}]}}]}}"#,
+            87 + second.compressed_size
+        );
+        assert_eq!(summary.layout_json(), json);
+    }
+
+    #[test]
+    fn missing() {
+        let mut buf = Vec::new();
+        let mut zip = ZipArchiveBuilder::new()
+            .place_first(["index.json"])
+            .max_entries(2)
+            .build(&mut buf)
+            .add_entry("a.txt", b"a", Level::Raw)
+            .unwrap()
+            .add_entry("b.txt", b"b", Level::Raw)
+            .unwrap();
+        assert!(matches!(
+            zip.push_entry("c.txt", b"c", Level::Raw),
+            Err(Error::LimitExceeded {
+                limit: Limit::Entries(2),
+                ..
+            })
+        ));
+        let summary = zip.finish().unwrap();
+        assert_eq!(summary.entries.len(), 2);
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+    }
+}
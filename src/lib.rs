@@ -3,14 +3,16 @@
 //! # ZIP generating steps
 //!
 //! 1. Create [`ZipArchive`] structure by [`new`](ZipArchive::new) method
+//!    (or by [`ZipArchiveBuilder`] to give archive-wide options)
 //! 2. Add zip entry by [`add_entry`](ZipArchive::add_entry) method
-//! 3. Write ending data by [`flush`](ZipArchive::flush) method.
+//! 3. Write ending data by [`finish`](ZipArchive::finish) method.
 //!
 //! # Note
 //!
 //! - If the return value of a method is an error, the output data is incomplete.
-//! - If you do not call `flush` method, [`drop`](ZipArchive::drop) write ending data.
+//! - If you do not call `finish` method, [`drop`](ZipArchive::drop) write ending data.
 //! - Failure of writing in `drop` will cause panic.
+//! - With `verify` feature, [`verify`] function checks consistency of generated archive.
 //!
 //! # Example
 //!
@@ -25,30 +27,156 @@
 //!     let zip_builder = ZipArchive::new(&mut file)
 //!         .add_entry("file1.txt", b"content", Level::Low)?
 //!         .add_entry("file2.txt", b"content", Level::Raw)?
-//!         .flush();
+//!         .finish()?;
 //!
 //!     Ok(())
 //! }
 //! ```
 
-use std::convert::TryFrom;
-use std::io::Write;
+use std::collections::HashMap;
+use std::io::{IoSlice, Read, Write};
 use std::ops::Drop;
+use std::path::Path;
 use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// Value of a 32-bit field, or the mark that it is in Zip64 extended information.
+fn zip64_field(value: u64, threshold: u64) -> u32 {
+    if value >= threshold {
+        u32::MAX
+    } else {
+        value as u32
+    }
+}
+/// Add `length` to `offset` in the archive, failing instead of wrapping around.
+fn advance(offset: &mut u64, length: u64) -> Result<()> {
+    *offset = offset.checked_add(length).ok_or(Error::OffsetOverflow)?;
+    Ok(())
+}
+
+/// Write all of `slices` by [`Write::write_vectored`].
+///
+/// Writers without vectored writes write one slice per call by the default implementation.
+fn write_all_vectored<W: Write>(output: &mut W, mut slices: &mut [IoSlice<'_>]) -> Result<()> {
+    IoSlice::advance_slices(&mut slices, 0);
+    while !slices.is_empty() {
+        match output.write_vectored(slices) {
+            Ok(0) => return Err(std::io::Error::from(std::io::ErrorKind::WriteZero).into()),
+            Ok(written) => IoSlice::advance_slices(&mut slices, written),
+            Err(error) if error.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(error) => return Err(error.into()),
+        }
+    }
+    Ok(())
+}
+
 extern crate deflate;
-use deflate::deflate_bytes_conf;
 use deflate::Compression;
-mod crc32;
+pub mod crc32;
 use crc32::CRC32;
-mod time;
-use time::DateTime;
+mod sha256;
+use sha256::SHA256;
+mod entropy;
 mod error;
-pub use error::Error;
+pub use entropy::estimate_entropy;
+mod time;
+pub use error::{Error, Limit, Stage};
+pub use time::{DateTime, ParseDateTimeError, TimeOverflow, TimeRounding};
+mod directory;
+#[cfg(feature = "aes")]
+mod encryption;
+#[cfg(feature = "aes")]
+pub use encryption::{wrap_encrypted, KeyDerivation, OsRandom, Pbkdf2, RandomSource};
+#[cfg(feature = "jar")]
+mod jar;
+mod layout;
+#[cfg(feature = "json-manifest")]
+mod manifest;
+mod metadata;
+mod name;
+use metadata::Metadata;
+pub use metadata::MetadataPolicy;
+pub use name::entry_name_of_path;
+#[cfg(feature = "ooxml")]
+mod ooxml;
+#[cfg(feature = "ooxml")]
+pub use ooxml::{Cell, Workbook};
+mod options;
+mod parallel;
+mod sparse;
+mod spec;
+mod spool;
+use spool::Spooled;
+mod folder;
+mod stream;
+#[cfg(feature = "tar")]
+mod tar;
+mod update;
+mod writer;
+pub use folder::Folder;
+#[cfg(feature = "unicode-normalization")]
+pub use options::Normalization;
+pub use options::{DosAttributes, EntryOptions, Padding, Strategy, Unicode};
+pub use spec::EntrySpec;
+pub use writer::{Abort, EntryWriter};
+mod builder;
+use builder::Config;
+pub use builder::{
+    CaseCollision, DirectoryOrder, Offsets, PoorCompression, ZipArchiveBuilder, ZipConfig,
+};
+mod policy;
+mod profile;
+pub use policy::{ApkPolicy, CompressionPolicy, ExtensionPolicy};
+pub use profile::{Profile, Violation};
+mod observer;
+pub use observer::{ArchiveStats, Observer};
+mod atomic;
+mod batch;
+mod central;
+pub use central::CentralDirectoryWriter;
+mod checkpoint;
+mod concat;
+mod counting;
+mod deadline;
+pub use checkpoint::Checkpoint;
+pub use counting::CountingWriter;
+#[cfg(any(feature = "verify", test))]
+mod merge;
+pub mod raw;
+#[cfg(any(feature = "verify", test))]
+pub use merge::{merge, DuplicateName};
+#[cfg(any(feature = "verify", test))]
+mod recompress;
+#[cfg(any(feature = "verify", test))]
+pub use recompress::recompress;
+mod rollback;
+pub use rollback::Truncate;
+mod retry;
+pub use retry::{RetryPolicy, RetryWriter};
+mod tee;
+mod trailer;
+pub use tee::Tee;
+pub use trailer::find_trailer;
+mod chunked;
+pub use chunked::ChunkedOutput;
+mod signing;
+use signing::Signed;
+pub use signing::Signer;
+mod throttle;
+pub use throttle::RateLimited;
+#[cfg(all(feature = "positioned", any(unix, windows)))]
+mod positioned;
+#[cfg(all(feature = "positioned", any(unix, windows)))]
+pub use positioned::PositionedWriter;
+#[cfg(any(feature = "verify", test))]
+mod verify;
+#[cfg(any(feature = "verify", test))]
+pub use verify::{verify, Inconsistency};
 
 pub type Result<T> = std::result::Result<T, Error>;
 
 /// Represents complression level.
-#[derive(Eq, PartialEq, Clone, Copy)]
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
 pub enum Level {
     /// Not compress. Store raw data.
     Raw,
@@ -78,14 +206,71 @@ impl Level {
     }
 }
 
+/// Information of an entry written to the zip.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EntryInfo {
+    /// Name of the entry.
+    pub name: String,
+    /// CRC-32 of the uncompressed content.
+    pub checksum: u32,
+    /// Size of the stored data.
+    pub compressed_size: u64,
+    /// Size of the content.
+    pub uncompressed_size: u64,
+    /// Offset of the local file header.
+    pub offset: u64,
+    /// Offset of the data after the local file header.
+    pub data_offset: u64,
+    /// SHA-256 of the content if [`digest`](ZipArchive::digest) is enabled.
+    pub sha256: Option<[u8; 32]>,
+}
+
+/// Summary of a finished archive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ArchiveSummary {
+    /// Information of all entries in the order of the central directory.
+    pub entries: Vec<EntryInfo>,
+    /// Size of the whole archive.
+    pub size: u64,
+    /// Total uncompressed size of entries.
+    pub uncompressed_size: u64,
+    /// Total compressed size of entries.
+    pub compressed_size: u64,
+    /// Size of the central directory.
+    pub central_directory_size: u64,
+    /// Time from creation of the archive to the end of [`finish`](ZipArchive::finish).
+    pub elapsed: Duration,
+    /// Signature returned by [`Signer::finish`] if [`signer`](ZipArchive::signer) is set.
+    pub signature: Option<Vec<u8>>,
+    /// Names of entries skipped after the [`deadline`](ZipArchiveBuilder::deadline),
+    /// in the order they were added.
+    pub skipped: Vec<String>,
+}
+
+#[derive(Clone)]
 struct ZipEntry {
+    flags: u16,
     method: u16,
     timestamp: u32,
     checksum: u32,
-    compressed_size: u32,
-    uncompressed_size: u32,
-    offset: u32,
+    compressed_size: u64,
+    uncompressed_size: u64,
+    offset: u64,
+    data_offset: u64,
     filename: String,
+    /// Name written in headers if it is not UTF-8, when `filename` is lossy.
+    raw_name: Option<Vec<u8>>,
+    sha256: Option<[u8; 32]>,
+    external_attributes: u32,
+    /// Extra fields of metadata written in both headers.
+    extra: Vec<u8>,
+    /// Host system in upper byte of version made by.
+    host: u8,
+    /// Zip64 extended information is in local file header regardless of the sizes,
+    /// which are unknown when a streamed entry starts.
+    zip64: bool,
 }
 
 impl ZipEntry {
@@ -93,23 +278,127 @@ impl ZipEntry {
         filename: &str,
         uncompressed_content: &[u8],
         compressed_content: &[u8],
-        method: u16,
-        offset: u32,
+        options: &EntryOptions,
+        offset: u64,
     ) -> ZipEntry {
         let mut hasher = CRC32::default();
         hasher.write(uncompressed_content);
         ZipEntry {
-            method,
-            timestamp: DateTime::now().dos_time(),
+            flags: options.flags(filename),
+            method: options.method(),
+            timestamp: options.timestamp(),
             checksum: hasher.finish(),
-            compressed_size: compressed_content.len() as u32,
-            uncompressed_size: uncompressed_content.len() as u32,
-            offset: offset,
-            filename: String::from_str(filename).unwrap(),
+            compressed_size: compressed_content.len() as u64,
+            uncompressed_size: uncompressed_content.len() as u64,
+            offset,
+            data_offset: offset,
+            filename: options.entry_name(filename).into_owned(),
+            raw_name: None,
+            sha256: None,
+            external_attributes: options.external_attributes(),
+            extra: options.time_extra(),
+            host: 0,
+            zip64: false,
+        }
+    }
+
+    /// Zip64 extended information for local file header.
+    ///
+    /// Both sizes are recorded if either reaches `threshold`, or always for streamed entries.
+    fn local_extra(&self, threshold: u64) -> Vec<u8> {
+        if !self.zip64 && self.compressed_size < threshold && self.uncompressed_size < threshold {
+            return Vec::new();
+        }
+        let (uncompressed_size, compressed_size) = self.local_sizes();
+        let mut extra = Vec::with_capacity(20);
+        extra.extend_from_slice(&1u16.to_le_bytes());
+        extra.extend_from_slice(&16u16.to_le_bytes());
+        extra.extend_from_slice(&uncompressed_size.to_le_bytes());
+        extra.extend_from_slice(&compressed_size.to_le_bytes());
+        extra
+    }
+
+    fn name_bytes(&self) -> &[u8] {
+        self.raw_name.as_deref().unwrap_or(self.filename.as_bytes())
+    }
+
+    fn check_extra(&self, extra: &[u8]) -> Result<()> {
+        if extra.len() > u16::MAX as usize {
+            return Err(Error::ExtraFieldTooLong {
+                name: self.filename.clone(),
+                length: extra.len(),
+            });
+        }
+        Ok(())
+    }
+
+    fn has_data_descriptor(&self) -> bool {
+        self.flags & 8 != 0
+    }
+
+    /// Uncompressed and compressed sizes in local file header, which are zeros
+    /// when data descriptor follows.
+    fn local_sizes(&self) -> (u64, u64) {
+        if self.has_data_descriptor() {
+            (0, 0)
+        } else {
+            (self.uncompressed_size, self.compressed_size)
+        }
+    }
+
+    /// Zip64 extended information for central directory header.
+    ///
+    /// Only the fields which reach `threshold` are recorded.
+    fn central_extra(&self, threshold: u64) -> Vec<u8> {
+        let fields: Vec<u64> = [self.uncompressed_size, self.compressed_size, self.offset]
+            .iter()
+            .filter(|&&value| value >= threshold)
+            .copied()
+            .collect();
+        if fields.is_empty() {
+            return Vec::new();
+        }
+        let mut extra = Vec::with_capacity(4 + fields.len() * 8);
+        extra.extend_from_slice(&1u16.to_le_bytes());
+        extra.extend_from_slice(&(fields.len() as u16 * 8).to_le_bytes());
+        for value in fields {
+            extra.extend_from_slice(&value.to_le_bytes());
+        }
+        extra
+    }
+
+    /// Version needed to extract in units of 0.1 of the specification.
+    ///
+    /// 1.0 for stored files, 2.0 for deflate and directories, 4.5 for Zip64
+    /// and 6.3 for PPMd. The same value is used in both headers of the entry.
+    fn version_needed(&self, threshold: u64) -> u16 {
+        let version = match self.method {
+            0 if !self.filename.ends_with('/') && !self.has_data_descriptor() => 10,
+            99 => 51,
+            98 => 63,
+            _ => 20,
+        };
+        if self.local_extra(threshold).is_empty() && self.central_extra(threshold).is_empty() {
+            version
+        } else {
+            version.max(45)
+        }
+    }
+
+    fn info(&self) -> EntryInfo {
+        EntryInfo {
+            name: self.filename.clone(),
+            checksum: self.checksum,
+            compressed_size: self.compressed_size,
+            uncompressed_size: self.uncompressed_size,
+            offset: self.offset,
+            data_offset: self.data_offset,
+            sha256: self.sha256,
         }
     }
 }
 
+/// The state stays `Processing` when writing fails, which poisons the archive.
 #[derive(Eq, PartialEq)]
 enum ZipState {
     Processing,
@@ -118,135 +407,1602 @@ enum ZipState {
 }
 
 /// The main struct you will need to use in this library.
-
-pub struct ZipArchive<'a, T: Write + 'a> {
+///
+/// It is [`Send`] when the writer is, so it can be moved to another thread
+/// like a scoped thread or a worker of a thread pool while building.
+/// The writer can be a trait object, see [`DynZipArchive`].
+pub struct ZipArchive<'a, T: Write + ?Sized + 'a> {
     state: ZipState,
     output: &'a mut T,
     entries: Vec<ZipEntry>,
-    offset: u32,
+    offset: u64,
+    config: Config,
+    digests: HashMap<[u8; 32], usize>,
+    folded_names: HashMap<String, String>,
+    first: Vec<String>,
+    deferred: Vec<layout::Deferred>,
+    deferred_size: u64,
+    lazy_entries: Vec<LazyEntry<'a>>,
+    total_size: u64,
+    started: Instant,
+    signer: Option<signing::Signing<'a>>,
+    /// Trailer framed for the end of the comment by `finish_with_trailer`.
+    trailer: Option<Vec<u8>>,
+    /// Names of entries skipped after the deadline.
+    skipped: Vec<String>,
+    batch: Option<Vec<u8>>,
+}
+
+/// [`ZipArchive`] writing to a trait object, whose type does not depend on the writer.
+///
+/// For plugin systems and other code passing archives across dynamic boundaries.
+/// Any writer which is [`Send`] coerces to the trait object.
+///
+/// ```
+/// # use zip_builder::{DynZipArchive, Level, ZipArchive};
+/// fn add_readme(zip: &mut DynZipArchive<'_>) -> zip_builder::Result<()> {
+///     zip.push_entry("readme.txt", b"read me", Level::Default)
+/// }
+///
+/// let mut output: Box<dyn std::io::Write + Send> = Box::new(Vec::new());
+/// let mut zip: DynZipArchive = ZipArchive::new(&mut *output);
+/// add_readme(&mut zip)?;
+/// zip.finish()?;
+/// # Ok::<(), zip_builder::Error>(())
+/// ```
+pub type DynZipArchive<'a> = ZipArchive<'a, dyn Write + Send + 'a>;
+
+type Generator<'a> = Box<dyn FnOnce(&[EntryInfo]) -> Result<Vec<u8>> + Send + 'a>;
+
+struct LazyEntry<'a> {
+    name: String,
+    options: EntryOptions,
+    generator: Generator<'a>,
+    position: usize,
 }
 
-impl<'a, T: Write + 'a> ZipArchive<'a, T> {
+impl<'a, T: Write + ?Sized + 'a> ZipArchive<'a, T> {
     /// Create a new [`ZipArchive`] structure.
+    ///
+    /// Use [`ZipArchiveBuilder`] to set archive-wide options.
     pub fn new(output: &'a mut T) -> ZipArchive<'a, T> {
+        ZipArchive::with_config(output, Config::default())
+    }
+
+    fn with_config(output: &'a mut T, config: Config) -> ZipArchive<'a, T> {
+        let first = config.place_first.clone();
+        let offset = match config.base_offset {
+            Some((length, Offsets::Absolute)) => length,
+            _ => 0,
+        };
         ZipArchive {
             state: ZipState::Breathe,
             output,
             entries: Vec::<ZipEntry>::new(),
-            offset: 0,
+            offset,
+            config,
+            digests: HashMap::new(),
+            folded_names: HashMap::new(),
+            first,
+            deferred: Vec::new(),
+            deferred_size: 0,
+            lazy_entries: Vec::new(),
+            total_size: 0,
+            started: Instant::now(),
+            signer: None,
+            trailer: None,
+            skipped: Vec::new(),
+            batch: None,
         }
     }
 
-    fn pk0304(output: &mut T, entry: &ZipEntry) -> Result<u32> {
-        let mut write_size = output.write(&0x04034b50u32.to_le_bytes())?;
-        write_size += output.write(&20u16.to_le_bytes())?;
-        write_size += output.write(&2048u16.to_le_bytes())?;
-        write_size += output.write(&entry.method.to_le_bytes())?;
-        write_size += output.write(&entry.timestamp.to_le_bytes())?;
-        write_size += output.write(&entry.checksum.to_le_bytes())?;
-        write_size += output.write(&entry.compressed_size.to_le_bytes())?;
-        write_size += output.write(&entry.uncompressed_size.to_le_bytes())?;
-        write_size += output.write(&u16::try_from(entry.filename.len())?.to_le_bytes())?;
-        write_size += output.write(&0u16.to_le_bytes())?;
-        write_size += output.write(entry.filename.as_bytes())?;
-        Ok(u32::try_from(write_size)?)
+    /// Compute SHA-256 of each content.
+    ///
+    /// The digest is available in [`EntryInfo`] returned by [`entries`](ZipArchive::entries).
+    pub fn digest(mut self) -> Self {
+        self.config.digest = true;
+        self
+    }
+
+    /// Snapshot of the archive-level configuration.
+    ///
+    /// Options set on the archive like [`deduplicate`](ZipArchive::deduplicate)
+    /// are included, so another archive can be built the same way.
+    pub fn config(&self) -> ZipConfig {
+        ZipConfig::from_config(self.config.clone())
+    }
+
+    /// Information of the entries added so far.
+    pub fn entries(&self) -> impl Iterator<Item = EntryInfo> + '_ {
+        self.entries.iter().map(ZipEntry::info)
+    }
+
+    /// Enable deduplication of entries by content.
+    ///
+    /// When the content is identical to an entry added before, only central directory header
+    /// pointing at the existing data is written. Encrypted entries and entries at
+    /// [`fixed_offset`](EntryOptions::fixed_offset) neither share the data of other entries
+    /// nor are shared.
+    pub fn deduplicate(mut self) -> Self {
+        self.config.deduplicate = true;
+        self
+    }
+
+    fn pk0304<W: Write>(
+        output: &mut W,
+        entry: &ZipEntry,
+        extra: &[u8],
+        threshold: u64,
+    ) -> Result<u64> {
+        let zip64 = entry.local_extra(threshold);
+        let (uncompressed_size, compressed_size) = if zip64.is_empty() {
+            let (uncompressed_size, compressed_size) = entry.local_sizes();
+            (uncompressed_size as u32, compressed_size as u32)
+        } else {
+            (raw::ZIP64_MARK, raw::ZIP64_MARK)
+        };
+        let extra = [zip64.as_slice(), &entry.extra, extra].concat();
+        entry.check_extra(&extra)?;
+        let header = raw::LocalFileHeader {
+            version_needed: entry.version_needed(threshold),
+            flags: entry.flags,
+            method: entry.method,
+            timestamp: entry.timestamp,
+            crc32: if entry.has_data_descriptor() {
+                0
+            } else {
+                entry.checksum
+            },
+            compressed_size,
+            uncompressed_size,
+            filename: entry.name_bytes(),
+            extra: &extra,
+        };
+        header.write_to(output)?;
+        Ok(header.size() as u64)
     }
 
     /// Add a entry to the zip.
     ///
-    /// Level means compression level.
-    pub fn add_entry(mut self, name: &str, content: &[u8], level: Level) -> Result<Self> {
+    /// Options means compression level and so on. [`Level`] is also accepted.
+    pub fn add_entry<O: Into<EntryOptions>>(
+        mut self,
+        name: &str,
+        content: &[u8],
+        options: O,
+    ) -> Result<Self> {
+        let result = self.push_entry(name, content, options);
+        self.or_poison(result)
+    }
+
+    /// Add a file of `path` as an entry.
+    ///
+    /// Metadata of the file is stored according to [`EntryOptions::metadata`].
+    pub fn add_file<P: AsRef<Path>, O: Into<EntryOptions>>(
+        mut self,
+        name: &str,
+        path: P,
+        options: O,
+    ) -> Result<Self> {
+        let result = self.push_file(name, path.as_ref(), options.into());
+        self.or_poison(result)
+    }
+
+    fn push_file(&mut self, name: &str, path: &Path, options: EntryOptions) -> Result<()> {
+        self.check_poisoned()?;
+        if self.overdue(name) {
+            return Ok(());
+        }
+        let source = |error: std::io::Error| Error::from(error).in_entry(name, Stage::Source);
+        let content = sparse::read(path).map_err(source)?;
+        let policy = options.inherit(&self.config.defaults).metadata_policy();
+        let metadata = Metadata::new(&std::fs::metadata(path).map_err(source)?, policy);
+        let options = match metadata.modified {
+            Some(modified) => options.modified(modified),
+            None => options,
+        };
+        if self.unchanged(name, &content) {
+            return Ok(());
+        }
+        self.check_limits(name, content.len(), None)?;
+        self.check_options(name, options)?;
+        if self.defer(name, &content, options, &metadata) {
+            return Ok(());
+        }
+        self.state = ZipState::Processing;
+        self.write_entry(name, &content, options, &metadata)?;
+        self.place(name)?;
+        if self.config.auto_flush {
+            self.output.flush()?;
+        }
+        self.state = ZipState::Breathe;
+        Ok(())
+    }
+
+    /// The archive is dropped on error, which must not write ending data.
+    fn or_poison(mut self, result: Result<()>) -> Result<Self> {
+        match result {
+            Ok(()) => Ok(self),
+            Err(error) => {
+                self.state = ZipState::Processing;
+                Err(error)
+            }
+        }
+    }
+
+    /// Add a entry to the zip without consuming the archive.
+    ///
+    /// If compression or writing fails in the middle of the entry, the archive is poisoned
+    /// and later calls including [`finish`](ZipArchive::finish) fail with [`Error::Poisoned`].
+    /// An error of limits leaves the archive as it was.
+    pub fn push_entry<O: Into<EntryOptions>>(
+        &mut self,
+        name: &str,
+        content: &[u8],
+        options: O,
+    ) -> Result<()> {
+        self.push_entry_with(name, content, options.into(), &Metadata::default())
+    }
+
+    fn push_entry_with(
+        &mut self,
+        name: &str,
+        content: &[u8],
+        options: EntryOptions,
+        metadata: &Metadata,
+    ) -> Result<()> {
+        self.check_poisoned()?;
+        if self.overdue(name) || self.unchanged(name, content) {
+            return Ok(());
+        }
+        self.check_limits(name, content.len(), None)?;
+        self.check_options(name, options)?;
+        if self.defer(name, content, options, metadata) {
+            return Ok(());
+        }
         self.state = ZipState::Processing;
-        if let Some(compression) = level.compression() {
-            let compressed_body = deflate_bytes_conf(content, compression);
-            let entry = ZipEntry::new(name, content, &compressed_body, level.method(), self.offset);
-            self.offset += Self::pk0304(self.output, &entry)? as u32;
-            self.offset += self.output.write(compressed_body.as_slice())? as u32;
+        self.write_entry(name, content, options, metadata)?;
+        self.place(name)?;
+        if self.config.auto_flush {
+            self.output.flush()?;
+        }
+        self.state = ZipState::Breathe;
+        Ok(())
+    }
+
+    fn check_poisoned(&self) -> Result<()> {
+        match self.state {
+            ZipState::Processing => Err(Error::Poisoned),
+            _ => Ok(()),
+        }
+    }
+
+    /// Replace the entry of the same name added before.
+    ///
+    /// The new content is written after the existing entries and the old data is left
+    /// as dead space, but the central directory lists the new entry at the position of
+    /// the old one. So an entry like manifest can be written last but listed first.
+    /// If no entry has the name, this is the same as [`add_entry`](ZipArchive::add_entry).
+    pub fn replace_entry<O: Into<EntryOptions>>(
+        mut self,
+        name: &str,
+        content: &[u8],
+        options: O,
+    ) -> Result<Self> {
+        let result = self.push_replaced(name, content, options.into());
+        self.or_poison(result)
+    }
+
+    /// Replace the entry like [`replace_entry`](ZipArchive::replace_entry), leaving the
+    /// archive as it was on an error of limits or options.
+    fn push_replaced(&mut self, name: &str, content: &[u8], options: EntryOptions) -> Result<()> {
+        self.check_poisoned()?;
+        if self.overdue(name) {
+            return Ok(());
+        }
+        let prefixed = self.prefixed(name);
+        let position = self
+            .entries
+            .iter()
+            .position(|entry| entry.filename == prefixed);
+        self.check_limits(name, content.len(), position)?;
+        self.check_options(name, options)?;
+        self.state = ZipState::Processing;
+        self.write_entry(name, content, options, &Metadata::default())?;
+        if let Some(index) = position {
+            let last = self.entries.len() - 1;
+            self.total_size -= self.entries[index].uncompressed_size;
+            self.digests.retain(|_, value| *value != index);
+            for value in self.digests.values_mut() {
+                if *value == last {
+                    *value = index;
+                }
+            }
+            self.entries.swap_remove(index);
+        }
+        if self.config.auto_flush {
+            self.output.flush()?;
+        }
+        self.state = ZipState::Breathe;
+        Ok(())
+    }
+
+    /// Register an entry whose content is generated during [`finish`](ZipArchive::finish).
+    ///
+    /// The generator receives information of the entries written before it,
+    /// so an index listing every other entry can be made without two passes.
+    /// The entry is listed in the central directory at the position of registration.
+    /// The generator must be [`Send`] so that the archive stays [`Send`].
+    pub fn add_lazy_entry<O, F>(self, name: &str, options: O, generator: F) -> Self
+    where
+        O: Into<EntryOptions>,
+        F: FnOnce(&[EntryInfo]) -> Vec<u8> + Send + 'a,
+    {
+        self.try_lazy_entry(name, options, move |entries| Ok(generator(entries)))
+    }
+
+    /// Register a lazy entry like [`add_lazy_entry`](ZipArchive::add_lazy_entry) whose
+    /// generator fails [`finish`](ZipArchive::finish) by returning an error.
+    pub(crate) fn try_lazy_entry<O, F>(mut self, name: &str, options: O, generator: F) -> Self
+    where
+        O: Into<EntryOptions>,
+        F: FnOnce(&[EntryInfo]) -> Result<Vec<u8>> + Send + 'a,
+    {
+        self.lazy_entries.push(LazyEntry {
+            name: String::from_str(name).unwrap(),
+            options: options.into(),
+            generator: Box::new(generator),
+            position: self.entries.len(),
+        });
+        self
+    }
+
+    fn write_lazy_entries(&mut self) -> Result<()> {
+        let lazy_entries = std::mem::take(&mut self.lazy_entries);
+        let mut positions = Vec::with_capacity(lazy_entries.len());
+        for lazy in lazy_entries {
+            let infos: Vec<EntryInfo> = self.entries.iter().map(ZipEntry::info).collect();
+            let content = (lazy.generator)(&infos)?;
+            self.check_limits(&lazy.name, content.len(), None)?;
+            self.check_options(&lazy.name, lazy.options)?;
+            self.write_entry(&lazy.name, &content, lazy.options, &Metadata::default())?;
+            positions.push(lazy.position);
+        }
+        let generated = self.entries.split_off(self.entries.len() - positions.len());
+        for (index, (entry, position)) in generated.into_iter().zip(positions).enumerate() {
+            self.entries.insert(position + index, entry);
+        }
+        Ok(())
+    }
+
+    /// Check options of an entry before adding it.
+    fn check_options(&self, name: &str, options: EntryOptions) -> Result<()> {
+        let options = options.inherit(&self.config.defaults);
+        if options.time_overflows() {
+            return Err(Error::TimeOutOfRange {
+                name: name.to_string(),
+            });
+        }
+        self.check_profile(name, &options)
+    }
+
+    /// Check the limits before adding an entry, which may replace the entry at `replaced`.
+    fn check_limits(&self, name: &str, size: usize, replaced: Option<usize>) -> Result<()> {
+        let limits = &self.config.limits;
+        let length = self.config.root_prefix.len() + name.len();
+        if length > u16::MAX as usize {
+            return Err(Error::NameTooLong {
+                name: name.to_string(),
+                length,
+            });
+        }
+        let size = size as u64;
+        let count = self.entries.len() + self.lazy_entries.len() + self.deferred.len();
+        let total = self.total_size + self.deferred_size
+            - replaced.map_or(0, |index| self.entries[index].uncompressed_size);
+        let exceeded = if limits
+            .entries
+            .is_some_and(|max| replaced.is_none() && count >= max)
+        {
+            limits.entries.map(Limit::Entries)
+        } else if limits.entry_size.is_some_and(|max| size > max) {
+            limits.entry_size.map(Limit::EntrySize)
+        } else if limits.total_size.is_some_and(|max| total + size > max) {
+            limits.total_size.map(Limit::TotalSize)
+        } else if limits.name_length.is_some_and(|max| length > max) {
+            limits.name_length.map(Limit::NameLength)
+        } else {
+            None
+        };
+        match exceeded {
+            Some(limit) => Err(Error::LimitExceeded {
+                name: name.to_string(),
+                limit,
+            }),
+            None => self.check_case(name),
+        }
+    }
+
+    fn write_entry(
+        &mut self,
+        name: &str,
+        content: &[u8],
+        options: EntryOptions,
+        metadata: &Metadata,
+    ) -> Result<()> {
+        self.record_case(name);
+        let name = &self.prefixed(name).into_owned();
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("zip_entry", name).entered();
+        #[cfg(feature = "tracing")]
+        let start = std::time::Instant::now();
+        self.write_entry_data(name, content, options, metadata)?;
+        if let (Some(observer), Some(entry)) = (&self.config.observer, self.entries.last()) {
+            observer.on_entry_complete(&entry.info());
+        }
+        #[cfg(feature = "tracing")]
+        if let Some(entry) = self.entries.last() {
+            tracing::debug!(
+                uncompressed_size = entry.uncompressed_size,
+                compressed_size = entry.compressed_size,
+                ratio = entry.compressed_size as f64 / entry.uncompressed_size.max(1) as f64,
+                elapsed_us = start.elapsed().as_micros() as u64,
+                "entry written"
+            );
+        }
+        Ok(())
+    }
+
+    /// Options of the entry with the level of the compression policy and the defaults.
+    fn entry_options(&self, name: &str, mut options: EntryOptions) -> EntryOptions {
+        if let (Some(policy), None) = (&self.config.compression_policy, options.level_of()) {
+            if let Some(level) = policy.level(name) {
+                options = options.level(level);
+            }
+        }
+        options.inherit(&self.config.defaults)
+    }
+
+    fn write_entry_data(
+        &mut self,
+        name: &str,
+        content: &[u8],
+        options: EntryOptions,
+        metadata: &Metadata,
+    ) -> Result<()> {
+        let mut options = self.entry_options(name, options);
+        if let (Some(sniffer), false) = (&self.config.sniffer, content.is_empty()) {
+            let leading = &content[..content.len().min(builder::SNIFF_LENGTH)];
+            options = options.level(sniffer(name, leading));
+        }
+        options = options.probe(content).choose_method(content);
+        self.total_size += content.len() as u64;
+        let digest = if self.config.digest || self.config.deduplicate {
+            let mut hasher = SHA256::default();
+            hasher.write(content);
+            Some(hasher.finish())
+        } else {
+            None
+        };
+        let shareable = self.config.deduplicate && options.shareable();
+        if let (true, Some(digest)) = (shareable, digest) {
+            if let Some(&index) = self.digests.get(&digest) {
+                let filename = options.entry_name(name).into_owned();
+                // The shared data decides only whether data descriptor follows it.
+                let descriptor = self.entries[index].flags & 8;
+                let mut entry = ZipEntry {
+                    flags: options.flags(&filename) & !8 | descriptor,
+                    filename,
+                    raw_name: None,
+                    extra: Vec::new(),
+                    sha256: Some(digest).filter(|_| self.config.digest),
+                    external_attributes: options.external_attributes(),
+                    ..self.entries[index].clone()
+                };
+                metadata.apply(&mut entry);
+                self.entries.push(entry);
+                return Ok(());
+            }
+            self.digests.insert(digest, self.entries.len());
+        }
+        let digest = digest.filter(|_| self.config.digest);
+        if self.should_stream(&options, content.len()) {
+            return self.write_streamed(name, content, options, metadata, digest);
+        }
+        let spooled = self
+            .spool(&options, content)
+            .map_err(|error| error.in_entry(name, Stage::Compression))?;
+        let compressed_body = match spooled {
+            Spooled::Memory(Some(body))
+                if self.poor_compression(name, content.len(), body.len()) =>
+            {
+                options = options.level(Level::Raw);
+                None
+            }
+            Spooled::File(file)
+                if self.poor_compression(name, content.len(), file.len as usize) =>
+            {
+                options = options.level(Level::Raw);
+                None
+            }
+            Spooled::File(file) => {
+                return self.write_spooled(name, content, options, metadata, digest, file);
+            }
+            Spooled::Memory(body) => body,
+        };
+        #[cfg(feature = "aes")]
+        let compressed_body = match (options.encrypted(), &self.config.password) {
+            (false, _) => compressed_body,
+            (true, Some(password)) => Some(
+                encryption::encrypt(
+                    &self.config,
+                    password,
+                    compressed_body.as_deref().unwrap_or(content),
+                )
+                .map_err(|error| error.in_entry(name, Stage::Compression))?,
+            ),
+            (true, None) => return Err(Error::NoPassword),
+        };
+        if let Some(compressed_body) = compressed_body {
+            let mut entry = ZipEntry::new(name, content, &compressed_body, &options, self.offset);
+            entry.sha256 = digest;
+            metadata.apply(&mut entry);
+            #[cfg(feature = "aes")]
+            if options.encrypted() {
+                entry.seal();
+            }
+            let extra = self.pad(&mut entry, &options)?.unwrap_or_default();
+            self.write_local(&mut entry, &extra, &compressed_body)?;
             self.entries.push(entry);
         } else {
-            let entry = ZipEntry::new(name, content, &content, level.method(), self.offset);
-            self.offset += Self::pk0304(self.output, &entry)? as u32;
-            self.offset += self.output.write(content)? as u32;
+            let mut entry = ZipEntry::new(name, content, content, &options, self.offset);
+            entry.sha256 = digest;
+            metadata.apply(&mut entry);
+            let threshold = self.config.zip64_threshold;
+            let header = 30
+                + entry.name_bytes().len()
+                + entry.local_extra(threshold).len()
+                + entry.extra.len();
+            let extra = match self.pad(&mut entry, &options)? {
+                Some(extra) => extra,
+                None => options.alignment_extra(
+                    name,
+                    self.leading()
+                        .wrapping_add(self.offset)
+                        .wrapping_add(header as u64),
+                )?,
+            };
+            self.write_local(&mut entry, &extra, content)?;
             self.entries.push(entry);
         }
-        self.state = ZipState::Breathe;
-        Ok(self)
+        Ok(())
+    }
+
+    /// Pad before the entry to start its data at the fixed offset of the options if any,
+    /// and return the extra field of padding.
+    fn pad(&mut self, entry: &mut ZipEntry, options: &EntryOptions) -> Result<Option<Vec<u8>>> {
+        let (offset, padding) = match options.placement() {
+            Some(placement) => placement,
+            None => return Ok(None),
+        };
+        let threshold = self.config.zip64_threshold;
+        let header =
+            30 + entry.name_bytes().len() + entry.local_extra(threshold).len() + entry.extra.len();
+        let unreachable = || Error::OffsetUnreachable {
+            name: entry.filename.clone(),
+            offset,
+        };
+        let gap = offset
+            .checked_sub(self.offset.saturating_add(header as u64))
+            .ok_or_else(unreachable)?;
+        match padding {
+            Padding::Zeros => {
+                let mut output = Signed::new(self.output, &mut self.batch, &mut self.signer);
+                std::io::copy(&mut std::io::repeat(0).take(gap), &mut output)
+                    .map_err(|error| Error::from(error).in_entry(&entry.filename, Stage::Data))?;
+                advance(&mut self.offset, gap)?;
+                entry.offset = self.offset;
+                Ok(Some(Vec::new()))
+            }
+            Padding::ExtraField if gap == 0 => Ok(Some(Vec::new())),
+            Padding::ExtraField if (4..=u16::MAX as u64 + 4).contains(&gap) => {
+                let mut extra = Vec::with_capacity(gap as usize);
+                extra.extend_from_slice(&options::PADDING_EXTRA.to_le_bytes());
+                extra.extend_from_slice(&((gap - 4) as u16).to_le_bytes());
+                extra.resize(gap as usize, 0);
+                Ok(Some(extra))
+            }
+            Padding::ExtraField => Err(unreachable()),
+        }
     }
 
-    fn pk0102(output: &mut T, entry: &ZipEntry) -> Result<u32> {
-        let mut write_size = output.write(&0x02014b50u32.to_le_bytes())?;
-        write_size += output.write(&20u16.to_le_bytes())?;
-        write_size += output.write(&20u16.to_le_bytes())?;
-        write_size += output.write(&2048u16.to_le_bytes())?;
-        write_size += output.write(&entry.method.to_le_bytes())?;
-        write_size += output.write(&entry.timestamp.to_le_bytes())?;
-        write_size += output.write(&entry.checksum.to_le_bytes())?;
-        write_size += output.write(&entry.compressed_size.to_le_bytes())?;
-        write_size += output.write(&entry.uncompressed_size.to_le_bytes())?;
-        write_size += output.write(&(u32::try_from(entry.filename.len())?).to_le_bytes())?;
-        write_size += output.write(&0u16.to_le_bytes())?;
-        write_size += output.write(&0u16.to_le_bytes())?;
-        write_size += output.write(&0u16.to_le_bytes())?;
-        write_size += output.write(&0u16.to_le_bytes())?;
-        write_size += output.write(&0u16.to_le_bytes())?;
-        write_size += output.write(&entry.offset.to_le_bytes())?;
-        write_size += output.write(entry.filename.as_bytes())?;
-        Ok(u32::try_from(write_size)?)
+    /// Length of leading bytes not counted in offsets.
+    fn leading(&self) -> u64 {
+        match self.config.base_offset {
+            Some((length, Offsets::Relative)) => length,
+            _ => 0,
+        }
     }
 
-    /// Write ending data.
+    /// Write local file header, data and data descriptor of the entry by vectored writes.
+    fn write_local(&mut self, entry: &mut ZipEntry, extra: &[u8], data: &[u8]) -> Result<()> {
+        let threshold = self.config.zip64_threshold;
+        let mut header = Vec::with_capacity(30 + entry.name_bytes().len() + extra.len());
+        Self::pk0304(&mut header, entry, extra, threshold)
+            .map_err(|error| error.in_entry(&entry.filename, Stage::LocalHeader))?;
+        let mut end = self.offset;
+        advance(&mut end, header.len() as u64)?;
+        entry.data_offset = end;
+        let name = &entry.filename;
+        let mut descriptor = Vec::new();
+        Self::pk0708(&mut descriptor, entry, threshold)
+            .map_err(|error| error.in_entry(name, Stage::Data))?;
+        advance(&mut end, (data.len() + descriptor.len()) as u64)?;
+        let mut output = Signed::new(self.output, &mut self.batch, &mut self.signer);
+        write_all_vectored(
+            &mut output,
+            &mut [
+                IoSlice::new(&header),
+                IoSlice::new(data),
+                IoSlice::new(&descriptor),
+            ],
+        )
+        .map_err(|error| error.in_entry(name, Stage::Data))?;
+        self.offset = end;
+        Ok(())
+    }
+
+    /// Report poor compression to the observer, and return whether to store the entry instead.
+    fn poor_compression(
+        &self,
+        name: &str,
+        uncompressed_size: usize,
+        compressed_size: usize,
+    ) -> bool {
+        let (threshold, action) = match self.config.ratio_threshold {
+            Some(ratio_threshold) if uncompressed_size > 0 => ratio_threshold,
+            _ => return false,
+        };
+        let ratio = compressed_size as f64 / uncompressed_size as f64;
+        if ratio <= threshold {
+            return false;
+        }
+        if let Some(observer) = &self.config.observer {
+            observer.on_poor_compression(name, ratio);
+        }
+        action == PoorCompression::Store
+    }
+
+    /// Data descriptor (PK0708) if the entry has the flag.
     ///
-    /// Specifically, central directory header (PK0102) and end of central directory record (PK0506).
-    pub fn flush(mut self) -> Result<()> {
+    /// Sizes are 8 bytes when local file header has Zip64 extended information.
+    fn pk0708<W: Write>(output: &mut W, entry: &ZipEntry, threshold: u64) -> Result<u64> {
+        if !entry.has_data_descriptor() {
+            return Ok(0);
+        }
+        let descriptor = raw::DataDescriptor {
+            crc32: entry.checksum,
+            compressed_size: entry.compressed_size,
+            uncompressed_size: entry.uncompressed_size,
+            zip64: !entry.local_extra(threshold).is_empty(),
+        };
+        descriptor.write_to(output)?;
+        Ok(descriptor.size() as u64)
+    }
+
+    /// Flush the output writer by [`Write::flush`].
+    ///
+    /// Unlike [`finish`](ZipArchive::finish), this does not write ending data.
+    pub fn flush_output(self) -> Result<Self> {
+        self.output.flush()?;
+        Ok(self)
+    }
+
+    fn pk0102<W: Write>(
+        directory: &mut CentralDirectoryWriter<'_, W>,
+        entry: &ZipEntry,
+    ) -> Result<()> {
+        let threshold = directory.threshold;
+        let version = entry.version_needed(threshold);
+        let extra = [entry.central_extra(threshold).as_slice(), &entry.extra].concat();
+        entry.check_extra(&extra)?;
+        let header = raw::CentralDirectoryHeader {
+            version_made_by: (entry.host as u16) << 8 | version,
+            version_needed: version,
+            flags: entry.flags,
+            method: entry.method,
+            timestamp: entry.timestamp,
+            crc32: entry.checksum,
+            compressed_size: zip64_field(entry.compressed_size, threshold),
+            uncompressed_size: zip64_field(entry.uncompressed_size, threshold),
+            disk_number_start: 0,
+            internal_attributes: 0,
+            external_attributes: entry.external_attributes,
+            local_header_offset: zip64_field(entry.offset, threshold),
+            filename: entry.name_bytes(),
+            extra: &extra,
+            comment: &[],
+        };
+        directory.add(&header)
+    }
+
+    fn write_ending(&mut self) -> Result<ArchiveSummary> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("zip_finish").entered();
+        #[cfg(feature = "tracing")]
+        let start = std::time::Instant::now();
         self.state = ZipState::Processing;
-        let entries = std::mem::take(&mut self.entries);
+        let comment = self.comment();
+        if comment.len() > u16::MAX as usize {
+            return Err(Error::CommentTooLong {
+                length: comment.len(),
+            });
+        }
+        self.write_deferred()?;
+        self.write_lazy_entries()?;
+        #[cfg(feature = "json-manifest")]
+        if self.config.json_manifest {
+            self.write_manifest()?;
+        }
+        let entries = self.config.order.sort(std::mem::take(&mut self.entries));
+        if let Some(profile) = self.config.profile {
+            profile.check_entries(&entries)?;
+        }
         let top_of_central_directory = self.offset;
+        let threshold = self.config.zip64_threshold;
+        // The records are small, so they are written at once.
+        self.batch = Some(Vec::new());
+        let mut output = Signed::new(self.output, &mut self.batch, &mut self.signer);
+        let mut directory = CentralDirectoryWriter::new(&mut output, top_of_central_directory);
+        directory.threshold = threshold;
         for entry in entries.iter() {
-            self.offset += Self::pk0102(&mut self.output, entry)?;
+            Self::pk0102(&mut directory, entry)
+                .map_err(|error| error.in_entry(&entry.filename, Stage::CentralDirectory))?;
         }
-        let size_of_the_central_directory = self.offset - top_of_central_directory;
-        self.output.write(&0x06054b50u32.to_le_bytes())?;
-        self.output.write(&0u32.to_le_bytes())?;
-        self.output.write(&(entries.len() as u16).to_le_bytes())?;
-        self.output.write(&(entries.len() as u16).to_le_bytes())?;
+        let size_of_the_central_directory = directory.size();
+        let (mut record, offset) = directory.end()?;
+        self.offset = offset;
+        // The comment is not known yet when the signature is embedded in it.
+        let mut fixed = Vec::with_capacity(record.size());
+        record.write_to(&mut fixed)?;
+        output.write_all(&fixed[..20])?;
+        let embedded = match self.signer.as_mut() {
+            Some(signer) if signer.embed_in_comment() => Some(signer.finish()),
+            _ => None,
+        };
+        let comment = embedded.clone().unwrap_or(comment);
+        // The embedded signature does not cover the comment.
+        let mut unsigned = None;
+        let signer = match embedded {
+            Some(_) => &mut unsigned,
+            None => &mut self.signer,
+        };
+        let mut output = Signed::new(self.output, &mut self.batch, signer);
+        record.comment = &comment;
+        let mut ending = Vec::with_capacity(record.size());
+        record.write_to(&mut ending)?;
+        let mut size = self.offset;
+        advance(&mut size, ending.len() as u64)?;
+        output.write_all(&ending[20..])?;
+        let signature = embedded.or_else(|| self.signer.as_mut().map(|signer| signer.finish()));
         self.output
-            .write(&size_of_the_central_directory.to_le_bytes())?;
-        self.output.write(&top_of_central_directory.to_le_bytes())?;
-        self.output.write(&0u16.to_le_bytes())?;
+            .write_all(&self.batch.take().unwrap_or_default())?;
+        if self.config.auto_flush {
+            self.output.flush()?;
+        }
         self.state = ZipState::Finished;
-        Ok(())
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            entries = entries.len(),
+            central_directory_size = size_of_the_central_directory,
+            elapsed_us = start.elapsed().as_micros() as u64,
+            "archive finished"
+        );
+        if let Some(observer) = &self.config.observer {
+            observer.on_archive_complete(&ArchiveStats {
+                entries: entries.len(),
+                bytes_in: self.total_size,
+                bytes_out: size,
+            });
+        }
+        Ok(ArchiveSummary {
+            entries: entries.iter().map(ZipEntry::info).collect(),
+            size,
+            uncompressed_size: self.total_size,
+            compressed_size: entries.iter().map(|entry| entry.compressed_size).sum(),
+            central_directory_size: size_of_the_central_directory,
+            elapsed: self.started.elapsed(),
+            signature,
+            skipped: std::mem::take(&mut self.skipped),
+        })
+    }
+
+    /// Write ending data and finish the archive.
+    ///
+    /// Specifically, central directory header (PK0102) and end of central directory record (PK0506).
+    /// The returned [`ArchiveSummary`] reports what was written.
+    pub fn finish(mut self) -> Result<ArchiveSummary> {
+        self.check_poisoned()?;
+        self.write_ending()
+    }
+
+    /// Write ending data.
+    #[deprecated(note = "use `finish`, which does not collide with `Write::flush`")]
+    pub fn flush(self) -> Result<()> {
+        self.finish().map(|_| ())
     }
 }
 
-impl<'a, T: Write + 'a> Drop for ZipArchive<'a, T> {
-    /// If flush method has be not called, this method write ending data.
+impl<'a, T: Write + ?Sized + 'a> Drop for ZipArchive<'a, T> {
+    /// If finish method has be not called, this method write ending data.
     /// But failing to write causes a panic.
-    /// It is recommended to always call [`flush`](ZipArchive::flush) explicitly.
+    /// It is recommended to always call [`finish`](ZipArchive::finish) explicitly.
     fn drop(&mut self) {
         if self.state == ZipState::Breathe {
-            self.state = ZipState::Processing;
-            let entries = std::mem::take(&mut self.entries);
-            let top_of_central_directory = self.offset;
-            for entry in entries.iter() {
-                self.offset += Self::pk0102(&mut self.output, entry).unwrap();
-            }
-            let size_of_the_central_directory = self.offset - top_of_central_directory;
-            self.output.write(&0x06054b50u32.to_le_bytes()).unwrap();
-            self.output.write(&0u32.to_le_bytes()).unwrap();
-            self.output
-                .write(&(entries.len() as u16).to_le_bytes())
+            self.write_ending().unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        verify, Checkpoint, DateTime, DosAttributes, DynZipArchive, EntryOptions, Error, Level,
+        Padding, Stage, Strategy, TimeOverflow, Unicode, ZipArchive, ZipArchiveBuilder,
+    };
+    use std::io::Write;
+
+    #[derive(Default)]
+    struct FlushCounter {
+        written: usize,
+        flushes: usize,
+    }
+
+    impl Write for FlushCounter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.written += buf.len();
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            self.flushes += 1;
+            Ok(())
+        }
+    }
+
+    /// Writer accepting at most `limit` bytes of a vectored write.
+    struct VectoredCounter {
+        buf: Vec<u8>,
+        calls: usize,
+        limit: usize,
+    }
+
+    impl Write for VectoredCounter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.write_vectored(&[std::io::IoSlice::new(buf)])
+        }
+
+        fn write_vectored(&mut self, bufs: &[std::io::IoSlice<'_>]) -> std::io::Result<usize> {
+            self.calls += 1;
+            let mut written = 0;
+            for buf in bufs {
+                let length = buf.len().min(self.limit - written);
+                self.buf.extend_from_slice(&buf[..length]);
+                written += length;
+            }
+            Ok(written)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn write_vectored() {
+        fn build<W: Write>(output: &mut W) {
+            ZipArchiveBuilder::new()
+                .modified(std::time::UNIX_EPOCH + std::time::Duration::from_secs(1369010347))
+                .build(output)
+                .add_entry("a.txt", b"content", Level::Raw)
+                .unwrap()
+                .add_entry("b.txt", b"content", Level::Default)
+                .unwrap()
+                .finish()
+                .unwrap();
+        }
+        let mut expected = Vec::new();
+        build(&mut expected);
+        for (limit, calls) in [(usize::MAX, 3), (7, expected.len().div_ceil(7) + 2)] {
+            let mut output = VectoredCounter {
+                buf: Vec::new(),
+                calls: 0,
+                limit,
+            };
+            build(&mut output);
+            assert_eq!(output.buf, expected);
+            assert!(output.calls <= calls);
+        }
+    }
+
+    #[test]
+    fn flush_output() {
+        let mut output = FlushCounter::default();
+        ZipArchive::new(&mut output)
+            .add_entry("a.txt", b"a", Level::Raw)
+            .unwrap()
+            .flush_output()
+            .unwrap()
+            .finish()
+            .unwrap();
+        assert_eq!(output.flushes, 1);
+        let mut output = FlushCounter::default();
+        ZipArchiveBuilder::new()
+            .auto_flush()
+            .build(&mut output)
+            .add_entry("a.txt", b"a", Level::Raw)
+            .unwrap()
+            .add_entry("b.txt", b"b", Level::Raw)
+            .unwrap()
+            .finish()
+            .unwrap();
+        assert_eq!(output.flushes, 3);
+    }
+
+    #[test]
+    fn strategy() {
+        let mut text = b"abc".repeat(200);
+        text.extend_from_slice(&[0; 600]);
+        let mut buf = Vec::new();
+        let summary = ZipArchive::new(&mut buf)
+            .add_entry("default.txt", &text, Level::High)
+            .unwrap()
+            .add_entry(
+                "huffman.txt",
+                &text,
+                EntryOptions::new(Level::High).strategy(Strategy::HuffmanOnly),
+            )
+            .unwrap()
+            .add_entry(
+                "rle.txt",
+                &text,
+                EntryOptions::new(Level::High).strategy(Strategy::Rle),
+            )
+            .unwrap()
+            .finish()
+            .unwrap();
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+        let sizes: Vec<_> = summary
+            .entries
+            .iter()
+            .map(|entry| entry.compressed_size)
+            .collect();
+        // Only runs of a byte are matched by RLE, and nothing by Huffman only.
+        assert!(sizes[0] < sizes[2], "{:?}", sizes);
+        assert!(sizes[2] < sizes[1], "{:?}", sizes);
+    }
+
+    #[test]
+    fn digest() {
+        let mut buf = Vec::new();
+        let zip = ZipArchive::new(&mut buf)
+            .digest()
+            .add_entry("abc.txt", b"abc", Level::Default)
+            .unwrap();
+        let info = zip.entries().next().unwrap();
+        assert_eq!(info.name, "abc.txt");
+        assert_eq!(info.checksum, 0x352441c2);
+        assert_eq!(info.sha256.unwrap()[..4], [0xba, 0x78, 0x16, 0xbf]);
+        zip.finish().unwrap();
+    }
+
+    #[cfg(feature = "brotli")]
+    #[test]
+    fn brotli() {
+        let mut buf = Vec::new();
+        ZipArchive::new(&mut buf)
+            .add_entry(
+                "brotli.txt",
+                &[b'a'; 1000],
+                EntryOptions::new(Level::Default).brotli(0x4272),
+            )
+            .unwrap()
+            .finish()
+            .unwrap();
+        assert_eq!(
+            verify(buf.as_slice()).unwrap(),
+            vec![crate::Inconsistency::UnsupportedMethod {
+                name: "brotli.txt".to_string(),
+                method: 0x4272
+            }]
+        );
+        let data_offset = 30 + "brotli.txt".len() + u16::from_le_bytes([buf[28], buf[29]]) as usize;
+        let size = u32::from_le_bytes([buf[18], buf[19], buf[20], buf[21]]) as usize;
+        let mut decoded = Vec::new();
+        brotli::BrotliDecompress(&mut &buf[data_offset..data_offset + size], &mut decoded).unwrap();
+        assert_eq!(decoded, vec![b'a'; 1000]);
+    }
+
+    #[cfg(feature = "brotli")]
+    #[test]
+    #[should_panic(expected = "standard compression method")]
+    fn brotli_standard_method() {
+        let _ = EntryOptions::new(Level::Default).brotli(8);
+    }
+
+    #[cfg(feature = "ppmd")]
+    #[test]
+    fn ppmd() {
+        let mut buf = Vec::new();
+        let text = b"PPMd is good at text. PPMd is good at logs. ".repeat(50);
+        let mut zip = ZipArchive::new(&mut buf);
+        for level in [Level::Low, Level::Default, Level::High].iter() {
+            zip = zip
+                .add_entry("ppmd.txt", &text, EntryOptions::new(*level).ppmd())
                 .unwrap();
-            self.output
-                .write(&(entries.len() as u16).to_le_bytes())
+        }
+        zip.finish().unwrap();
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn two_pass() {
+        let noise: Vec<u8> = (0..1000u32)
+            .scan(1u32, |state, _| {
+                *state = state.wrapping_mul(1103515245).wrapping_add(12345);
+                Some((*state >> 24) as u8)
+            })
+            .collect();
+        let text = b"Two passes choose the method. ".repeat(50);
+        let mut buf = Vec::new();
+        let summary = ZipArchiveBuilder::new()
+            .two_pass()
+            .build(&mut buf)
+            .add_entry("noise.bin", &noise, Level::High)
+            .unwrap()
+            .add_entry("text.txt", &text, Level::High)
+            .unwrap()
+            .add_entry("raw.txt", &text, Level::Raw)
+            .unwrap()
+            .finish()
+            .unwrap();
+        let sizes: Vec<_> = summary
+            .entries
+            .iter()
+            .map(|entry| (entry.compressed_size, entry.uncompressed_size))
+            .collect();
+        assert_eq!(sizes[0], (1000, 1000));
+        assert!(sizes[1].0 < sizes[1].1);
+        assert_eq!(sizes[2].0, sizes[2].1);
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn replace_entry() {
+        let mut buf = Vec::new();
+        let summary = ZipArchive::new(&mut buf)
+            .deduplicate()
+            .add_entry("manifest.txt", b"", Level::Raw)
+            .unwrap()
+            .add_entry("a.txt", b"a", Level::Raw)
+            .unwrap()
+            .add_entry("b.txt", b"b", Level::Raw)
+            .unwrap()
+            .replace_entry("manifest.txt", b"a.txt\nb.txt\n", Level::Default)
+            .unwrap()
+            .add_entry("empty.txt", b"", Level::Raw)
+            .unwrap()
+            .finish()
+            .unwrap();
+        let names: Vec<_> = summary
+            .entries
+            .iter()
+            .map(|entry| &entry.name[..])
+            .collect();
+        assert_eq!(names, ["manifest.txt", "a.txt", "b.txt", "empty.txt"]);
+        assert_eq!(summary.entries[0].uncompressed_size, 12);
+        assert_ne!(summary.entries[3].offset, 0);
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+
+        let mut buf = Vec::new();
+        let mut zip = ZipArchiveBuilder::new().max_entry_size(4).build(&mut buf);
+        zip.push_entry("a.txt", b"a", Level::Raw).unwrap();
+        assert!(matches!(
+            zip.push_replaced("a.txt", b"too large", Level::Raw.into()),
+            Err(Error::LimitExceeded { .. })
+        ));
+        let summary = zip.finish().unwrap();
+        assert_eq!(summary.entries[0].uncompressed_size, 1);
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn lazy_entry() {
+        let mut buf = Vec::new();
+        let summary = ZipArchive::new(&mut buf)
+            .add_entry("a.txt", b"a", Level::Raw)
+            .unwrap()
+            .add_lazy_entry("index.txt", Level::Default, |entries| {
+                entries
+                    .iter()
+                    .map(|entry| format!("{} {:08x}\n", entry.name, entry.checksum))
+                    .collect::<String>()
+                    .into_bytes()
+            })
+            .add_entry("b.txt", b"b", Level::Raw)
+            .unwrap()
+            .add_lazy_entry("count.txt", Level::Raw, |entries| {
+                entries.len().to_string().into_bytes()
+            })
+            .finish()
+            .unwrap();
+        let names: Vec<_> = summary
+            .entries
+            .iter()
+            .map(|entry| &entry.name[..])
+            .collect();
+        assert_eq!(names, ["a.txt", "index.txt", "b.txt", "count.txt"]);
+        assert_eq!(summary.entries[1].uncompressed_size, 30);
+        assert_eq!(summary.entries[3].uncompressed_size, 1);
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn deduplicate() {
+        let content = [b'x'; 1000];
+        let mut plain = Vec::new();
+        ZipArchive::new(&mut plain)
+            .add_entry("a.txt", &content, Level::Raw)
+            .unwrap()
+            .add_entry("b.txt", &content, Level::Raw)
+            .unwrap()
+            .finish()
+            .unwrap();
+        let mut deduplicated = Vec::new();
+        let summary = ZipArchive::new(&mut deduplicated)
+            .deduplicate()
+            .add_entry("a.txt", &content, Level::Raw)
+            .unwrap()
+            .add_entry("b.txt", &content, Level::Raw)
+            .unwrap()
+            .finish()
+            .unwrap();
+        assert_eq!(plain.len() - deduplicated.len(), 30 + 5 + 1000);
+        assert_eq!(summary.size as usize, deduplicated.len());
+        assert_eq!(summary.entries[0].offset, summary.entries[1].offset);
+        assert_eq!(verify(deduplicated.as_slice()).unwrap(), vec![]);
+
+        let mut buf = Vec::new();
+        let options = EntryOptions::new(Level::Raw).unicode(Unicode::Auto);
+        ZipArchive::new(&mut buf)
+            .deduplicate()
+            .add_entry("ascii.txt", &content, options)
+            .unwrap()
+            .add_entry("日本語.txt", &content, options)
+            .unwrap()
+            .finish()
+            .unwrap();
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+        let flags: Vec<_> = verify::read_raw_entries(&buf)
+            .unwrap()
+            .into_iter()
+            .map(|(header, _)| header.flags & 2048)
+            .collect();
+        assert_eq!(flags, [0, 2048]);
+    }
+
+    #[test]
+    fn send() {
+        fn assert_send<S: Send>(_: &S) {}
+        let mut buf = Vec::new();
+        let zip = ZipArchiveBuilder::new()
+            .time_slice(1024, || {})
+            .build(&mut buf)
+            .add_entry("main.txt", b"main", Level::Default)
+            .unwrap()
+            .add_lazy_entry("index.txt", Level::Raw, |entries| {
+                entries
+                    .iter()
+                    .map(|entry| entry.name.clone())
+                    .collect::<String>()
+                    .into_bytes()
+            });
+        assert_send(&zip);
+        std::thread::scope(|scope| {
+            scope
+                .spawn(move || {
+                    zip.add_entry("worker.txt", b"worker", Level::Default)
+                        .unwrap()
+                        .finish()
+                        .unwrap();
+                })
+                .join()
                 .unwrap();
-            self.output
-                .write(&size_of_the_central_directory.to_le_bytes())
+        });
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn summary() {
+        let mut buf = Vec::new();
+        let summary = ZipArchive::new(&mut buf)
+            .add_entry("raw.txt", b"raw content", Level::Raw)
+            .unwrap()
+            .add_entry("deflate.txt", &[b'a'; 1000], Level::High)
+            .unwrap()
+            .finish()
+            .unwrap();
+        assert_eq!(summary.entries.len(), 2);
+        assert_eq!(summary.size as usize, buf.len());
+        assert_eq!(summary.uncompressed_size, 1011);
+        assert_eq!(
+            summary.compressed_size,
+            11 + summary.entries[1].compressed_size
+        );
+        assert_eq!(summary.central_directory_size, 46 * 2 + 7 + 11);
+        assert!(summary.elapsed > std::time::Duration::ZERO);
+    }
+
+    struct FailingWriter {
+        capacity: usize,
+    }
+
+    impl Write for FailingWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            if self.capacity < buf.len() {
+                return Err(std::io::ErrorKind::WriteZero.into());
+            }
+            self.capacity -= buf.len();
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn poisoned() {
+        let mut output = FailingWriter { capacity: 100 };
+        let mut zip = ZipArchive::new(&mut output);
+        zip.push_entry("first.txt", b"first", Level::Raw).unwrap();
+        let result = zip.push_entry("second.txt", &[b'a'; 100], Level::Raw);
+        #[cfg(feature = "backtrace")]
+        assert!(result.as_ref().unwrap_err().backtrace().is_some());
+        match result {
+            Err(Error::Entry {
+                name,
+                stage: Stage::Data,
+                source,
+                ..
+            }) => {
+                assert_eq!(name, "second.txt");
+                assert!(matches!(*source, Error::IoError(_)));
+            }
+            _ => panic!("writing error is not reported"),
+        }
+        assert!(matches!(
+            zip.push_entry("third.txt", b"third", Level::Raw),
+            Err(Error::Poisoned)
+        ));
+        assert!(matches!(zip.finish(), Err(Error::Poisoned)));
+    }
+
+    #[test]
+    fn fixed_offset() {
+        for padding in [Padding::Zeros, Padding::ExtraField] {
+            let mut buf = Vec::new();
+            let summary = ZipArchive::new(&mut buf)
+                .add_entry("a.txt", b"a", Level::Raw)
+                .unwrap()
+                .add_entry(
+                    "boot.bin",
+                    &[b'b'; 100],
+                    EntryOptions::new(Level::High).fixed_offset(0x100, padding),
+                )
+                .unwrap()
+                .add_entry(
+                    "next.bin",
+                    b"next",
+                    EntryOptions::new(Level::Raw).fixed_offset(0x200, padding),
+                )
+                .unwrap()
+                .finish()
                 .unwrap();
-            self.output
-                .write(&top_of_central_directory.to_le_bytes())
+            assert_eq!(summary.entries[1].data_offset, 0x100);
+            assert_eq!(summary.entries[2].data_offset, 0x200);
+            assert_eq!(&buf[0x200..0x204], b"next");
+            assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+            let header = summary.entries[1].offset as usize;
+            match padding {
+                Padding::Zeros => assert!(buf[36..header].iter().all(|&byte| byte == 0)),
+                Padding::ExtraField => assert_eq!(header, 36),
+            }
+        }
+        let result = ZipArchive::new(&mut Vec::new())
+            .add_entry("a.txt", b"a", Level::Raw)
+            .unwrap()
+            .add_entry(
+                "b.txt",
+                b"b",
+                EntryOptions::new(Level::Raw).fixed_offset(36 + 30 + 5 + 2, Padding::ExtraField),
+            )
+            .map(drop);
+        assert!(matches!(
+            result,
+            Err(Error::OffsetUnreachable { name, offset: 73 }) if name == "b.txt"
+        ));
+        let result = ZipArchive::new(&mut Vec::new())
+            .add_entry(
+                "a.txt",
+                b"a",
+                EntryOptions::default().fixed_offset(10, Padding::Zeros),
+            )
+            .map(drop);
+        assert!(matches!(result, Err(Error::OffsetUnreachable { .. })));
+
+        let mut buf = Vec::new();
+        let summary = ZipArchive::new(&mut buf)
+            .deduplicate()
+            .add_entry("a.bin", &[b'x'; 100], Level::Raw)
+            .unwrap()
+            .add_entry(
+                "b.bin",
+                &[b'x'; 100],
+                EntryOptions::new(Level::Raw).fixed_offset(4096, Padding::Zeros),
+            )
+            .unwrap()
+            .finish()
+            .unwrap();
+        assert_eq!(summary.entries[1].data_offset, 4096);
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn zip64_boundaries() {
+        // The last offset of 32 bits and the first offset beyond them.
+        for (offset, zip64) in [(u32::MAX as u64 - 1, false), (u32::MAX as u64, true)] {
+            let checkpoint = Checkpoint {
+                entries: Vec::new(),
+                offset,
+                digests: Vec::new(),
+            };
+            let mut buf = Vec::new();
+            let summary = ZipArchiveBuilder::new()
+                .resume(&mut buf, checkpoint)
+                .add_entry("a.txt", b"content", Level::Raw)
+                .unwrap()
+                .finish()
+                .unwrap();
+            assert_eq!(summary.entries[0].offset, offset);
+            let central = &buf[30 + 5 + 7..];
+            assert_eq!(&central[..4], &0x02014b50u32.to_le_bytes());
+            let recorded = u32::from_le_bytes([central[42], central[43], central[44], central[45]]);
+            assert_eq!(recorded == u32::MAX, zip64);
+            let eocd = &buf[buf.len() - 22..];
+            assert_eq!(&eocd[16..20], &u32::MAX.to_le_bytes());
+        }
+
+        for (count, zip64) in [(65534, false), (65535, true)] {
+            let mut buf = Vec::new();
+            let mut zip = ZipArchive::new(&mut buf);
+            for index in 0..count {
+                zip.push_entry(&index.to_string(), b"", Level::Raw).unwrap();
+            }
+            zip.finish().unwrap();
+            let eocd = &buf[buf.len() - 22..];
+            let entries = u16::from_le_bytes([eocd[10], eocd[11]]);
+            // 65535 is also the mark that the count is in Zip64 end of central directory.
+            assert_eq!(entries as usize, count);
+            let locator = &buf[buf.len() - 42..buf.len() - 22];
+            assert_eq!(locator[..4] == 0x07064b50u32.to_le_bytes(), zip64);
+            if zip64 {
+                let record = &buf[buf.len() - 42 - 56..buf.len() - 42];
+                let mut total = [0; 8];
+                total.copy_from_slice(&record[32..40]);
+                assert_eq!(u64::from_le_bytes(total), count as u64);
+            }
+        }
+    }
+
+    #[test]
+    fn offset_overflow() {
+        let mut buf = Vec::new();
+        let checkpoint = Checkpoint {
+            entries: Vec::new(),
+            offset: u64::MAX - 40,
+            digests: Vec::new(),
+        };
+        let result = ZipArchiveBuilder::new()
+            .resume(&mut buf, checkpoint)
+            .add_entry("a.txt", b"content", Level::Raw)
+            .map(drop);
+        assert!(matches!(result, Err(Error::OffsetOverflow)));
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn dyn_output() {
+        let mut expected = Vec::new();
+        ZipArchive::new(&mut expected)
+            .add_entry("a.txt", b"content", Level::Default)
+            .unwrap()
+            .finish()
+            .unwrap();
+        let mut buf = Vec::new();
+        let zip: DynZipArchive = ZipArchiveBuilder::new().build(&mut buf);
+        // The archive is still Send.
+        let summary = std::thread::scope(|scope| {
+            scope
+                .spawn(move || zip.add_entry("a.txt", b"content", Level::Default)?.finish())
+                .join()
+                .unwrap()
+        })
+        .unwrap();
+        assert_eq!(summary.size, expected.len() as u64);
+        assert_eq!(buf, expected);
+    }
+
+    #[test]
+    fn large_offset() {
+        let checkpoint = Checkpoint {
+            entries: Vec::new(),
+            offset: 5 << 30,
+            digests: Vec::new(),
+        };
+        let mut buf = Vec::new();
+        let summary = ZipArchiveBuilder::new()
+            .resume(&mut buf, checkpoint)
+            .add_entry("a.txt", b"content", Level::Raw)
+            .unwrap()
+            .finish()
+            .unwrap();
+        assert_eq!(summary.entries[0].offset, 5 << 30);
+        assert_eq!(summary.size, (5 << 30) + buf.len() as u64);
+        let eocd = &buf[buf.len() - 22..];
+        assert_eq!(&eocd[16..20], &u32::MAX.to_le_bytes());
+        let locator = &buf[buf.len() - 42..buf.len() - 22];
+        assert_eq!(&locator[..4], &0x07064b50u32.to_le_bytes());
+        let mut record = [0; 8];
+        record.copy_from_slice(&locator[8..16]);
+        assert_eq!(u64::from_le_bytes(record), summary.size - 22 - 20 - 56);
+    }
+
+    #[test]
+    fn version_needed() {
+        let mut buf = Vec::new();
+        ZipArchiveBuilder::new()
+            .zip64_threshold(100)
+            .build(&mut buf)
+            .add_entry("raw.txt", b"raw", Level::Raw)
+            .unwrap()
+            .add_entry("deflate.txt", b"deflate", Level::Default)
+            .unwrap()
+            .add_entry("large.txt", &[b'a'; 100], Level::Raw)
+            .unwrap()
+            .finish()
+            .unwrap();
+        let versions = |signature: u32, skip: usize| {
+            buf.windows(4)
+                .enumerate()
+                .filter(|(_, window)| *window == signature.to_le_bytes())
+                .map(|(position, _)| {
+                    u16::from_le_bytes([buf[position + skip], buf[position + skip + 1]])
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(versions(0x04034b50, 4), [10, 20, 45]);
+        assert_eq!(versions(0x02014b50, 6), [10, 20, 45]);
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn flags() {
+        for threshold in [u32::MAX, 1] {
+            let mut buf = Vec::new();
+            ZipArchiveBuilder::new()
+                .unicode(Unicode::Never)
+                .zip64_threshold(threshold)
+                .build(&mut buf)
+                .add_entry(
+                    "high.txt",
+                    &[b'a'; 100],
+                    EntryOptions::new(Level::High).compression_hint(true),
+                )
+                .unwrap()
+                .add_entry(
+                    "descriptor.txt",
+                    b"descriptor",
+                    EntryOptions::new(Level::Raw).data_descriptor(true),
+                )
+                .unwrap()
+                .finish()
                 .unwrap();
-            self.output.write(&0u16.to_le_bytes()).unwrap();
-            self.state = ZipState::Finished;
+            assert_eq!(&buf[6..8], &2u16.to_le_bytes());
+            let header = buf
+                .windows(14)
+                .position(|window| window == b"descriptor.txt")
+                .unwrap()
+                - 30;
+            assert_eq!(&buf[header + 6..header + 8], &8u16.to_le_bytes());
+            assert_eq!(&buf[header + 14..header + 18], &[0; 4]);
+            assert!(buf
+                .windows(4)
+                .any(|window| window == 0x08074b50u32.to_le_bytes()));
+            assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+        }
+    }
+
+    #[test]
+    fn dos_attributes() {
+        let mut buf = Vec::new();
+        let attributes = DosAttributes::HIDDEN | DosAttributes::READ_ONLY;
+        ZipArchive::new(&mut buf)
+            .add_entry(
+                "hidden.txt",
+                b"hidden",
+                EntryOptions::new(Level::Raw).dos_attributes(attributes),
+            )
+            .unwrap()
+            .add_entry("plain.txt", b"plain", Level::Raw)
+            .unwrap()
+            .finish()
+            .unwrap();
+        let attributes: Vec<u32> = buf
+            .windows(4)
+            .enumerate()
+            .filter(|(_, window)| *window == 0x02014b50u32.to_le_bytes())
+            .map(|(position, _)| {
+                let field = &buf[position + 38..position + 42];
+                u32::from_le_bytes([field[0], field[1], field[2], field[3]])
+            })
+            .collect();
+        assert_eq!(attributes, [0x03, 0]);
+    }
+
+    #[test]
+    fn time_overflow() {
+        let far: DateTime = "2200-01-01T00:00:00Z".parse().unwrap();
+        let mut buf = Vec::new();
+        let mut zip = ZipArchiveBuilder::new()
+            .modified(far.system_time())
+            .build(&mut buf);
+        let rejected = zip.push_entry(
+            "rejected.txt",
+            b"rejected",
+            EntryOptions::new(Level::Raw).time_overflow(TimeOverflow::Reject),
+        );
+        assert!(matches!(rejected, Err(Error::TimeOutOfRange { name }) if name == "rejected.txt"));
+        zip.push_entry("far.txt", b"far", Level::Raw).unwrap();
+        zip.finish().unwrap();
+        let timestamp = u32::from_le_bytes([buf[10], buf[11], buf[12], buf[13]]);
+        let last = DateTime::from_dos_time(timestamp).unwrap();
+        assert_eq!(last, "2107-12-31T23:59:58Z".parse().unwrap());
+        let mut ntfs = vec![0x0A, 0, 32, 0, 0, 0, 0, 0, 1, 0, 24, 0];
+        let filetime = (far.unix_time() + 11644473600) * 10_000_000;
+        for _ in 0..3 {
+            ntfs.extend_from_slice(&filetime.to_le_bytes());
         }
+        assert_eq!(buf[28..30], [36, 0]);
+        assert_eq!(buf[37..73], ntfs[..]);
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        let mut buf = Vec::new();
+        let summary = ZipArchive::new(&mut buf)
+            .digest()
+            .add_entry("a.txt", b"a", Level::Raw)
+            .unwrap()
+            .finish()
+            .unwrap();
+        let json = serde_json::to_string(&summary).unwrap();
+        assert!(json.contains(r#""name":"a.txt","checksum":3904355907"#));
+        assert_eq!(
+            serde_json::from_str::<super::ArchiveSummary>(&json).unwrap(),
+            summary
+        );
     }
 }
@@ -0,0 +1,80 @@
+use crate::{DateTime, Metadata, Result, ZipArchive};
+use serde::Serialize;
+use std::io::Write;
+
+/// Name of the manifest entry.
+const MANIFEST_NAME: &str = "MANIFEST.json";
+
+#[derive(Serialize)]
+struct Manifest<'e> {
+    entries: Vec<ManifestEntry<'e>>,
+}
+
+#[derive(Serialize)]
+struct ManifestEntry<'e> {
+    name: &'e str,
+    size: u64,
+    crc32: u32,
+    modified: Option<String>,
+}
+
+impl<'a, T: Write + ?Sized + 'a> ZipArchive<'a, T> {
+    /// Write `MANIFEST.json` of the entries written so far.
+    pub(crate) fn write_manifest(&mut self) -> Result<()> {
+        let manifest = Manifest {
+            entries: self
+                .entries
+                .iter()
+                .map(|entry| ManifestEntry {
+                    name: &entry.filename,
+                    size: entry.uncompressed_size,
+                    crc32: entry.checksum,
+                    modified: DateTime::from_dos_time(entry.timestamp).map(|time| time.to_string()),
+                })
+                .collect(),
+        };
+        let json = serde_json::to_vec(&manifest).map_err(std::io::Error::from)?;
+        let options = self.config.defaults;
+        self.check_limits(MANIFEST_NAME, json.len(), None)?;
+        self.write_entry(MANIFEST_NAME, &json, options, &Metadata::default())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{verify, EntryOptions, Level, ZipArchiveBuilder};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn it_works() {
+        let modified = UNIX_EPOCH + Duration::from_secs(1608905122);
+        let mut buf = Vec::new();
+        let summary = ZipArchiveBuilder::new()
+            .json_manifest()
+            .level(Level::Raw)
+            .modified(modified)
+            .build(&mut buf)
+            .add_entry("a.txt", b"a", Level::Raw)
+            .unwrap()
+            .add_entry(
+                "dir/\"quoted\".txt",
+                b"",
+                EntryOptions::new(Level::Raw).modified(UNIX_EPOCH),
+            )
+            .unwrap()
+            .finish()
+            .unwrap();
+        let manifest = summary.entries.last().unwrap();
+        assert_eq!(manifest.name, "MANIFEST.json");
+        let start = manifest.data_offset as usize;
+        let json = &buf[start..start + manifest.uncompressed_size as usize];
+        assert_eq!(
+            std::str::from_utf8(json).unwrap(),
+            concat!(
+                r#"{"entries":[{"name":"a.txt","size":1,"crc32":3904355907,"modified":"2020-12-25T14:05:22Z"},"#,
+                r#"{"name":"dir/\"quoted\".txt","size":0,"crc32":0,"modified":null}]}"#
+            )
+        );
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+    }
+}
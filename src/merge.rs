@@ -0,0 +1,296 @@
+use crate::verify::{decode, read_raw_entries, Header};
+use crate::{ArchiveSummary, Error, Result, ZipArchive, ZipEntry, ZipState, SHA256};
+use std::collections::HashMap;
+use std::io::{Read, Seek, SeekFrom, Write};
+
+/// What to do with an entry whose name is already added when merging archives.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DuplicateName {
+    /// Keep the entry added first and skip the others.
+    KeepFirst,
+    /// Keep the entry added last.
+    KeepLast,
+    /// Fail with [`Error::DuplicateName`].
+    Reject,
+}
+
+/// Merge entries of archives into one archive in the order of `inputs` without recompression.
+///
+/// See [`ZipArchive::merge_archive`]. Entries replaced with [`DuplicateName::KeepLast`]
+/// are not written at all.
+///
+/// ```
+/// # use zip_builder::{merge, DuplicateName, Level, ZipArchive};
+/// # use std::io::Cursor;
+/// let mut shards = Vec::new();
+/// for name in ["a.txt", "b.txt"] {
+///     let mut shard = Vec::new();
+///     ZipArchive::new(&mut shard)
+///         .add_entry(name, b"content", Level::Default)?
+///         .finish()?;
+///     shards.push(Cursor::new(shard));
+/// }
+/// let mut output = Vec::new();
+/// let summary = merge(&mut shards, &mut output, DuplicateName::Reject)?;
+/// assert_eq!(summary.entries.len(), 2);
+/// # Ok::<(), zip_builder::Error>(())
+/// ```
+pub fn merge<R, W>(
+    inputs: &mut [R],
+    output: &mut W,
+    duplicates: DuplicateName,
+) -> Result<ArchiveSummary>
+where
+    R: Read + Seek,
+    W: Write,
+{
+    let mut bufs = Vec::with_capacity(inputs.len());
+    for input in inputs.iter_mut() {
+        let mut buf = Vec::new();
+        input.seek(SeekFrom::Start(0))?;
+        input.read_to_end(&mut buf)?;
+        bufs.push(buf);
+    }
+    let archives = bufs
+        .iter()
+        .map(|buf| read_raw_entries(buf).ok_or(Error::InvalidArchive))
+        .collect::<Result<Vec<_>>>()?;
+    let mut last = HashMap::new();
+    if duplicates == DuplicateName::KeepLast {
+        for (archive, entries) in archives.iter().enumerate() {
+            for (index, (header, _)) in entries.iter().enumerate() {
+                last.insert(header.filename.clone(), (archive, index));
+            }
+        }
+    }
+    let mut zip = ZipArchive::new(output);
+    for (archive, entries) in archives.into_iter().enumerate() {
+        for (index, (header, data)) in entries.into_iter().enumerate() {
+            if last
+                .get(&header.filename)
+                .is_some_and(|&kept| kept != (archive, index))
+            {
+                continue;
+            }
+            let result = zip.push_raw(header, data, duplicates);
+            zip = zip.or_poison(result)?;
+        }
+    }
+    zip.finish()
+}
+
+impl<'a, T: Write + ?Sized + 'a> ZipArchive<'a, T> {
+    /// Add entries of an existing archive by copying their compressed data.
+    ///
+    /// Names, methods, CRCs, modification times and external attributes are kept,
+    /// and data descriptors are replaced with sizes in local file headers.
+    /// Extra fields and comments are not copied. An entry whose name is already added is
+    /// handled by `duplicates`, and [`DuplicateName::KeepLast`] leaves the data of the
+    /// replaced entry as dead space like [`replace_entry`](ZipArchive::replace_entry).
+    /// Fails with [`Error::InvalidArchive`] if the archive is inconsistent or has
+    /// encrypted entries.
+    pub fn merge_archive<R: Read>(mut self, input: R, duplicates: DuplicateName) -> Result<Self> {
+        let result = self.push_merged(input, duplicates);
+        self.or_poison(result)
+    }
+
+    fn push_merged<R: Read>(&mut self, mut input: R, duplicates: DuplicateName) -> Result<()> {
+        self.check_poisoned()?;
+        let mut buf = Vec::new();
+        input.read_to_end(&mut buf)?;
+        let entries = read_raw_entries(&buf).ok_or(Error::InvalidArchive)?;
+        for (header, data) in entries {
+            self.push_raw(header, data, duplicates)?;
+        }
+        Ok(())
+    }
+
+    /// Write an entry of another archive with its compressed data as it is.
+    fn push_raw(&mut self, header: Header, data: &[u8], duplicates: DuplicateName) -> Result<()> {
+        self.check_poisoned()?;
+        let name = String::from_utf8_lossy(&header.filename).into_owned();
+        if self.overdue(&name) {
+            return Ok(());
+        }
+        let filename = self.prefixed(&name).into_owned();
+        let position = self
+            .entries
+            .iter()
+            .position(|entry| entry.filename == filename);
+        match (position, duplicates) {
+            (Some(_), DuplicateName::KeepFirst) => return Ok(()),
+            (Some(_), DuplicateName::Reject) => {
+                return Err(Error::DuplicateName { name: filename })
+            }
+            _ => {}
+        }
+        self.check_limits(&name, header.uncompressed_size as usize, position)?;
+        let sha256 = if self.config.digest {
+            // Data of unsupported methods has no digest.
+            match decode(header.method, data, header.uncompressed_size) {
+                Ok(Some(content)) => {
+                    let mut hasher = SHA256::default();
+                    hasher.write(&content);
+                    Some(hasher.finish())
+                }
+                Ok(None) => None,
+                Err(_) => return Err(Error::InvalidArchive),
+            }
+        } else {
+            None
+        };
+        self.state = ZipState::Processing;
+        self.record_case(&name);
+        let mut entry = ZipEntry {
+            flags: header.flags & !8,
+            method: header.method,
+            timestamp: header.timestamp,
+            checksum: header.checksum,
+            compressed_size: header.compressed_size,
+            uncompressed_size: header.uncompressed_size,
+            offset: self.offset,
+            data_offset: self.offset,
+            filename,
+            raw_name: self.raw_name(&header.filename),
+            sha256,
+            external_attributes: header.external_attributes,
+            extra: Vec::new(),
+            host: (header.version_made_by >> 8) as u8,
+            zip64: false,
+        };
+        self.write_local(&mut entry, &[], data)?;
+        if let Some(observer) = &self.config.observer {
+            observer.on_entry_complete(&entry.info());
+        }
+        self.total_size += entry.uncompressed_size;
+        match position {
+            Some(index) => {
+                self.total_size -= self.entries[index].uncompressed_size;
+                self.digests.retain(|_, value| *value != index);
+                self.entries[index] = entry;
+            }
+            None => self.entries.push(entry),
+        }
+        self.place(&name)?;
+        if self.config.auto_flush {
+            self.output.flush()?;
+        }
+        self.state = ZipState::Breathe;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{merge, DuplicateName};
+    use crate::verify::read_entries;
+    use crate::{verify, EntryOptions, Error, Level, ZipArchive, SHA256};
+    use std::io::Cursor;
+
+    fn shard(entries: &[(&str, &[u8])]) -> Cursor<Vec<u8>> {
+        let mut buf = Vec::new();
+        let mut zip = ZipArchive::new(&mut buf);
+        for (name, content) in entries {
+            zip = zip
+                .add_entry(
+                    name,
+                    content,
+                    EntryOptions::new(Level::High).data_descriptor(true),
+                )
+                .unwrap();
+        }
+        zip.finish().unwrap();
+        Cursor::new(buf)
+    }
+
+    fn contents(buf: &[u8]) -> Vec<(String, Vec<u8>)> {
+        read_entries(buf)
+            .unwrap()
+            .into_iter()
+            .map(|(header, content)| (String::from_utf8(header.filename).unwrap(), content))
+            .collect()
+    }
+
+    #[test]
+    fn it_works() {
+        let expected = [
+            (DuplicateName::KeepFirst, &b"first"[..]),
+            (DuplicateName::KeepLast, &b"last"[..]),
+        ];
+        for (duplicates, kept) in expected {
+            let mut shards = [
+                shard(&[("a.txt", &[b'a'; 1000]), ("dup.txt", b"first")]),
+                shard(&[("dup.txt", b"last"), ("b.txt", b"b")]),
+            ];
+            let mut buf = Vec::new();
+            let summary = merge(&mut shards, &mut buf, duplicates).unwrap();
+            assert_eq!(summary.size, buf.len() as u64);
+            assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+            assert_eq!(
+                contents(&buf),
+                [
+                    ("a.txt".to_string(), vec![b'a'; 1000]),
+                    ("dup.txt".to_string(), kept.to_vec()),
+                    ("b.txt".to_string(), b"b".to_vec()),
+                ]
+            );
+            // Only the kept entry is written.
+            assert_eq!(
+                buf.windows(7).filter(|window| window == b"dup.txt").count(),
+                2
+            );
+        }
+        let mut shards = [
+            shard(&[("dup.txt", b"first")]),
+            shard(&[("dup.txt", b"last")]),
+        ];
+        assert!(matches!(
+            merge(&mut shards, &mut Vec::new(), DuplicateName::Reject),
+            Err(Error::DuplicateName { name }) if name == "dup.txt"
+        ));
+    }
+
+    #[test]
+    fn merge_archive() {
+        let mut buf = Vec::new();
+        ZipArchive::new(&mut buf)
+            .add_entry("dup.txt", b"old", Level::Raw)
+            .unwrap()
+            .add_entry("c.txt", b"c", Level::Raw)
+            .unwrap()
+            .merge_archive(
+                shard(&[("dup.txt", b"new")]).into_inner().as_slice(),
+                DuplicateName::KeepLast,
+            )
+            .unwrap()
+            .finish()
+            .unwrap();
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+        assert_eq!(
+            contents(&buf),
+            [
+                ("dup.txt".to_string(), b"new".to_vec()),
+                ("c.txt".to_string(), b"c".to_vec()),
+            ]
+        );
+        assert!(matches!(
+            ZipArchive::new(&mut Vec::new())
+                .merge_archive(&b"not a zip"[..], DuplicateName::KeepFirst),
+            Err(Error::InvalidArchive)
+        ));
+
+        let mut buf = Vec::new();
+        let summary = ZipArchive::new(&mut buf)
+            .digest()
+            .merge_archive(
+                shard(&[("a.txt", &[b'a'; 100])]).into_inner().as_slice(),
+                DuplicateName::Reject,
+            )
+            .unwrap()
+            .finish()
+            .unwrap();
+        let mut hasher = SHA256::default();
+        hasher.write(&[b'a'; 100]);
+        assert_eq!(summary.entries[0].sha256, Some(hasher.finish()));
+    }
+}
@@ -0,0 +1,151 @@
+use crate::ZipEntry;
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Metadata of source files stored by [`add_file`](crate::ZipArchive::add_file).
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum MetadataPolicy {
+    /// Store nothing of the file. Entry options are used as they are.
+    None,
+    /// Store modification time in the header and extended timestamp (0x5455).
+    Times,
+    /// Store permissions and read-only flag in addition to times.
+    /// On Unix, uid and gid are also stored (0x7875).
+    Full,
+}
+
+/// Metadata of a file to be stored in an entry.
+#[derive(Default, Clone)]
+pub(crate) struct Metadata {
+    pub(crate) modified: Option<SystemTime>,
+    /// Name in bytes which is not UTF-8.
+    pub(crate) raw_name: Option<Vec<u8>>,
+    extra: Vec<u8>,
+    host: u8,
+    external_attributes: u32,
+}
+
+impl Metadata {
+    pub(crate) fn new(metadata: &fs::Metadata, policy: MetadataPolicy) -> Metadata {
+        let mut result = Metadata::default();
+        if policy == MetadataPolicy::None {
+            return result;
+        }
+        if let Ok(modified) = metadata.modified() {
+            result.set_modified(modified);
+        }
+        if policy == MetadataPolicy::Full {
+            if metadata.permissions().readonly() {
+                result.external_attributes |= 0x01;
+            }
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::MetadataExt;
+                result.set_unix(metadata.mode(), metadata.uid(), metadata.gid());
+            }
+        }
+        result
+    }
+
+    /// Set modification time with extended timestamp (0x5455).
+    pub(crate) fn set_modified(&mut self, modified: SystemTime) {
+        let seconds = modified
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs().min(i32::MAX as u64) as u32);
+        self.extra.extend_from_slice(&0x5455u16.to_le_bytes());
+        self.extra.extend_from_slice(&5u16.to_le_bytes());
+        self.extra.push(1);
+        self.extra.extend_from_slice(&seconds.to_le_bytes());
+        self.modified = Some(modified);
+    }
+
+    /// Set host system and external attributes as they are recorded.
+    #[cfg(any(feature = "verify", test))]
+    pub(crate) fn set_attributes(&mut self, host: u8, external_attributes: u32) {
+        self.host = host;
+        self.external_attributes = external_attributes;
+    }
+
+    /// Set Unix mode, and uid and gid (0x7875).
+    pub(crate) fn set_unix(&mut self, mode: u32, uid: u32, gid: u32) {
+        self.host = 3;
+        self.external_attributes |= mode << 16;
+        if mode & 0o222 == 0 {
+            self.external_attributes |= 0x01;
+        }
+        self.extra.extend_from_slice(&0x7875u16.to_le_bytes());
+        self.extra.extend_from_slice(&11u16.to_le_bytes());
+        self.extra.push(1);
+        self.extra.push(4);
+        self.extra.extend_from_slice(&uid.to_le_bytes());
+        self.extra.push(4);
+        self.extra.extend_from_slice(&gid.to_le_bytes());
+    }
+
+    pub(crate) fn apply(&self, entry: &mut ZipEntry) {
+        entry.extra.extend_from_slice(&self.extra);
+        if self.raw_name.is_some() {
+            entry.raw_name = self.raw_name.clone();
+        }
+        entry.host = self.host;
+        entry.external_attributes |= self.external_attributes;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::MetadataPolicy;
+    use crate::{verify, EntryOptions, Level, ZipArchive};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn archive(policy: MetadataPolicy) -> Vec<u8> {
+        let path = std::env::temp_dir().join(format!("zip-builder-metadata-{:?}", policy));
+        std::fs::write(&path, b"file content").unwrap();
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(UNIX_EPOCH + Duration::from_secs(1608905123))
+            .unwrap();
+        drop(file);
+        let mut buf = Vec::new();
+        ZipArchive::new(&mut buf)
+            .add_file(
+                "file.txt",
+                &path,
+                EntryOptions::new(Level::Raw).metadata(policy),
+            )
+            .unwrap()
+            .finish()
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+        buf
+    }
+
+    fn contains(buf: &[u8], bytes: &[u8]) -> usize {
+        buf.windows(bytes.len())
+            .filter(|window| *window == bytes)
+            .count()
+    }
+
+    #[test]
+    fn it_works() {
+        let mut timestamp = vec![0x55, 0x54, 5, 0, 1];
+        timestamp.extend_from_slice(&1608905123u32.to_le_bytes());
+
+        let none = archive(MetadataPolicy::None);
+        assert_eq!(contains(&none, &timestamp), 0);
+
+        let times = archive(MetadataPolicy::Times);
+        assert_eq!(contains(&times, &timestamp), 2);
+        assert_eq!(&times[10..14], &1369010347u32.to_le_bytes());
+
+        let full = archive(MetadataPolicy::Full);
+        assert_eq!(contains(&full, &timestamp), 2);
+        #[cfg(unix)]
+        {
+            assert_eq!(contains(&full, &[0x75, 0x78, 11, 0, 1, 4]), 2);
+            let central = full.len() - 22 - (46 + 8 + 9 + 15);
+            assert_eq!(full[central + 5], 3);
+            assert_eq!(full[central + 40] & 0x80, 0x80);
+        }
+    }
+}
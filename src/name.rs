@@ -0,0 +1,388 @@
+use crate::{CaseCollision, EntryOptions, Error, Metadata, Result, Unicode, ZipArchive};
+use std::borrow::Cow;
+use std::ffi::OsStr;
+use std::io::Write;
+use std::path::Path;
+
+/// Entry name of a file path, as [`ZipArchive::add_path`] names entries.
+///
+/// Leading `/`, `.` and `..` components are removed. On Windows, `\` is converted to `/`,
+/// and verbatim prefix `\\?\`, device prefix `\\.\`, drive letter and server and share
+/// of UNC paths are removed so that `C:\Users\me\file.txt` is `Users/me/file.txt`.
+pub fn entry_name_of_path(path: &Path) -> String {
+    normalize_path(&path.to_string_lossy(), cfg!(windows))
+}
+
+fn normalize_path(path: &str, windows: bool) -> String {
+    let path = if windows {
+        path.replace('\\', "/")
+    } else {
+        path.to_string()
+    };
+    let mut rest = path.as_str();
+    if windows {
+        let unc = if let Some(verbatim) = rest.strip_prefix("//?/UNC/") {
+            rest = verbatim;
+            true
+        } else if let Some(device) = rest
+            .strip_prefix("//?/")
+            .or_else(|| rest.strip_prefix("//./"))
+        {
+            rest = device;
+            false
+        } else if let Some(server) = rest.strip_prefix("//") {
+            rest = server;
+            true
+        } else {
+            false
+        };
+        if unc {
+            // Server and share.
+            rest = rest.splitn(3, '/').nth(2).unwrap_or("");
+        }
+        let bytes = rest.as_bytes();
+        if bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' {
+            rest = &rest[2..];
+        }
+    }
+    let mut name = rest
+        .split('/')
+        .filter(|component| !matches!(*component, "" | "." | ".."))
+        .collect::<Vec<_>>()
+        .join("/");
+    if !name.is_empty() && rest.ends_with('/') {
+        name.push('/');
+    }
+    name
+}
+
+/// Name compared for case collision, in lower case of NFC if `unicode-normalization` is enabled.
+fn fold_case(name: &str) -> String {
+    #[cfg(feature = "unicode-normalization")]
+    let name: Cow<str> = {
+        use unicode_normalization::UnicodeNormalization;
+        Cow::Owned(name.nfc().collect())
+    };
+    #[cfg(not(feature = "unicode-normalization"))]
+    let name = Cow::Borrowed(name);
+    name.to_lowercase()
+}
+
+impl<'a, T: Write + ?Sized + 'a> ZipArchive<'a, T> {
+    /// Name of the entry in the archive with the root prefix.
+    pub(crate) fn prefixed<'n>(&self, name: &'n str) -> Cow<'n, str> {
+        match self.config.root_prefix.as_str() {
+            "" => Cow::Borrowed(name),
+            prefix => Cow::Owned(format!("{}{}", prefix, name)),
+        }
+    }
+
+    /// Check that the name does not collide with an existing name by case.
+    pub(crate) fn check_case(&self, name: &str) -> Result<()> {
+        let action = match self.config.case_collision {
+            Some(action) => action,
+            None => return Ok(()),
+        };
+        let existing = match self.folded_names.get(&fold_case(name)) {
+            Some(existing) if existing != name => existing,
+            _ => return Ok(()),
+        };
+        if let Some(observer) = &self.config.observer {
+            observer.on_case_collision(name, existing);
+        }
+        match action {
+            CaseCollision::Report => Ok(()),
+            CaseCollision::Reject => Err(Error::CaseCollision {
+                name: name.to_string(),
+                existing: existing.to_string(),
+            }),
+        }
+    }
+
+    /// Record the name for [`check_case`](ZipArchive::check_case).
+    pub(crate) fn record_case(&mut self, name: &str) {
+        if self.config.case_collision.is_some() {
+            self.folded_names
+                .entry(fold_case(name))
+                .or_insert_with(|| name.to_string());
+        }
+    }
+
+    /// Forget the name recorded by an entry which is rolled back.
+    pub(crate) fn forget_case(&mut self, name: &str) {
+        self.folded_names.remove(&fold_case(name));
+    }
+
+    /// Add a entry of the name in bytes, which may not be UTF-8.
+    ///
+    /// The bytes are written as they are, for names of file systems like old Linux servers.
+    /// UTF-8 flag follows [`EntryOptions::unicode`] if it is set, otherwise the flag is set
+    /// only if the name is UTF-8 with non-ASCII characters.
+    /// [`EntryInfo`](crate::EntryInfo) has the name with invalid bytes replaced.
+    pub fn add_entry_bytes<O: Into<EntryOptions>>(
+        mut self,
+        name: &[u8],
+        content: &[u8],
+        options: O,
+    ) -> Result<Self> {
+        let result = self.push_entry_bytes(name, content, options);
+        self.or_poison(result)
+    }
+
+    /// Add a entry of the name in bytes without consuming the archive.
+    ///
+    /// See [`add_entry_bytes`](ZipArchive::add_entry_bytes) and
+    /// [`push_entry`](ZipArchive::push_entry).
+    pub fn push_entry_bytes<O: Into<EntryOptions>>(
+        &mut self,
+        name: &[u8],
+        content: &[u8],
+        options: O,
+    ) -> Result<()> {
+        let lossy = String::from_utf8_lossy(name);
+        let length = self.config.root_prefix.len() + name.len();
+        if length > u16::MAX as usize {
+            return Err(Error::NameTooLong {
+                name: lossy.into_owned(),
+                length,
+            });
+        }
+        let options = options.into();
+        let utf8 = match options.inherit(&self.config.defaults).unicode_policy() {
+            Some(Unicode::Always) => true,
+            Some(Unicode::Never) => false,
+            _ => std::str::from_utf8(name).is_ok_and(|name| !name.is_ascii()),
+        };
+        let options = options.unicode(if utf8 {
+            Unicode::Always
+        } else {
+            Unicode::Never
+        });
+        let mut metadata = Metadata::default();
+        metadata.raw_name = self.raw_name(name);
+        self.push_entry_with(&lossy, content, options, &metadata)
+    }
+
+    /// Name in bytes with the root prefix if it is not UTF-8.
+    pub(crate) fn raw_name(&self, name: &[u8]) -> Option<Vec<u8>> {
+        if std::str::from_utf8(name).is_ok() {
+            return None;
+        }
+        let prefix = self.config.root_prefix.as_bytes();
+        Some([prefix, name].concat())
+    }
+
+    /// Add a file of `path` as an entry named by [`entry_name_of_path`].
+    ///
+    /// See [`add_file`](ZipArchive::add_file).
+    pub fn add_path<P: AsRef<Path>, O: Into<EntryOptions>>(
+        self,
+        path: P,
+        options: O,
+    ) -> Result<Self> {
+        let name = entry_name_of_path(path.as_ref());
+        self.add_file(&name, path, options)
+    }
+
+    /// Add a entry of the name of OS string, such as a file name of [`std::fs::read_dir`].
+    ///
+    /// On Unix the name is written in bytes as [`add_entry_bytes`](ZipArchive::add_entry_bytes).
+    /// On other platforms it is converted to UTF-8, replacing invalid characters.
+    pub fn add_entry_os<O: Into<EntryOptions>>(
+        self,
+        name: &OsStr,
+        content: &[u8],
+        options: O,
+    ) -> Result<Self> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+            self.add_entry_bytes(name.as_bytes(), content, options)
+        }
+        #[cfg(not(unix))]
+        {
+            self.add_entry(&name.to_string_lossy(), content, options)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::normalize_path;
+    use crate::{verify, EntryOptions, Level, Observer, Unicode, ZipArchive};
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn paths() {
+        for &(path, name) in [
+            (r"C:\Users\me\file.txt", "Users/me/file.txt"),
+            (r"c:file.txt", "file.txt"),
+            (r"\\?\C:\Users\me\file.txt", "Users/me/file.txt"),
+            (r"\\?\UNC\server\share\dir\file.txt", "dir/file.txt"),
+            (r"\\server\share\dir\file.txt", "dir/file.txt"),
+            (r"\\.\C:\file.txt", "file.txt"),
+            (r".\dir\..\file.txt", "dir/file.txt"),
+            (r"dir\sub\", "dir/sub/"),
+            ("/mixed\\sep/file.txt", "mixed/sep/file.txt"),
+        ]
+        .iter()
+        {
+            assert_eq!(normalize_path(path, true), name, "{}", path);
+        }
+        assert_eq!(
+            normalize_path("/home/me/./file.txt", false),
+            "home/me/file.txt"
+        );
+        assert_eq!(normalize_path("dir/back\\slash", false), "dir/back\\slash");
+        assert_eq!(normalize_path("C:/file.txt", false), "C:/file.txt");
+    }
+
+    #[test]
+    fn bytes() {
+        let mut buf = Vec::new();
+        let summary = ZipArchive::new(&mut buf)
+            .add_entry_bytes(b"caf\xe9.txt", b"latin-1", Level::Raw)
+            .unwrap()
+            .add_entry_bytes("café.txt".as_bytes(), b"utf-8", Level::Raw)
+            .unwrap()
+            .add_entry_bytes(
+                b"forced\xff",
+                b"forced",
+                EntryOptions::new(Level::Raw).unicode(Unicode::Always),
+            )
+            .unwrap()
+            .finish()
+            .unwrap();
+        assert_eq!(summary.entries[0].name, "caf\u{FFFD}.txt");
+        assert_eq!(summary.entries[1].name, "café.txt");
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+        let flags = |offset: usize| u16::from_le_bytes([buf[offset + 6], buf[offset + 7]]);
+        assert_eq!(&buf[30..38], b"caf\xe9.txt");
+        assert_eq!(flags(0) & 2048, 0);
+        let second = 30 + 8 + 7;
+        assert_eq!(&buf[second + 30..second + 39], "café.txt".as_bytes());
+        assert_eq!(flags(second) & 2048, 2048);
+        let third = second + 30 + 9 + 5;
+        assert_eq!(&buf[third + 30..third + 37], b"forced\xff");
+        assert_eq!(flags(third) & 2048, 2048);
+    }
+
+    #[test]
+    fn root_prefix() {
+        use crate::ZipArchiveBuilder;
+        let mut buf = Vec::new();
+        let summary = ZipArchiveBuilder::new()
+            .root_prefix("myapp-1.2.3/")
+            .build(&mut buf)
+            .add_entry("README.md", b"old", Level::Raw)
+            .unwrap()
+            .add_entry_bytes(b"caf\xe9.txt", b"latin-1", Level::Raw)
+            .unwrap()
+            .replace_entry("README.md", b"new", Level::Raw)
+            .unwrap()
+            .finish()
+            .unwrap();
+        let names: Vec<_> = summary
+            .entries
+            .iter()
+            .map(|entry| &entry.name[..])
+            .collect();
+        assert_eq!(
+            names,
+            ["myapp-1.2.3/README.md", "myapp-1.2.3/caf\u{FFFD}.txt"]
+        );
+        assert_eq!(&buf[30..51], b"myapp-1.2.3/README.md");
+        let second = 30 + 21 + 3;
+        assert_eq!(&buf[second + 30..second + 50], b"myapp-1.2.3/caf\xe9.txt");
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+    }
+
+    #[derive(Default)]
+    struct Collisions(Mutex<Vec<(String, String)>>);
+
+    impl Observer for Arc<Collisions> {
+        fn on_case_collision(&self, name: &str, existing: &str) {
+            let pair = (name.to_string(), existing.to_string());
+            self.0.lock().unwrap().push(pair);
+        }
+    }
+
+    #[test]
+    fn case_collision() {
+        use crate::{CaseCollision, Error, ZipArchiveBuilder};
+        let collisions = Arc::new(Collisions::default());
+        let mut buf = Vec::new();
+        let mut zip = ZipArchiveBuilder::new()
+            .case_collision(CaseCollision::Reject)
+            .observer(collisions.clone())
+            .build(&mut buf)
+            .add_entry("README.md", b"readme", Level::Raw)
+            .unwrap()
+            .add_entry("README.md", b"same name", Level::Raw)
+            .unwrap();
+        assert!(matches!(
+            zip.push_entry("Readme.md", b"collision", Level::Raw),
+            Err(Error::CaseCollision { name, existing })
+                if name == "Readme.md" && existing == "README.md"
+        ));
+        zip.push_entry("Readme.txt", b"other", Level::Raw).unwrap();
+        zip.finish().unwrap();
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+        assert_eq!(
+            *collisions.0.lock().unwrap(),
+            vec![("Readme.md".to_string(), "README.md".to_string())]
+        );
+
+        let mut buf = Vec::new();
+        let summary = ZipArchiveBuilder::new()
+            .case_collision(CaseCollision::Report)
+            .build(&mut buf)
+            .add_entry("Dir/", b"", Level::Raw)
+            .unwrap()
+            .add_entry("dir/", b"", Level::Raw)
+            .unwrap()
+            .finish()
+            .unwrap();
+        assert_eq!(summary.entries.len(), 2);
+    }
+
+    #[cfg(feature = "unicode-normalization")]
+    #[test]
+    fn normalized_case_collision() {
+        use crate::{CaseCollision, Error, ZipArchiveBuilder};
+        let mut buf = Vec::new();
+        let mut zip = ZipArchiveBuilder::new()
+            .case_collision(CaseCollision::Reject)
+            .build(&mut buf)
+            .add_entry("Caf\u{e9}.txt", b"composed", Level::Raw)
+            .unwrap();
+        assert!(matches!(
+            zip.push_entry("cafe\u{301}.txt", b"decomposed", Level::Raw),
+            Err(Error::CaseCollision { .. })
+        ));
+    }
+
+    #[cfg(feature = "unicode-normalization")]
+    #[test]
+    fn normalization() {
+        use crate::{Normalization, ZipArchiveBuilder};
+        let mut buf = Vec::new();
+        let summary = ZipArchiveBuilder::new()
+            .normalization(Normalization::Nfc)
+            .build(&mut buf)
+            .add_entry("cafe\u{301}.txt", b"decomposed", Level::Raw)
+            .unwrap()
+            .add_entry(
+                "caf\u{e9}/",
+                b"",
+                EntryOptions::new(Level::Raw).normalization(Normalization::Nfd),
+            )
+            .unwrap()
+            .finish()
+            .unwrap();
+        assert_eq!(summary.entries[0].name, "caf\u{e9}.txt");
+        assert_eq!(summary.entries[1].name, "cafe\u{301}/");
+        assert_eq!(&buf[30..39], "caf\u{e9}.txt".as_bytes());
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+    }
+}
@@ -0,0 +1,121 @@
+use crate::EntryInfo;
+
+/// Statistics of a finished archive given to [`Observer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ArchiveStats {
+    /// Number of entries.
+    pub entries: usize,
+    /// Total uncompressed size of entries.
+    pub bytes_in: u64,
+    /// Size of the whole archive written to the output.
+    pub bytes_out: u64,
+}
+
+/// Hooks called during archive generation.
+///
+/// This is for wiring metrics like counters of the host application.
+/// All methods do nothing by default.
+pub trait Observer {
+    /// Called after an entry is written.
+    fn on_entry_complete(&self, _entry: &EntryInfo) {}
+
+    /// Called when compression of an entry is worse than the threshold set by
+    /// [`compression_ratio_threshold`](crate::ZipArchiveBuilder::compression_ratio_threshold).
+    ///
+    /// `ratio` is compressed size divided by uncompressed size.
+    fn on_poor_compression(&self, _name: &str, _ratio: f64) {}
+
+    /// Called when `name` differs from `existing` name only by case, if
+    /// [`case_collision`](crate::ZipArchiveBuilder::case_collision) is set.
+    fn on_case_collision(&self, _name: &str, _existing: &str) {}
+
+    /// Called after the archive is finished.
+    fn on_archive_complete(&self, _stats: &ArchiveStats) {}
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ArchiveStats, Observer};
+    use crate::{verify, EntryInfo, Level, PoorCompression, ZipArchiveBuilder};
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Default)]
+    struct Counters {
+        entries: AtomicU64,
+        bytes_in: AtomicU64,
+        stats: Mutex<Option<ArchiveStats>>,
+        poor: Mutex<Vec<String>>,
+    }
+
+    impl Observer for Arc<Counters> {
+        fn on_entry_complete(&self, entry: &EntryInfo) {
+            self.entries.fetch_add(1, Ordering::SeqCst);
+            self.bytes_in
+                .fetch_add(entry.uncompressed_size, Ordering::SeqCst);
+        }
+
+        fn on_poor_compression(&self, name: &str, ratio: f64) {
+            assert!(ratio > 0.9);
+            self.poor.lock().unwrap().push(name.to_string());
+        }
+
+        fn on_archive_complete(&self, stats: &ArchiveStats) {
+            *self.stats.lock().unwrap() = Some(*stats);
+        }
+    }
+
+    #[test]
+    fn it_works() {
+        let counters = Arc::new(Counters::default());
+        let mut buf = Vec::new();
+        ZipArchiveBuilder::new()
+            .observer(counters.clone())
+            .build(&mut buf)
+            .add_entry("raw.txt", b"raw content", Level::Raw)
+            .unwrap()
+            .add_entry("deflate.txt", &[b'a'; 1000], Level::High)
+            .unwrap()
+            .finish()
+            .unwrap();
+        assert_eq!(counters.entries.load(Ordering::SeqCst), 2);
+        assert_eq!(counters.bytes_in.load(Ordering::SeqCst), 1011);
+        assert_eq!(
+            *counters.stats.lock().unwrap(),
+            Some(ArchiveStats {
+                entries: 2,
+                bytes_in: 1011,
+                bytes_out: buf.len() as u64,
+            })
+        );
+    }
+
+    #[test]
+    fn poor_compression() {
+        // Bytes of a linear congruential generator barely compress.
+        let noise: Vec<u8> = (0..1000u32)
+            .scan(1u32, |state, _| {
+                *state = state.wrapping_mul(1103515245).wrapping_add(12345);
+                Some((*state >> 24) as u8)
+            })
+            .collect();
+        for (action, method) in [(PoorCompression::Report, 8), (PoorCompression::Store, 0)] {
+            let counters = Arc::new(Counters::default());
+            let mut buf = Vec::new();
+            let summary = ZipArchiveBuilder::new()
+                .observer(counters.clone())
+                .compression_ratio_threshold(0.9, action)
+                .build(&mut buf)
+                .add_entry("text.txt", &[b'a'; 1000], Level::Default)
+                .unwrap()
+                .add_entry("noise.bin", &noise, Level::Default)
+                .unwrap()
+                .finish()
+                .unwrap();
+            assert_eq!(*counters.poor.lock().unwrap(), ["noise.bin"]);
+            assert_eq!(buf[summary.entries[1].offset as usize + 8], method);
+            assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+        }
+    }
+}
@@ -0,0 +1,239 @@
+use crate::{ArchiveSummary, EntryInfo, Level, Result, ZipArchive};
+use std::io::Write;
+
+const CONTENT_TYPES: &str = "[Content_Types].xml";
+const SHEET_TYPE: &str =
+    "application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml";
+const RELATIONSHIPS: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
+
+/// Value of a cell of worksheet.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Cell<'c> {
+    /// Cell without value.
+    Empty,
+    /// Numeric value. Infinity and NaN are written as empty cells.
+    Number(f64),
+    /// Text value, written as inline string.
+    Text(&'c str),
+}
+
+impl From<f64> for Cell<'_> {
+    fn from(number: f64) -> Self {
+        Cell::Number(number)
+    }
+}
+
+impl<'c> From<&'c str> for Cell<'c> {
+    fn from(text: &'c str) -> Self {
+        Cell::Text(text)
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Column name like `A`, `Z`, `AA` of 0-based index.
+fn column(mut index: usize) -> String {
+    let mut name = Vec::new();
+    loop {
+        name.push(b'A' + (index % 26) as u8);
+        if index < 26 {
+            break;
+        }
+        index = index / 26 - 1;
+    }
+    name.reverse();
+    String::from_utf8(name).unwrap()
+}
+
+fn content_types(entries: &[EntryInfo]) -> Vec<u8> {
+    let mut xml = String::from(concat!(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+        r#"<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types">"#,
+        r#"<Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/>"#,
+        r#"<Default Extension="xml" ContentType="application/xml"/>"#,
+        r#"<Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/>"#,
+    ));
+    for entry in entries {
+        if entry.name.starts_with("xl/worksheets/") {
+            xml.push_str(&format!(
+                r#"<Override PartName="/{}" ContentType="{}"/>"#,
+                entry.name, SHEET_TYPE
+            ));
+        }
+    }
+    xml.push_str("</Types>");
+    xml.into_bytes()
+}
+
+/// Writer of spreadsheet (`.xlsx`) over [`ZipArchive`].
+///
+/// Boilerplate parts are written by this, so only worksheets are to be given.
+/// Rows of a worksheet are serialized as they are taken from the iterator,
+/// and the part is compressed when the iterator ends.
+///
+/// ```rust
+/// use zip_builder::{Cell, Workbook, ZipArchive};
+///
+/// let mut buf = Vec::new();
+/// Workbook::new(ZipArchive::new(&mut buf))
+///     .add_sheet("Prices", (1..=3).map(|n| vec![Cell::Text("item"), Cell::Number(n as f64)]))?
+///     .finish()?;
+/// # Ok::<(), zip_builder::Error>(())
+/// ```
+pub struct Workbook<'a, T: Write + ?Sized + 'a> {
+    zip: ZipArchive<'a, T>,
+    sheets: Vec<String>,
+}
+
+impl<'a, T: Write + ?Sized + 'a> Workbook<'a, T> {
+    /// Start a workbook in the empty archive.
+    pub fn new(zip: ZipArchive<'a, T>) -> Workbook<'a, T> {
+        Workbook {
+            zip: zip.add_lazy_entry(CONTENT_TYPES, Level::Default, content_types),
+            sheets: Vec::new(),
+        }
+    }
+
+    /// Add a worksheet of `name` with `rows`.
+    pub fn add_sheet<'c, R, C>(mut self, name: &str, rows: R) -> Result<Self>
+    where
+        R: IntoIterator,
+        R::Item: IntoIterator<Item = C>,
+        C: Into<Cell<'c>>,
+    {
+        let mut xml = String::from(concat!(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+            r#"<worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main">"#,
+            "<sheetData>",
+        ));
+        for (row, cells) in rows.into_iter().enumerate() {
+            xml.push_str(&format!(r#"<row r="{}">"#, row + 1));
+            for (index, cell) in cells.into_iter().enumerate() {
+                let reference = format!("{}{}", column(index), row + 1);
+                match cell.into() {
+                    Cell::Empty => {}
+                    Cell::Number(number) if !number.is_finite() => {}
+                    Cell::Number(number) => {
+                        xml.push_str(&format!(r#"<c r="{}"><v>{}</v></c>"#, reference, number))
+                    }
+                    Cell::Text(text) => xml.push_str(&format!(
+                        r#"<c r="{}" t="inlineStr"><is><t xml:space="preserve">{}</t></is></c>"#,
+                        reference,
+                        escape(text)
+                    )),
+                }
+            }
+            xml.push_str("</row>");
+        }
+        xml.push_str("</sheetData></worksheet>");
+        self.sheets.push(name.to_string());
+        let part = format!("xl/worksheets/sheet{}.xml", self.sheets.len());
+        self.zip = self.zip.add_entry(&part, xml.as_bytes(), Level::Default)?;
+        Ok(self)
+    }
+
+    /// Write the workbook and the relationships, and finish the archive.
+    pub fn finish(self) -> Result<ArchiveSummary> {
+        let mut workbook = String::from(concat!(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+            r#"<workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" "#,
+            r#"xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships">"#,
+            "<sheets>",
+        ));
+        let mut relationships = String::from(concat!(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+            r#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">"#,
+        ));
+        for (index, name) in self.sheets.iter().enumerate() {
+            workbook.push_str(&format!(
+                r#"<sheet name="{}" sheetId="{}" r:id="rId{}"/>"#,
+                escape(name),
+                index + 1,
+                index + 1
+            ));
+            relationships.push_str(&format!(
+                r#"<Relationship Id="rId{}" Type="{}/worksheet" Target="worksheets/sheet{}.xml"/>"#,
+                index + 1,
+                RELATIONSHIPS,
+                index + 1
+            ));
+        }
+        workbook.push_str("</sheets></workbook>");
+        relationships.push_str("</Relationships>");
+        let package = format!(
+            concat!(
+                r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+                r#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">"#,
+                r#"<Relationship Id="rId1" Type="{}/officeDocument" Target="xl/workbook.xml"/>"#,
+                "</Relationships>"
+            ),
+            RELATIONSHIPS
+        );
+        self.zip
+            .add_entry("_rels/.rels", package.as_bytes(), Level::Default)?
+            .add_entry("xl/workbook.xml", workbook.as_bytes(), Level::Default)?
+            .add_entry(
+                "xl/_rels/workbook.xml.rels",
+                relationships.as_bytes(),
+                Level::Default,
+            )?
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{column, escape, Cell, Workbook};
+    use crate::{verify, ZipArchive};
+
+    #[test]
+    fn it_works() {
+        assert_eq!(column(0), "A");
+        assert_eq!(column(25), "Z");
+        assert_eq!(column(26), "AA");
+        assert_eq!(column(701), "ZZ");
+        assert_eq!(column(702), "AAA");
+        assert_eq!(escape("<a & \"b\">"), "&lt;a &amp; &quot;b&quot;&gt;");
+
+        let mut buf = Vec::new();
+        let summary = Workbook::new(ZipArchive::new(&mut buf))
+            .add_sheet("Numbers", (1..=3).map(|n| [n as f64, (n * n) as f64]))
+            .unwrap()
+            .add_sheet(
+                "Text",
+                vec![vec![Cell::Text("a < b"), Cell::Empty, "c".into()]],
+            )
+            .unwrap()
+            .finish()
+            .unwrap();
+        let names: Vec<_> = summary
+            .entries
+            .iter()
+            .map(|entry| &entry.name[..])
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "[Content_Types].xml",
+                "xl/worksheets/sheet1.xml",
+                "xl/worksheets/sheet2.xml",
+                "_rels/.rels",
+                "xl/workbook.xml",
+                "xl/_rels/workbook.xml.rels"
+            ]
+        );
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+    }
+}
@@ -0,0 +1,629 @@
+use crate::builder::TimeSlice;
+use crate::metadata::MetadataPolicy;
+use crate::time::{DateTime, TimeOverflow, TimeRounding, LAST_YEAR_OF_DOS_TIME};
+use crate::Level;
+use crate::{Error, Result};
+use deflate::deflate_bytes_conf;
+use deflate::write::DeflateEncoder;
+use deflate::Compression;
+use deflate::CompressionOptions;
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use std::io::Write;
+use std::ops::BitOr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Represents strategy of the deflate compressor.
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum Strategy {
+    /// Search matches according to the level.
+    Default,
+    /// Only Huffman coding without matching. Fast, for high-entropy data.
+    HuffmanOnly,
+    /// Only run-length matching. For data with runs of same bytes like simple images.
+    Rle,
+}
+
+/// Padding before data of an entry at a fixed offset.
+///
+/// See [`EntryOptions::fixed_offset`].
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum Padding {
+    /// Zero bytes before the local file header, which extractors skip as dead space.
+    Zeros,
+    /// Extra field (0x5A42) of zeros in the local file header. The gap must be zero,
+    /// or from 4 bytes up to 65535 bytes of the field data.
+    ExtraField,
+}
+
+/// Header ID of the extra field of [`Padding::ExtraField`].
+pub(crate) const PADDING_EXTRA: u16 = 0x5A42;
+
+/// Policy of UTF-8 flag (general purpose bit 11) for entry names.
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum Unicode {
+    /// Always set the flag.
+    Always,
+    /// Set the flag only if the name contains non-ASCII characters.
+    Auto,
+    /// Never set the flag.
+    Never,
+}
+
+/// Size of the sample compressed in two-pass mode.
+const TWO_PASS_SAMPLE: usize = 1 << 16;
+
+/// Unicode normalization form of entry names.
+#[cfg(feature = "unicode-normalization")]
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum Normalization {
+    /// Composed form, which most file systems and extractors use.
+    Nfc,
+    /// Decomposed form, which file names of macOS (HFS+) have.
+    Nfd,
+}
+
+/// DOS file attributes recorded in external file attributes.
+///
+/// Combine them with `|`.
+#[derive(Eq, PartialEq, Clone, Copy, Debug, Default)]
+pub struct DosAttributes(u8);
+
+impl DosAttributes {
+    /// Read-only file.
+    pub const READ_ONLY: DosAttributes = DosAttributes(0x01);
+    /// Hidden file.
+    pub const HIDDEN: DosAttributes = DosAttributes(0x02);
+    /// System file.
+    pub const SYSTEM: DosAttributes = DosAttributes(0x04);
+    /// Directory.
+    pub const DIRECTORY: DosAttributes = DosAttributes(0x10);
+    /// File to be archived.
+    pub const ARCHIVE: DosAttributes = DosAttributes(0x20);
+
+    /// Raw value of the attributes.
+    pub fn bits(self) -> u8 {
+        self.0
+    }
+}
+
+impl BitOr for DosAttributes {
+    type Output = DosAttributes;
+
+    fn bitor(self, other: DosAttributes) -> DosAttributes {
+        DosAttributes(self.0 | other.0)
+    }
+}
+
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+enum Codec {
+    Deflate,
+    #[cfg(feature = "brotli")]
+    Brotli(u16),
+    #[cfg(feature = "ppmd")]
+    Ppmd,
+}
+
+/// Options of an entry.
+///
+/// [`Level`] can be used where options are expected.
+/// Options which are not set are inherited from the archive defaults
+/// given by [`ZipArchiveBuilder`](crate::ZipArchiveBuilder).
+#[derive(Eq, PartialEq, Clone, Copy, Debug, Default)]
+pub struct EntryOptions {
+    level: Option<Level>,
+    strategy: Option<Strategy>,
+    codec: Option<Codec>,
+    modified: Option<SystemTime>,
+    time_rounding: Option<TimeRounding>,
+    time_overflow: Option<TimeOverflow>,
+    unicode: Option<Unicode>,
+    #[cfg(feature = "unicode-normalization")]
+    normalization: Option<Normalization>,
+    alignment: Option<u32>,
+    fixed_offset: Option<(u64, Padding)>,
+    compression_hint: Option<bool>,
+    two_pass: Option<bool>,
+    entropy_probe: Option<bool>,
+    data_descriptor: Option<bool>,
+    dos_attributes: Option<DosAttributes>,
+    metadata: Option<MetadataPolicy>,
+    #[cfg(feature = "aes")]
+    encrypt: Option<bool>,
+}
+
+impl EntryOptions {
+    /// Create options with compression level.
+    pub fn new(level: Level) -> EntryOptions {
+        EntryOptions::default().level(level)
+    }
+
+    /// Set compression level. [`Level::Default`] if not set.
+    pub fn level(mut self, level: Level) -> Self {
+        self.level = Some(level);
+        self
+    }
+
+    /// Set strategy of the deflate compressor.
+    ///
+    /// It is ignored for [`Level::Raw`].
+    pub fn strategy(mut self, strategy: Strategy) -> Self {
+        self.strategy = Some(strategy);
+        self
+    }
+
+    /// Set modification time. Current time if not set.
+    pub fn modified(mut self, time: SystemTime) -> Self {
+        self.modified = Some(time);
+        self
+    }
+
+    /// Set rounding of modification time to DOS time. [`TimeRounding::Truncate`] if not set.
+    ///
+    /// Extended timestamp is not rounded.
+    pub fn time_rounding(mut self, rounding: TimeRounding) -> Self {
+        self.time_rounding = Some(rounding);
+        self
+    }
+
+    /// Set handling of modification time after 2107. [`TimeOverflow::Clamp`] if not set.
+    pub fn time_overflow(mut self, overflow: TimeOverflow) -> Self {
+        self.time_overflow = Some(overflow);
+        self
+    }
+
+    /// Set policy of UTF-8 flag. [`Unicode::Always`] if not set.
+    pub fn unicode(mut self, unicode: Unicode) -> Self {
+        self.unicode = Some(unicode);
+        self
+    }
+
+    /// Set Unicode normalization form of the name. Not normalized if not set.
+    ///
+    /// Names listed on macOS are decomposed, so [`Normalization::Nfc`] avoids names
+    /// which look the same but differ on other platforms.
+    #[cfg(feature = "unicode-normalization")]
+    pub fn normalization(mut self, normalization: Normalization) -> Self {
+        self.normalization = Some(normalization);
+        self
+    }
+
+    /// Align data of stored entries to multiple of `alignment` bytes.
+    ///
+    /// Padding is written as extra field (0xD935) of local file header,
+    /// in the same manner as zipalign of Android. Adding the entry fails with
+    /// [`Error::ExtraFieldTooLong`] if the padding does not fit in the extra field,
+    /// which may happen for alignments above 65529. Alignments which do not fit
+    /// in 16 bits, such as 65536 for pages of 64 KB, are recorded as 0 in the field.
+    pub fn alignment(mut self, alignment: u32) -> Self {
+        self.alignment = Some(alignment);
+        self
+    }
+
+    /// Place data of the entry at `offset` of the archive with `padding` before it,
+    /// such as files at fixed addresses of firmware images. Not placed if not set.
+    ///
+    /// The offset counts in the same way as offsets in the central directory.
+    /// This is never inherited from the archive defaults, overrides the alignment,
+    /// and the entry is neither streamed, spooled nor deduplicated.
+    /// Adding the entry fails with [`Error::OffsetUnreachable`](crate::Error::OffsetUnreachable)
+    /// if its data would start after `offset` or the gap does not fit in the padding.
+    pub fn fixed_offset(mut self, offset: u64, padding: Padding) -> Self {
+        self.fixed_offset = Some((offset, padding));
+        self
+    }
+
+    /// Record the deflate level in general purpose bits 1 and 2. Not recorded if not set.
+    ///
+    /// [`Level::High`] is marked as maximum and [`Level::Low`] as fast.
+    /// Some extractors only show the bits, they never affect decompression.
+    pub fn compression_hint(mut self, hint: bool) -> Self {
+        self.compression_hint = Some(hint);
+        self
+    }
+
+    /// Choose the method by compressing a sample of the content first. Not chosen if not set.
+    ///
+    /// The first 64 KiB is compressed by each of deflate and PPMd (with feature `ppmd`)
+    /// at the level, and the content is compressed by the smallest of them,
+    /// or stored if none is smaller than the sample. [`Level::Raw`] is still stored.
+    /// Zstandard is not a candidate since this crate has no zstd encoder.
+    pub fn two_pass(mut self, two_pass: bool) -> Self {
+        self.two_pass = Some(two_pass);
+        self
+    }
+
+    /// Write CRC and sizes in data descriptor (PK0708) after the data,
+    /// and set general purpose bit 3. Not used if not set.
+    ///
+    /// Local file header has zeros for them, as streaming writers make.
+    /// This is for testing extractors which have to handle such archives.
+    pub fn data_descriptor(mut self, descriptor: bool) -> Self {
+        self.data_descriptor = Some(descriptor);
+        self
+    }
+
+    /// Set DOS file attributes. None of them if not set.
+    pub fn dos_attributes(mut self, attributes: DosAttributes) -> Self {
+        self.dos_attributes = Some(attributes);
+        self
+    }
+
+    /// Set metadata of source files stored by [`add_file`](crate::ZipArchive::add_file).
+    /// [`MetadataPolicy::None`] if not set.
+    ///
+    /// Times of the file take precedence over [`modified`](EntryOptions::modified).
+    pub fn metadata(mut self, policy: MetadataPolicy) -> Self {
+        self.metadata = Some(policy);
+        self
+    }
+
+    /// Store the content without compression if [`estimate_entropy`](crate::estimate_entropy)
+    /// of it is 7.8 bits per byte or more. Not probed if not set.
+    ///
+    /// This skips compressing already compressed data, and is checked before
+    /// [`two_pass`](EntryOptions::two_pass).
+    pub fn entropy_probe(mut self, probe: bool) -> Self {
+        self.entropy_probe = Some(probe);
+        self
+    }
+
+    /// Encrypt with AES-256 (WinZip AE-2) by the password of the archive. Not encrypted if not set.
+    ///
+    /// Set the password with [`ZipArchiveBuilder::password`](crate::ZipArchiveBuilder::password).
+    /// Names of entries are not encrypted, see [`wrap_encrypted`](crate::wrap_encrypted).
+    #[cfg(feature = "aes")]
+    pub fn encrypt(mut self, encrypt: bool) -> Self {
+        self.encrypt = Some(encrypt);
+        self
+    }
+
+    /// Options where unset ones are taken from `defaults`.
+    pub(crate) fn inherit(self, defaults: &EntryOptions) -> EntryOptions {
+        EntryOptions {
+            level: self.level.or(defaults.level),
+            strategy: self.strategy.or(defaults.strategy),
+            codec: self.codec.or(defaults.codec),
+            modified: self.modified.or(defaults.modified),
+            time_rounding: self.time_rounding.or(defaults.time_rounding),
+            time_overflow: self.time_overflow.or(defaults.time_overflow),
+            unicode: self.unicode.or(defaults.unicode),
+            #[cfg(feature = "unicode-normalization")]
+            normalization: self.normalization.or(defaults.normalization),
+            alignment: self.alignment.or(defaults.alignment),
+            fixed_offset: self.fixed_offset,
+            compression_hint: self.compression_hint.or(defaults.compression_hint),
+            two_pass: self.two_pass.or(defaults.two_pass),
+            entropy_probe: self.entropy_probe.or(defaults.entropy_probe),
+            data_descriptor: self.data_descriptor.or(defaults.data_descriptor),
+            dos_attributes: self.dos_attributes.or(defaults.dos_attributes),
+            metadata: self.metadata.or(defaults.metadata),
+            #[cfg(feature = "aes")]
+            encrypt: self.encrypt.or(defaults.encrypt),
+        }
+    }
+
+    fn compression_level(&self) -> Level {
+        self.level.unwrap_or(Level::Default)
+    }
+
+    fn date_time(&self) -> DateTime {
+        let rounding = self.time_rounding.unwrap_or(TimeRounding::Truncate);
+        match &self.modified {
+            Some(time) => DateTime::from_system_time_rounded(time, rounding),
+            None => DateTime::from_system_time_rounded(&SystemTime::now(), rounding),
+        }
+    }
+
+    pub(crate) fn timestamp(&self) -> u32 {
+        self.date_time().dos_time()
+    }
+
+    /// Whether modification time is after DOS time and it is to be rejected.
+    pub(crate) fn time_overflows(&self) -> bool {
+        self.time_overflow == Some(TimeOverflow::Reject)
+            && self.date_time().year() > LAST_YEAR_OF_DOS_TIME
+    }
+
+    /// NTFS extra field (0x000A) of modification time after DOS time.
+    ///
+    /// Access and creation times are the same as modification time.
+    pub(crate) fn time_extra(&self) -> Vec<u8> {
+        let modified = match self.modified {
+            Some(time) if self.date_time().year() > LAST_YEAR_OF_DOS_TIME => time,
+            _ => return Vec::new(),
+        };
+        // FILETIME is 100 nanoseconds from 1601.
+        let since_epoch = modified.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let filetime = (since_epoch.as_secs() + 11644473600) * 10_000_000
+            + since_epoch.subsec_nanos() as u64 / 100;
+        let mut extra = Vec::with_capacity(36);
+        extra.extend_from_slice(&0x000Au16.to_le_bytes());
+        extra.extend_from_slice(&32u16.to_le_bytes());
+        extra.extend_from_slice(&0u32.to_le_bytes());
+        extra.extend_from_slice(&1u16.to_le_bytes());
+        extra.extend_from_slice(&24u16.to_le_bytes());
+        for _ in 0..3 {
+            extra.extend_from_slice(&filetime.to_le_bytes());
+        }
+        extra
+    }
+
+    pub(crate) fn unicode_policy(&self) -> Option<Unicode> {
+        self.unicode
+    }
+
+    /// Name of the entry in the normalization form.
+    pub(crate) fn entry_name<'n>(&self, name: &'n str) -> Cow<'n, str> {
+        #[cfg(feature = "unicode-normalization")]
+        {
+            use unicode_normalization::UnicodeNormalization;
+            match self.normalization {
+                Some(Normalization::Nfc) => return Cow::Owned(name.nfc().collect()),
+                Some(Normalization::Nfd) => return Cow::Owned(name.nfd().collect()),
+                None => (),
+            }
+        }
+        Cow::Borrowed(name)
+    }
+
+    pub(crate) fn flags(&self, name: &str) -> u16 {
+        let unicode = match self.unicode.unwrap_or(Unicode::Always) {
+            Unicode::Always => 2048,
+            Unicode::Auto if !name.is_ascii() => 2048,
+            _ => 0,
+        };
+        let hint = match self.compression_level() {
+            _ if self.compression_hint != Some(true) || self.method() != 8 => 0,
+            Level::High => 2,
+            Level::Low => 4,
+            _ => 0,
+        };
+        let descriptor = if self.data_descriptor == Some(true) {
+            8
+        } else {
+            0
+        };
+        unicode | hint | descriptor
+    }
+
+    #[cfg(feature = "aes")]
+    pub(crate) fn encrypted(&self) -> bool {
+        self.encrypt == Some(true)
+    }
+
+    /// Whether the entry may share the data of an earlier entry of the same content
+    /// by [`deduplicate`](crate::ZipArchive::deduplicate).
+    pub(crate) fn shareable(&self) -> bool {
+        if self.placement().is_some() {
+            return false;
+        }
+        #[cfg(feature = "aes")]
+        if self.encrypted() {
+            return false;
+        }
+        true
+    }
+
+    pub(crate) fn level_of(&self) -> Option<Level> {
+        self.level
+    }
+
+    pub(crate) fn placement(&self) -> Option<(u64, Padding)> {
+        self.fixed_offset
+    }
+
+    pub(crate) fn metadata_policy(&self) -> MetadataPolicy {
+        self.metadata.unwrap_or(MetadataPolicy::None)
+    }
+
+    pub(crate) fn external_attributes(&self) -> u32 {
+        self.dos_attributes.unwrap_or_default().bits() as u32
+    }
+
+    /// Extra field to align data of the entry of `name` which starts at `offset`
+    /// without extra field.
+    pub(crate) fn alignment_extra(&self, name: &str, offset: u64) -> Result<Vec<u8>> {
+        let alignment = match self.alignment {
+            Some(alignment) if alignment > 1 && self.method() == 0 => alignment as u64,
+            _ => return Ok(Vec::new()),
+        };
+        let padding = ((alignment - (offset + 6) % alignment) % alignment) as usize;
+        if u16::try_from(6 + padding).is_err() {
+            return Err(Error::ExtraFieldTooLong {
+                name: name.to_string(),
+                length: 6 + padding,
+            });
+        }
+        let mut extra = Vec::with_capacity(6 + padding);
+        extra.extend_from_slice(&0xD935u16.to_le_bytes());
+        extra.extend_from_slice(&(2 + padding as u16).to_le_bytes());
+        let recorded = u16::try_from(alignment).unwrap_or(0);
+        extra.extend_from_slice(&recorded.to_le_bytes());
+        extra.resize(6 + padding, 0);
+        Ok(extra)
+    }
+
+    /// Compress with Brotli and record `method` as compression method. (Experimental)
+    ///
+    /// Brotli is not a standard method of Zip. Choose a private method id
+    /// understood by your own reader. Level selects quality of Brotli
+    /// and [`Level::Raw`] still stores raw data.
+    ///
+    /// Panics if `method` is an id assigned by APPNOTE (0 to 20 and 93 to 99),
+    /// since readers would decode the data as that method.
+    #[cfg(feature = "brotli")]
+    pub fn brotli(mut self, method: u16) -> Self {
+        assert!(
+            !matches!(method, 0..=20 | 93..=99),
+            "method {} is a standard compression method",
+            method
+        );
+        self.codec = Some(Codec::Brotli(method));
+        self
+    }
+
+    /// Compress with PPMd (method 98).
+    ///
+    /// Level selects model order and memory size of PPMd
+    /// and [`Level::Raw`] still stores raw data.
+    #[cfg(feature = "ppmd")]
+    pub fn ppmd(mut self) -> Self {
+        self.codec = Some(Codec::Ppmd);
+        self
+    }
+
+    pub(crate) fn method(&self) -> u16 {
+        let level = self.compression_level();
+        if level == Level::Raw {
+            return level.method();
+        }
+        match self.codec.unwrap_or(Codec::Deflate) {
+            Codec::Deflate => level.method(),
+            #[cfg(feature = "brotli")]
+            Codec::Brotli(method) => method,
+            #[cfg(feature = "ppmd")]
+            Codec::Ppmd => 98,
+        }
+    }
+
+    /// Compressed content, or `None` if content should be stored as it is.
+    pub(crate) fn compress(
+        &self,
+        content: &[u8],
+        slice: Option<&TimeSlice>,
+    ) -> Result<Option<Vec<u8>>> {
+        if self.codec.unwrap_or(Codec::Deflate) == Codec::Deflate && slice.is_none() {
+            let compression = match self.compression_level().compression() {
+                Some(compression) => self.deflate_options(compression),
+                None => return Ok(None),
+            };
+            return Ok(Some(deflate_bytes_conf(content, compression)));
+        }
+        self.compress_to(Vec::new(), content, slice)
+    }
+
+    /// Options storing incompressible `content` if the entropy probe is set.
+    pub(crate) fn probe(self, content: &[u8]) -> EntryOptions {
+        if self.entropy_probe == Some(true)
+            && crate::estimate_entropy(content) >= crate::entropy::INCOMPRESSIBLE
+        {
+            return self.level(Level::Raw);
+        }
+        self
+    }
+
+    /// Options with the method which compresses a sample of `content` best, in two-pass mode.
+    pub(crate) fn choose_method(self, content: &[u8]) -> EntryOptions {
+        if self.two_pass != Some(true) || self.compression_level() == Level::Raw {
+            return self;
+        }
+        let sample = &content[..content.len().min(TWO_PASS_SAMPLE)];
+        let candidates = [
+            Some(EntryOptions {
+                codec: Some(Codec::Deflate),
+                ..self
+            }),
+            #[cfg(feature = "ppmd")]
+            Some(self.ppmd()),
+            #[cfg(not(feature = "ppmd"))]
+            None,
+        ];
+        let mut best = (sample.len(), self.level(Level::Raw));
+        for candidate in candidates.iter().flatten() {
+            if let Ok(Some(compressed)) = candidate.compress(sample, None) {
+                if compressed.len() < best.0 {
+                    best = (compressed.len(), *candidate);
+                }
+            }
+        }
+        best.1
+    }
+
+    fn deflate_options(&self, compression: Compression) -> CompressionOptions {
+        match self.strategy.unwrap_or(Strategy::Default) {
+            Strategy::Default => compression.into(),
+            Strategy::HuffmanOnly => CompressionOptions::huffman_only(),
+            Strategy::Rle => CompressionOptions::rle(),
+        }
+    }
+
+    /// Deflate encoder of the level writing to `output`.
+    pub(crate) fn deflate_encoder<W: Write>(&self, output: W) -> DeflateEncoder<W> {
+        let compression = self
+            .compression_level()
+            .compression()
+            .unwrap_or(Compression::Default);
+        DeflateEncoder::new(output, self.deflate_options(compression))
+    }
+
+    /// Options deflating content whatever the codec is.
+    pub(crate) fn deflated(mut self) -> Self {
+        self.codec = Some(Codec::Deflate);
+        self
+    }
+
+    /// Write compressed content to `output` and return it,
+    /// or `None` if content should be stored as it is.
+    pub(crate) fn compress_to<W: Write>(
+        &self,
+        output: W,
+        content: &[u8],
+        slice: Option<&TimeSlice>,
+    ) -> Result<Option<W>> {
+        let write_all = |writer: &mut dyn Write| match slice {
+            Some(slice) => slice.write_all(writer, content),
+            None => writer.write_all(content),
+        };
+        let compression = match self.compression_level().compression() {
+            Some(compression) => compression,
+            None => return Ok(None),
+        };
+        match self.codec.unwrap_or(Codec::Deflate) {
+            Codec::Deflate => {
+                let mut encoder = DeflateEncoder::new(output, self.deflate_options(compression));
+                write_all(&mut encoder)?;
+                Ok(Some(encoder.finish()?))
+            }
+            #[cfg(feature = "brotli")]
+            Codec::Brotli(_) => {
+                let quality = match compression {
+                    Compression::Fast => 1,
+                    Compression::Default => 6,
+                    Compression::Best => 11,
+                };
+                // `into_inner` drops the error of the final flush, so finish
+                // into memory where flushing cannot fail.
+                let mut encoder = brotli::CompressorWriter::new(Vec::new(), 4096, quality, 22);
+                write_all(&mut encoder)?;
+                let compressed = encoder.into_inner();
+                let mut output = output;
+                output.write_all(&compressed)?;
+                Ok(Some(output))
+            }
+            #[cfg(feature = "ppmd")]
+            Codec::Ppmd => {
+                use ppmd_rust::{Ppmd8Encoder, RestoreMethod};
+                let (order, memory, restore) = match compression {
+                    Compression::Fast => (4u16, 1u16, RestoreMethod::Restart),
+                    Compression::Default => (6, 16, RestoreMethod::Restart),
+                    Compression::Best => (16, 64, RestoreMethod::CutOff),
+                };
+                let parameter = (order - 1) | (memory - 1) << 4 | (restore as u16) << 12;
+                let mut output = output;
+                output.write_all(&parameter.to_le_bytes())?;
+                let mut encoder =
+                    Ppmd8Encoder::new(output, order as u32, (memory as u32) << 20, restore)
+                        .map_err(|_| std::io::Error::from(std::io::ErrorKind::OutOfMemory))?;
+                write_all(&mut encoder)?;
+                Ok(Some(encoder.finish(false)?))
+            }
+        }
+    }
+}
+
+impl From<Level> for EntryOptions {
+    fn from(level: Level) -> Self {
+        EntryOptions::new(level)
+    }
+}
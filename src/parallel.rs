@@ -0,0 +1,213 @@
+use crate::signing::Signed;
+use crate::{advance, Config, EntryOptions, Result, ZipArchive, ZipEntry, ZipState};
+use std::io::Write;
+
+/// Entries of a shard with their options.
+type Shard<'s, 'e> = &'s [(&'e str, &'e [u8], EntryOptions)];
+
+impl<'a, T: Write + ?Sized + 'a> ZipArchive<'a, T> {
+    /// Add entries compressed by up to `threads` worker threads, in the order of `entries`.
+    ///
+    /// Entries are divided into consecutive shards of about the same total size.
+    /// Each worker builds its shard in memory with the options of this archive,
+    /// then the shards are copied to the output and the offsets of their entries are adjusted.
+    /// The output is the same as adding the entries one by one, except that
+    /// [`deduplicate`](ZipArchive::deduplicate) works only within a shard and
+    /// stored entries are not aligned, since offsets are unknown while the shards are built.
+    /// All compressed shards are kept in memory until the workers finish.
+    ///
+    /// ```
+    /// # use zip_builder::{Level, ZipArchive};
+    /// let files: Vec<(String, Vec<u8>)> = (0..100)
+    ///     .map(|index| (format!("{}.txt", index), vec![b'a'; 1000]))
+    ///     .collect();
+    /// let mut zip = Vec::new();
+    /// ZipArchive::new(&mut zip)
+    ///     .add_entries_parallel(
+    ///         files
+    ///             .iter()
+    ///             .map(|(name, content)| (name.as_str(), content.as_slice(), Level::High)),
+    ///         4,
+    ///     )?
+    ///     .finish()?;
+    /// # Ok::<(), zip_builder::Error>(())
+    /// ```
+    pub fn add_entries_parallel<'e, I, O>(mut self, entries: I, threads: usize) -> Result<Self>
+    where
+        I: IntoIterator<Item = (&'e str, &'e [u8], O)>,
+        O: Into<EntryOptions>,
+    {
+        let result = self.push_entries_parallel(entries, threads);
+        self.or_poison(result)
+    }
+
+    fn push_entries_parallel<'e, I, O>(&mut self, entries: I, threads: usize) -> Result<()>
+    where
+        I: IntoIterator<Item = (&'e str, &'e [u8], O)>,
+        O: Into<EntryOptions>,
+    {
+        self.check_poisoned()?;
+        let entries: Vec<_> = entries
+            .into_iter()
+            .filter(|(name, _, _)| !self.overdue(name))
+            .map(|(name, content, options)| (name, content, options.into()))
+            .collect();
+        let mut config = self.config.clone();
+        config.place_first.clear();
+        config.base_offset = None;
+        config.deadline = None;
+        #[cfg(feature = "json-manifest")]
+        {
+            config.json_manifest = false;
+        }
+        let shards = std::thread::scope(|scope| {
+            let workers: Vec<_> = partition(&entries, threads.max(1))
+                .into_iter()
+                .map(|shard| {
+                    let config = config.clone();
+                    scope.spawn(move || build_shard(config, shard))
+                })
+                .collect();
+            workers
+                .into_iter()
+                .map(|worker| {
+                    worker
+                        .join()
+                        .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+                })
+                .collect::<Result<Vec<_>>>()
+        })?;
+        self.state = ZipState::Processing;
+        for (data, entries) in shards {
+            let base = self.offset;
+            let mut end = base;
+            advance(&mut end, data.len() as u64)?;
+            let mut names = Vec::with_capacity(entries.len());
+            for mut entry in entries {
+                let prefix = self.config.root_prefix.len();
+                let name = entry.filename[prefix..].to_string();
+                self.check_limits(&name, entry.uncompressed_size as usize, None)?;
+                self.record_case(&name);
+                self.total_size += entry.uncompressed_size;
+                entry.offset += base;
+                entry.data_offset += base;
+                self.entries.push(entry);
+                names.push(name);
+            }
+            Signed::new(self.output, &mut self.batch, &mut self.signer).write_all(&data)?;
+            self.offset = end;
+            for name in names {
+                self.place(&name)?;
+            }
+            if self.config.auto_flush {
+                self.output.flush()?;
+            }
+        }
+        self.state = ZipState::Breathe;
+        Ok(())
+    }
+}
+
+/// Divide entries into at most `threads` consecutive shards of about the same total size.
+fn partition<'s, 'e>(
+    entries: &'s [(&'e str, &'e [u8], EntryOptions)],
+    threads: usize,
+) -> Vec<Shard<'s, 'e>> {
+    let total: usize = entries.iter().map(|(_, content, _)| content.len()).sum();
+    let share = total / threads + 1;
+    let mut shards = Vec::with_capacity(threads);
+    let (mut start, mut size) = (0, 0);
+    for (index, (_, content, _)) in entries.iter().enumerate() {
+        size += content.len();
+        if size >= share {
+            shards.push(&entries[start..=index]);
+            start = index + 1;
+            size = 0;
+        }
+    }
+    if start < entries.len() {
+        shards.push(&entries[start..]);
+    }
+    shards
+}
+
+/// Build a shard in memory and return its data and entries at offsets from the start.
+fn build_shard(config: Config, entries: Shard<'_, '_>) -> Result<(Vec<u8>, Vec<ZipEntry>)> {
+    let mut data = Vec::new();
+    let mut zip = ZipArchive::with_config(&mut data, config);
+    for &(name, content, options) in entries {
+        zip = zip.add_entry(name, content, options.alignment(1))?;
+    }
+    zip.state = ZipState::Finished;
+    let entries = std::mem::take(&mut zip.entries);
+    drop(zip);
+    Ok((data, entries))
+}
+
+#[cfg(test)]
+mod test {
+    use super::partition;
+    use crate::{verify, EntryOptions, Level, ZipArchiveBuilder};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn it_works() {
+        let files: Vec<(String, Vec<u8>)> = (0..20)
+            .map(|index| {
+                (
+                    format!("{}.txt", index),
+                    format!("{}", index).repeat(index * 100).into_bytes(),
+                )
+            })
+            .collect();
+        let entries = || {
+            files
+                .iter()
+                .map(|(name, content)| (name.as_str(), content.as_slice(), Level::High))
+        };
+        let builder = ZipArchiveBuilder::new()
+            .root_prefix("root")
+            .modified(UNIX_EPOCH + Duration::from_secs(1608905122));
+        let mut sequential = Vec::new();
+        builder
+            .build(&mut sequential)
+            .add_entry("first.txt", b"first", Level::Raw)
+            .unwrap()
+            .add_entries(entries())
+            .unwrap()
+            .finish()
+            .unwrap();
+        for threads in [1, 3, 8, 100] {
+            let mut parallel = Vec::new();
+            let summary = builder
+                .build(&mut parallel)
+                .add_entry("first.txt", b"first", Level::Raw)
+                .unwrap()
+                .add_entries_parallel(entries(), threads)
+                .unwrap()
+                .finish()
+                .unwrap();
+            assert_eq!(summary.entries.len(), 21);
+            assert_eq!(parallel, sequential);
+        }
+        assert_eq!(verify(sequential.as_slice()).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn shards() {
+        let content = [0u8; 10];
+        let entries: Vec<_> = (0..10)
+            .map(|_| ("a", &content[..], EntryOptions::default()))
+            .collect();
+        let sizes = |threads| -> Vec<usize> {
+            partition(&entries, threads)
+                .iter()
+                .map(|shard| shard.len())
+                .collect()
+        };
+        assert_eq!(sizes(1), [10]);
+        assert_eq!(sizes(3), [4, 4, 2]);
+        assert_eq!(sizes(20), [1; 10]);
+        assert!(partition(&[], 4).is_empty());
+    }
+}
@@ -0,0 +1,377 @@
+use crate::{DirectoryOrder, EntryInfo, EntryOptions, Level};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// Extensions of formats which are already compressed.
+const COMPRESSED_EXTENSIONS: [&str; 27] = [
+    "7z", "aac", "apk", "avi", "bz2", "docx", "epub", "flac", "gif", "gz", "jar", "jpeg", "jpg",
+    "m4a", "mkv", "mov", "mp3", "mp4", "ogg", "png", "pptx", "rar", "webp", "woff2", "xlsx", "xz",
+    "zip",
+];
+
+/// Policy to select compression level by extension of entry name.
+///
+/// [`Default`] policy stores known compressed formats (`.png`, `.jpg`, `.mp4`, `.zip` and so on)
+/// as raw data and compresses the rest with [`Level::Default`].
+#[derive(Clone, Debug)]
+pub struct ExtensionPolicy {
+    levels: HashMap<String, Level>,
+    fallback: Level,
+}
+
+impl ExtensionPolicy {
+    /// Create a policy without any rule.
+    ///
+    /// `fallback` is used for names of no matching extension.
+    pub fn new(fallback: Level) -> ExtensionPolicy {
+        ExtensionPolicy {
+            levels: HashMap::new(),
+            fallback,
+        }
+    }
+
+    /// Use `level` for names of `extension`.
+    ///
+    /// Extension is compared case-insensitively and without leading dot.
+    pub fn set(mut self, extension: &str, level: Level) -> Self {
+        self.levels.insert(
+            extension.trim_start_matches('.').to_ascii_lowercase(),
+            level,
+        );
+        self
+    }
+
+    /// Compression level for the entry name.
+    pub fn level(&self, name: &str) -> Level {
+        let basename = name.rsplit('/').next().unwrap_or(name);
+        match basename.rsplit_once('.') {
+            Some((stem, extension)) if !stem.is_empty() => self
+                .levels
+                .get(&extension.to_ascii_lowercase())
+                .copied()
+                .unwrap_or(self.fallback),
+            _ => self.fallback,
+        }
+    }
+}
+
+impl Default for ExtensionPolicy {
+    fn default() -> Self {
+        COMPRESSED_EXTENSIONS
+            .iter()
+            .fold(ExtensionPolicy::new(Level::Default), |policy, extension| {
+                policy.set(extension, Level::Raw)
+            })
+    }
+}
+
+/// Ordered rules of glob patterns of entry names to compression levels,
+/// attached to an archive by [`ZipArchiveBuilder::compression_policy`](crate::ZipArchiveBuilder::compression_policy).
+///
+/// The first rule matching the name decides the level. In patterns, `*` matches any
+/// characters but `/`, `**` matches any characters and `**/` also matches nothing,
+/// and `?` matches a character but `/`. Patterns without `/` are matched with the last
+/// component of names.
+///
+/// ```rust
+/// use zip_builder::{CompressionPolicy, Level};
+///
+/// let policy = CompressionPolicy::new()
+///     .rule("assets/**", Level::High)
+///     .rule("*.png", Level::Raw);
+/// assert_eq!(policy.level("assets/icons/app.png"), Some(Level::High));
+/// assert_eq!(policy.level("res/logo.png"), Some(Level::Raw));
+/// assert_eq!(policy.level("readme.md"), None);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct CompressionPolicy {
+    rules: Vec<(String, Level)>,
+}
+
+impl CompressionPolicy {
+    /// Create a policy without any rule.
+    pub fn new() -> CompressionPolicy {
+        CompressionPolicy::default()
+    }
+
+    /// Preset of Android packages: `resources.arsc`, native libraries `lib/**/*.so`
+    /// and known compressed formats of [`ExtensionPolicy::default`] are stored.
+    pub fn apk() -> CompressionPolicy {
+        COMPRESSED_EXTENSIONS.iter().fold(
+            CompressionPolicy::new()
+                .rule("/resources.arsc", Level::Raw)
+                .rule("lib/**/*.so", Level::Raw),
+            |policy, extension| policy.rule(&format!("*.{}", extension), Level::Raw),
+        )
+    }
+
+    /// Add a rule using `level` for names matching `pattern`, after the existing rules.
+    ///
+    /// A leading `/` anchors a pattern without other `/` at the top of the archive.
+    pub fn rule(mut self, pattern: &str, level: Level) -> Self {
+        self.rules.push((pattern.to_string(), level));
+        self
+    }
+
+    /// Compression level for the entry name, or `None` if no rule matches.
+    pub fn level(&self, name: &str) -> Option<Level> {
+        let basename = name.rsplit('/').next().unwrap_or(name);
+        self.rules.iter().find_map(|(pattern, level)| {
+            let (pattern, target) = match pattern.strip_prefix('/') {
+                Some(pattern) => (pattern, name),
+                None if pattern.contains('/') => (pattern.as_str(), name),
+                None => (pattern.as_str(), basename),
+            };
+            let pattern: Vec<char> = pattern.chars().collect();
+            let target: Vec<char> = target.chars().collect();
+            glob(&pattern, &target).then_some(*level)
+        })
+    }
+}
+
+/// Whether `name` matches the glob `pattern` of [`CompressionPolicy`].
+fn glob(pattern: &[char], name: &[char]) -> bool {
+    match pattern {
+        [] => name.is_empty(),
+        ['*', '*', '/', rest @ ..] => (0..=name.len())
+            .filter(|&index| index == 0 || name[index - 1] == '/')
+            .any(|index| glob(rest, &name[index..])),
+        ['*', '*', rest @ ..] => (0..=name.len()).any(|index| glob(rest, &name[index..])),
+        ['*', rest @ ..] => (0..=name.len())
+            .take_while(|&index| index == 0 || name[index - 1] != '/')
+            .any(|index| glob(rest, &name[index..])),
+        ['?', rest @ ..] => matches!(name, [c, tail @ ..] if *c != '/' && glob(rest, tail)),
+        [c, rest @ ..] => matches!(name, [d, tail @ ..] if d == c && glob(rest, tail)),
+    }
+}
+
+/// Preset of entry options for Android packages (APK and AAB).
+///
+/// Stored entries are aligned to 4 bytes like zipalign, native libraries `lib/**/*.so`
+/// are stored and aligned to the page so that they can be mapped without extraction,
+/// and `resources.arsc` is stored. Other entries follow [`ExtensionPolicy::default`].
+/// Sign the result with apksigner after building.
+///
+/// ```rust
+/// use zip_builder::{ApkPolicy, ZipArchiveBuilder};
+///
+/// let apk = ApkPolicy::new();
+/// let mut buf = Vec::new();
+/// let mut zip = ZipArchiveBuilder::new().order(apk.order()).build(&mut buf);
+/// for (name, content) in [
+///     ("AndroidManifest.xml", &b"manifest"[..]),
+///     ("lib/arm64-v8a/libmain.so", b"library"),
+///     ("resources.arsc", b"resources"),
+/// ] {
+///     zip = zip.add_entry(name, content, apk.options(name))?;
+/// }
+/// zip.finish()?;
+/// # Ok::<(), zip_builder::Error>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct ApkPolicy {
+    levels: ExtensionPolicy,
+    page_size: u32,
+}
+
+impl ApkPolicy {
+    /// Create the preset with page size of 4096 bytes.
+    pub fn new() -> ApkPolicy {
+        ApkPolicy {
+            levels: ExtensionPolicy::default(),
+            page_size: 4096,
+        }
+    }
+
+    /// Set page size to align native libraries, such as 16384 for devices of 16 KB pages.
+    ///
+    /// Panics if `page_size` is not a power of two up to 65536.
+    pub fn page_size(mut self, page_size: u32) -> Self {
+        assert!(
+            page_size.is_power_of_two() && page_size <= 0x10000,
+            "page size must be a power of two up to 65536"
+        );
+        self.page_size = page_size;
+        self
+    }
+
+    /// Options for the entry name.
+    pub fn options(&self, name: &str) -> EntryOptions {
+        let library = name.starts_with("lib/") && name.ends_with(".so");
+        if library {
+            EntryOptions::new(Level::Raw).alignment(self.page_size)
+        } else if name == "resources.arsc" {
+            EntryOptions::new(Level::Raw).alignment(4)
+        } else {
+            EntryOptions::new(self.levels.level(name)).alignment(4)
+        }
+    }
+
+    /// Order of the central directory: `AndroidManifest.xml`, dex files, `resources.arsc`,
+    /// other entries and `META-INF/` at last. Names are sorted in each group.
+    pub fn order(&self) -> DirectoryOrder {
+        DirectoryOrder::Custom(Self::compare)
+    }
+
+    fn compare(a: &EntryInfo, b: &EntryInfo) -> Ordering {
+        fn rank(name: &str) -> u8 {
+            match name {
+                "AndroidManifest.xml" => 0,
+                "resources.arsc" => 2,
+                _ if name.starts_with("classes") && name.ends_with(".dex") => 1,
+                _ if name.starts_with("META-INF/") => 4,
+                _ => 3,
+            }
+        }
+        (rank(&a.name), &a.name).cmp(&(rank(&b.name), &b.name))
+    }
+}
+
+impl Default for ApkPolicy {
+    fn default() -> Self {
+        ApkPolicy::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ApkPolicy, CompressionPolicy, ExtensionPolicy};
+    use crate::{verify, EntryOptions, Level, ZipArchive, ZipArchiveBuilder};
+
+    #[test]
+    fn it_works() {
+        let policy = ExtensionPolicy::default().set(".LOG", Level::High);
+        assert_eq!(policy.level("images/photo.JPG"), Level::Raw);
+        assert_eq!(policy.level("archive.tar.gz"), Level::Raw);
+        assert_eq!(policy.level("server.log"), Level::High);
+        assert_eq!(policy.level("readme.md"), Level::Default);
+        assert_eq!(policy.level("png/.png"), Level::Default);
+        assert_eq!(policy.level("Makefile"), Level::Default);
+    }
+
+    #[test]
+    fn compression_policy() {
+        let policy = CompressionPolicy::new()
+            .rule("/resources.arsc", Level::Raw)
+            .rule("lib/**/*.so", Level::Raw)
+            .rule("docs/**", Level::High)
+            .rule("*.t?t", Level::Low)
+            .rule("*.txt", Level::Raw);
+        assert_eq!(policy.level("resources.arsc"), Some(Level::Raw));
+        assert_eq!(policy.level("res/resources.arsc"), None);
+        assert_eq!(policy.level("lib/libmain.so"), Some(Level::Raw));
+        assert_eq!(policy.level("lib/arm64-v8a/libmain.so"), Some(Level::Raw));
+        assert_eq!(policy.level("assets/lib/libmain.so"), None);
+        assert_eq!(policy.level("docs/a/b.md"), Some(Level::High));
+        assert_eq!(policy.level("docs/readme.txt"), Some(Level::High));
+        assert_eq!(policy.level("src/読む.txt"), Some(Level::Low));
+        assert_eq!(policy.level("a.tx/t"), None);
+
+        let apk = CompressionPolicy::apk();
+        assert_eq!(apk.level("resources.arsc"), Some(Level::Raw));
+        assert_eq!(apk.level("res/icon.png"), Some(Level::Raw));
+        assert_eq!(apk.level("classes.dex"), None);
+
+        let mut buf = Vec::new();
+        let summary = ZipArchiveBuilder::new()
+            .compression_policy(policy)
+            .build(&mut buf)
+            .add_entry("docs/a.txt", &[b'a'; 1000], EntryOptions::default())
+            .unwrap()
+            .add_entry("resources.arsc", &[b'a'; 1000], EntryOptions::default())
+            .unwrap()
+            .add_entry("explicit.txt", &[b'a'; 1000], Level::Default)
+            .unwrap()
+            .finish()
+            .unwrap();
+        let stored: Vec<_> = summary
+            .entries
+            .iter()
+            .map(|entry| entry.compressed_size == 1000)
+            .collect();
+        assert_eq!(stored, [false, true, false]);
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn apk() {
+        let apk = ApkPolicy::new().page_size(16384);
+        let library = "lib/arm64-v8a/libmain.so";
+        assert_eq!(
+            apk.options(library),
+            EntryOptions::new(Level::Raw).alignment(16384)
+        );
+        assert_eq!(
+            apk.options("resources.arsc"),
+            EntryOptions::new(Level::Raw).alignment(4)
+        );
+        assert_eq!(
+            apk.options("res/icon.png"),
+            EntryOptions::new(Level::Raw).alignment(4)
+        );
+        assert_eq!(
+            apk.options("classes.dex"),
+            EntryOptions::new(Level::Default).alignment(4)
+        );
+
+        let mut buf = Vec::new();
+        let mut zip = ZipArchiveBuilder::new().order(apk.order()).build(&mut buf);
+        for name in [
+            "META-INF/CERT.SF",
+            library,
+            "resources.arsc",
+            "classes2.dex",
+            "classes.dex",
+            "AndroidManifest.xml",
+        ] {
+            let content = format!("content of {}", name);
+            zip = zip
+                .add_entry(name, content.as_bytes(), apk.options(name))
+                .unwrap();
+        }
+        let summary = zip.finish().unwrap();
+        let names: Vec<_> = summary
+            .entries
+            .iter()
+            .map(|entry| &entry.name[..])
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "AndroidManifest.xml",
+                "classes.dex",
+                "classes2.dex",
+                "resources.arsc",
+                library,
+                "META-INF/CERT.SF"
+            ]
+        );
+        let position = |name: &str| {
+            let content = format!("content of {}", name);
+            buf.windows(content.len())
+                .position(|window| window == content.as_bytes())
+        };
+        assert_eq!(position(library).map(|p| p % 16384), Some(0));
+        assert_eq!(position("resources.arsc").map(|p| p % 4), Some(0));
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+
+        let apk = ApkPolicy::new().page_size(65536);
+        let mut buf = Vec::new();
+        ZipArchive::new(&mut buf)
+            .add_entry("classes.dex", b"dex", apk.options("classes.dex"))
+            .unwrap()
+            .add_entry(library, b"64 KB page", apk.options(library))
+            .unwrap()
+            .finish()
+            .unwrap();
+        let position = buf.windows(10).position(|window| window == b"64 KB page");
+        assert_eq!(position, Some(65536));
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+    }
+
+    #[test]
+    #[should_panic(expected = "power of two")]
+    fn apk_page_size() {
+        let _ = ApkPolicy::new().page_size(12288);
+    }
+}
@@ -0,0 +1,82 @@
+use std::fs::File;
+use std::io::{Result, Write};
+
+/// Writer which writes to a shared [`File`] by positional writes.
+///
+/// It does not move the file cursor, so writers over one [`File`]
+/// (or clones of its handle) never contend for seeking.
+/// Unix uses `pwrite` and Windows uses `seek_write`.
+///
+/// ```rust
+/// use zip_builder::{Level, PositionedWriter, ZipArchive};
+///
+/// let file = tempfile();
+/// let mut output = PositionedWriter::new(&file, 0);
+/// ZipArchive::new(&mut output)
+///     .add_entry("file.txt", b"content", Level::Default)?
+///     .finish()?;
+/// assert_eq!(file.metadata()?.len(), output.position());
+/// # fn tempfile() -> std::fs::File {
+/// #     let path = std::env::temp_dir().join("zip-builder-positioned-doc");
+/// #     std::fs::File::create(path).unwrap()
+/// # }
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct PositionedWriter<'a> {
+    file: &'a File,
+    position: u64,
+}
+
+impl<'a> PositionedWriter<'a> {
+    /// Create a writer to `file` from `position`.
+    pub fn new(file: &'a File, position: u64) -> PositionedWriter<'a> {
+        PositionedWriter { file, position }
+    }
+
+    /// Position where the next write starts.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+}
+
+impl<'a> Write for PositionedWriter<'a> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        #[cfg(unix)]
+        let written = std::os::unix::fs::FileExt::write_at(self.file, buf, self.position)?;
+        #[cfg(windows)]
+        let written = std::os::windows::fs::FileExt::seek_write(self.file, buf, self.position)?;
+        self.position += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::PositionedWriter;
+    use crate::{verify, Level, ZipArchive};
+    use std::fs::File;
+    use std::io::Write;
+
+    #[test]
+    fn it_works() {
+        let path = std::env::temp_dir().join("zip-builder-positioned");
+        let file = File::create(&path).unwrap();
+        let mut header = PositionedWriter::new(&file, 0);
+        let mut output = PositionedWriter::new(&file, 4);
+        ZipArchive::new(&mut output)
+            .add_entry("file.txt", b"content", Level::Default)
+            .unwrap()
+            .finish()
+            .unwrap();
+        header.write_all(b"LEAD").unwrap();
+        let buf = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(&buf[..4], b"LEAD");
+        assert_eq!(buf.len() as u64, output.position());
+        assert_eq!(verify(&buf[4..]).unwrap(), vec![]);
+    }
+}
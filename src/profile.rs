@@ -0,0 +1,242 @@
+use crate::{EntryOptions, Error, Result, ZipArchive, ZipEntry};
+use std::fmt::{Display, Formatter};
+use std::io::Write;
+
+/// Package formats based on Zip with constraints on entries.
+///
+/// Set it by [`ZipArchiveBuilder::profile`](crate::ZipArchiveBuilder::profile)
+/// to get [`Error::NonConforming`] instead of a package which readers reject.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Profile {
+    /// EPUB: `mimetype` is stored as the first entry, and `META-INF/container.xml` exists.
+    Epub,
+    /// Java archive: `META-INF/MANIFEST.MF` is listed first if exists.
+    Jar,
+    /// Anki deck package: `collection.anki2` and `media` exist.
+    Apkg,
+    /// Word document: the parts of the package and the document exist.
+    Docx,
+}
+
+/// Rule of [`Profile`] broken by an entry.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Violation {
+    /// The entry must be stored without compression.
+    Compressed,
+    /// The entry must be written at the start of the archive.
+    NotAtStart,
+    /// The entry must be listed before others in the central directory.
+    NotListedFirst,
+    /// The entry is required but not added.
+    Missing,
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Compressed => write!(f, "must be stored"),
+            Self::NotAtStart => write!(f, "must be the first entry"),
+            Self::NotListedFirst => write!(f, "must be listed first"),
+            Self::Missing => write!(f, "is required"),
+        }
+    }
+}
+
+struct Rules {
+    /// Name of the entry which must be written at offset 0.
+    at_start: Option<&'static str>,
+    /// Names which must be listed first in the central directory in this order if exist.
+    listed_first: &'static [&'static str],
+    /// Names which must exist.
+    required: &'static [&'static str],
+    /// Names which must be stored.
+    stored: &'static [&'static str],
+}
+
+impl Profile {
+    /// Profile of the file extension like `epub` or `.docx`, compared case-insensitively.
+    pub fn from_extension(extension: &str) -> Option<Profile> {
+        match extension
+            .trim_start_matches('.')
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "epub" => Some(Profile::Epub),
+            "jar" => Some(Profile::Jar),
+            "apkg" => Some(Profile::Apkg),
+            "docx" => Some(Profile::Docx),
+            _ => None,
+        }
+    }
+
+    fn rules(self) -> Rules {
+        match self {
+            Profile::Epub => Rules {
+                at_start: Some("mimetype"),
+                listed_first: &["mimetype"],
+                required: &["mimetype", "META-INF/container.xml"],
+                stored: &["mimetype"],
+            },
+            Profile::Jar => Rules {
+                at_start: None,
+                listed_first: &["META-INF/", "META-INF/MANIFEST.MF"],
+                required: &[],
+                stored: &[],
+            },
+            Profile::Apkg => Rules {
+                at_start: None,
+                listed_first: &[],
+                required: &["collection.anki2", "media"],
+                stored: &[],
+            },
+            Profile::Docx => Rules {
+                at_start: None,
+                listed_first: &[],
+                required: &["[Content_Types].xml", "_rels/.rels", "word/document.xml"],
+                stored: &[],
+            },
+        }
+    }
+
+    fn violation(self, name: &str, violation: Violation) -> Error {
+        Error::NonConforming {
+            name: name.to_string(),
+            profile: self,
+            violation,
+        }
+    }
+
+    /// Check the entry to be written at `offset`.
+    fn check_entry(self, name: &str, options: &EntryOptions, offset: u64) -> Result<()> {
+        let rules = self.rules();
+        if rules.stored.contains(&name) && options.method() != 0 {
+            Err(self.violation(name, Violation::Compressed))
+        } else if rules.at_start == Some(name) && offset != 0 {
+            Err(self.violation(name, Violation::NotAtStart))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Check the entries in the order of the central directory.
+    pub(crate) fn check_entries(self, entries: &[ZipEntry]) -> Result<()> {
+        let rules = self.rules();
+        if let Some(missing) = rules
+            .required
+            .iter()
+            .find(|&&name| !entries.iter().any(|entry| entry.filename == name))
+        {
+            return Err(self.violation(missing, Violation::Missing));
+        }
+        let present: Vec<&str> = rules
+            .listed_first
+            .iter()
+            .copied()
+            .filter(|&name| entries.iter().any(|entry| entry.filename == name))
+            .collect();
+        for (entry, name) in entries.iter().zip(present) {
+            if entry.filename != name {
+                return Err(self.violation(name, Violation::NotListedFirst));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a, T: Write + ?Sized + 'a> ZipArchive<'a, T> {
+    /// Check the entry against the profile before it is written.
+    /// Check the entry of inherited `options` before adding it.
+    pub(crate) fn check_profile(&self, name: &str, options: &EntryOptions) -> Result<()> {
+        match self.config.profile {
+            Some(profile) => profile.check_entry(name, options, self.offset),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Profile, Violation};
+    use crate::{verify, Error, Level, ZipArchiveBuilder};
+
+    fn violation<T>(result: crate::Result<T>) -> Option<(String, Violation)> {
+        match result {
+            Err(Error::NonConforming {
+                name, violation, ..
+            }) => Some((name, violation)),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn it_works() {
+        assert_eq!(Profile::from_extension(".EPUB"), Some(Profile::Epub));
+        assert_eq!(Profile::from_extension("zip"), None);
+
+        let epub = ZipArchiveBuilder::new().profile(Profile::Epub);
+        let mut buf = Vec::new();
+        let result = epub
+            .build(&mut buf)
+            .add_entry("mimetype", b"application/epub+zip", Level::Raw)
+            .unwrap()
+            .add_entry("META-INF/container.xml", b"<container/>", Level::Default)
+            .unwrap()
+            .finish();
+        assert!(result.is_ok());
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+
+        let mut buf = Vec::new();
+        let result =
+            epub.build(&mut buf)
+                .add_entry("mimetype", b"application/epub+zip", Level::Default);
+        assert_eq!(
+            violation(result),
+            Some(("mimetype".to_string(), Violation::Compressed))
+        );
+
+        let mut buf = Vec::new();
+        let result = epub
+            .build(&mut buf)
+            .add_entry("META-INF/container.xml", b"<container/>", Level::Default)
+            .unwrap()
+            .add_entry("mimetype", b"application/epub+zip", Level::Raw);
+        assert_eq!(
+            violation(result),
+            Some(("mimetype".to_string(), Violation::NotAtStart))
+        );
+
+        let mut buf = Vec::new();
+        let result = epub
+            .build(&mut buf)
+            .add_entry("mimetype", b"application/epub+zip", Level::Raw)
+            .unwrap()
+            .finish();
+        assert_eq!(
+            violation(result),
+            Some(("META-INF/container.xml".to_string(), Violation::Missing))
+        );
+
+        let jar = ZipArchiveBuilder::new().profile(Profile::Jar);
+        let mut buf = Vec::new();
+        let result = jar
+            .build(&mut buf)
+            .add_entry("META-INF/", b"", Level::Raw)
+            .unwrap()
+            .add_entry("A.class", b"class", Level::Default)
+            .unwrap()
+            .add_entry(
+                "META-INF/MANIFEST.MF",
+                b"Manifest-Version: 1.0\r\n",
+                Level::Default,
+            )
+            .unwrap()
+            .finish();
+        assert_eq!(
+            violation(result),
+            Some((
+                "META-INF/MANIFEST.MF".to_string(),
+                Violation::NotListedFirst
+            ))
+        );
+    }
+}
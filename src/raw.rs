@@ -0,0 +1,690 @@
+//! Records of Zip format to compose custom archive layouts.
+//!
+//! [`ZipArchive`](crate::ZipArchive) writes archives with these records. Fields are written
+//! as they are, so consistency between records (sizes, offsets and Zip64 extended
+//! information) is the responsibility of the user.
+//!
+//! ```rust
+//! use zip_builder::raw::{CentralDirectoryHeader, EndOfCentralDirectory, LocalFileHeader};
+//!
+//! let mut buf = Vec::new();
+//! let local = LocalFileHeader {
+//!     version_needed: 10,
+//!     flags: 0,
+//!     method: 0,
+//!     timestamp: 0x519970AB,
+//!     crc32: 0xADF3_F363,
+//!     compressed_size: 4,
+//!     uncompressed_size: 4,
+//!     filename: b"a.txt",
+//!     extra: &[],
+//! };
+//! local.write_to(&mut buf)?;
+//! buf.extend_from_slice(b"data");
+//! let top = buf.len() as u32;
+//! let central = CentralDirectoryHeader::from_local(&local, 0);
+//! central.write_to(&mut buf)?;
+//! EndOfCentralDirectory {
+//!     entries: 1,
+//!     central_directory_size: central.size() as u32,
+//!     central_directory_offset: top,
+//!     comment: &[],
+//! }
+//! .write_to(&mut buf)?;
+//! # Ok::<(), zip_builder::Error>(())
+//! ```
+
+use crate::Result;
+use std::convert::TryFrom;
+use std::io::Write;
+
+/// Signature of local file header (PK0304).
+pub const LOCAL_FILE_HEADER_SIGNATURE: u32 = 0x04034b50;
+/// Signature of data descriptor (PK0708).
+pub const DATA_DESCRIPTOR_SIGNATURE: u32 = 0x08074b50;
+/// Signature of central directory header (PK0102).
+pub const CENTRAL_DIRECTORY_HEADER_SIGNATURE: u32 = 0x02014b50;
+/// Signature of Zip64 end of central directory record (PK0606).
+pub const ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x06064b50;
+/// Signature of Zip64 end of central directory locator (PK0607).
+pub const ZIP64_END_OF_CENTRAL_DIRECTORY_LOCATOR_SIGNATURE: u32 = 0x07064b50;
+/// Signature of end of central directory record (PK0506).
+pub const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x06054b50;
+
+/// Compression method of stored data.
+pub const METHOD_STORED: u16 = 0;
+/// Compression method of deflate.
+pub const METHOD_DEFLATED: u16 = 8;
+
+/// Flag of data descriptor following the data.
+pub const FLAG_DATA_DESCRIPTOR: u16 = 8;
+/// Flag of names encoded in UTF-8.
+pub const FLAG_UTF8: u16 = 2048;
+
+/// Header ID of Zip64 extended information extra field.
+pub const ZIP64_EXTRA_ID: u16 = 0x0001;
+/// Value of 32-bit fields whose value is in Zip64 extended information.
+pub const ZIP64_MARK: u32 = u32::MAX;
+
+/// Reader of little endian fields.
+pub(crate) struct Fields<'b> {
+    buf: &'b [u8],
+    pub(crate) pos: usize,
+}
+
+impl<'b> Fields<'b> {
+    pub(crate) fn new(buf: &'b [u8], pos: usize) -> Fields<'b> {
+        Fields { buf, pos }
+    }
+
+    pub(crate) fn bytes(&mut self, len: usize) -> Option<&'b [u8]> {
+        let bytes = self.buf.get(self.pos..self.pos.checked_add(len)?)?;
+        self.pos += len;
+        Some(bytes)
+    }
+
+    pub(crate) fn u16(&mut self) -> Option<u16> {
+        self.bytes(2).map(|b| u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    pub(crate) fn u32(&mut self) -> Option<u32> {
+        self.bytes(4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    pub(crate) fn u64(&mut self) -> Option<u64> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.bytes(8)?);
+        Some(u64::from_le_bytes(bytes))
+    }
+}
+
+/// Local file header (PK0304), followed by the data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LocalFileHeader<'a> {
+    pub version_needed: u16,
+    pub flags: u16,
+    pub method: u16,
+    /// DOS time in lower 16 bits and DOS date in upper 16 bits.
+    pub timestamp: u32,
+    pub crc32: u32,
+    pub compressed_size: u32,
+    pub uncompressed_size: u32,
+    pub filename: &'a [u8],
+    pub extra: &'a [u8],
+}
+
+impl<'a> LocalFileHeader<'a> {
+    /// Size of the record in bytes.
+    pub fn size(&self) -> usize {
+        30 + self.filename.len() + self.extra.len()
+    }
+
+    /// Parse the record at the beginning of `bytes`.
+    ///
+    /// Returns `None` if the signature differs or `bytes` is too short.
+    pub fn from_bytes(bytes: &'a [u8]) -> Option<Self> {
+        let mut fields = Fields::new(bytes, 0);
+        if fields.u32()? != LOCAL_FILE_HEADER_SIGNATURE {
+            return None;
+        }
+        let version_needed = fields.u16()?;
+        let flags = fields.u16()?;
+        let method = fields.u16()?;
+        let timestamp = fields.u32()?;
+        let crc32 = fields.u32()?;
+        let compressed_size = fields.u32()?;
+        let uncompressed_size = fields.u32()?;
+        let filename_length = fields.u16()? as usize;
+        let extra_length = fields.u16()? as usize;
+        Some(LocalFileHeader {
+            version_needed,
+            flags,
+            method,
+            timestamp,
+            crc32,
+            compressed_size,
+            uncompressed_size,
+            filename: fields.bytes(filename_length)?,
+            extra: fields.bytes(extra_length)?,
+        })
+    }
+
+    /// Serialize the record.
+    ///
+    /// Fails with [`Error::IntError`](crate::Error::IntError) if the name or the extra field
+    /// is longer than 65535 bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut record = Vec::with_capacity(self.size());
+        record.extend_from_slice(&LOCAL_FILE_HEADER_SIGNATURE.to_le_bytes());
+        record.extend_from_slice(&self.version_needed.to_le_bytes());
+        record.extend_from_slice(&self.flags.to_le_bytes());
+        record.extend_from_slice(&self.method.to_le_bytes());
+        record.extend_from_slice(&self.timestamp.to_le_bytes());
+        record.extend_from_slice(&self.crc32.to_le_bytes());
+        record.extend_from_slice(&self.compressed_size.to_le_bytes());
+        record.extend_from_slice(&self.uncompressed_size.to_le_bytes());
+        record.extend_from_slice(&u16::try_from(self.filename.len())?.to_le_bytes());
+        record.extend_from_slice(&u16::try_from(self.extra.len())?.to_le_bytes());
+        record.extend_from_slice(self.filename);
+        record.extend_from_slice(self.extra);
+        Ok(record)
+    }
+
+    /// Write the record to `output`.
+    pub fn write_to<W: Write>(&self, output: &mut W) -> Result<()> {
+        output.write_all(&self.to_bytes()?)?;
+        Ok(())
+    }
+}
+
+/// Data descriptor (PK0708) with the signature.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DataDescriptor {
+    pub crc32: u32,
+    pub compressed_size: u64,
+    pub uncompressed_size: u64,
+    /// Write sizes in 8 bytes, which is required if local file header has Zip64 extended
+    /// information. Otherwise sizes are truncated to 4 bytes.
+    pub zip64: bool,
+}
+
+impl DataDescriptor {
+    /// Size of the record in bytes.
+    pub fn size(&self) -> usize {
+        if self.zip64 {
+            24
+        } else {
+            16
+        }
+    }
+
+    /// Parse the record at the beginning of `bytes`.
+    ///
+    /// The signature may be omitted as the specification allows.
+    /// Returns `None` if `bytes` is too short.
+    pub fn from_bytes(bytes: &[u8], zip64: bool) -> Option<Self> {
+        let mut fields = Fields::new(bytes, 0);
+        if bytes.starts_with(&DATA_DESCRIPTOR_SIGNATURE.to_le_bytes()) {
+            fields.u32()?;
+        }
+        let crc32 = fields.u32()?;
+        let (compressed_size, uncompressed_size) = if zip64 {
+            (fields.u64()?, fields.u64()?)
+        } else {
+            (fields.u32()? as u64, fields.u32()? as u64)
+        };
+        Some(DataDescriptor {
+            crc32,
+            compressed_size,
+            uncompressed_size,
+            zip64,
+        })
+    }
+
+    /// Serialize the record.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut record = Vec::with_capacity(self.size());
+        record.extend_from_slice(&DATA_DESCRIPTOR_SIGNATURE.to_le_bytes());
+        record.extend_from_slice(&self.crc32.to_le_bytes());
+        if self.zip64 {
+            record.extend_from_slice(&self.compressed_size.to_le_bytes());
+            record.extend_from_slice(&self.uncompressed_size.to_le_bytes());
+        } else {
+            record.extend_from_slice(&u32::try_from(self.compressed_size)?.to_le_bytes());
+            record.extend_from_slice(&u32::try_from(self.uncompressed_size)?.to_le_bytes());
+        }
+        Ok(record)
+    }
+
+    /// Write the record to `output`.
+    pub fn write_to<W: Write>(&self, output: &mut W) -> Result<()> {
+        output.write_all(&self.to_bytes()?)?;
+        Ok(())
+    }
+}
+
+/// Central directory header (PK0102).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CentralDirectoryHeader<'a> {
+    /// Host system in upper 8 bits and version of the specification in lower 8 bits.
+    pub version_made_by: u16,
+    pub version_needed: u16,
+    pub flags: u16,
+    pub method: u16,
+    /// DOS time in lower 16 bits and DOS date in upper 16 bits.
+    pub timestamp: u32,
+    pub crc32: u32,
+    pub compressed_size: u32,
+    pub uncompressed_size: u32,
+    pub disk_number_start: u16,
+    pub internal_attributes: u16,
+    pub external_attributes: u32,
+    pub local_header_offset: u32,
+    pub filename: &'a [u8],
+    pub extra: &'a [u8],
+    pub comment: &'a [u8],
+}
+
+impl<'a> CentralDirectoryHeader<'a> {
+    /// Header of the same fields as `local` for the local header at `offset`.
+    ///
+    /// Version made by is that of MS-DOS, and attributes and comment are empty.
+    pub fn from_local(local: &LocalFileHeader<'a>, offset: u32) -> Self {
+        CentralDirectoryHeader {
+            version_made_by: local.version_needed,
+            version_needed: local.version_needed,
+            flags: local.flags,
+            method: local.method,
+            timestamp: local.timestamp,
+            crc32: local.crc32,
+            compressed_size: local.compressed_size,
+            uncompressed_size: local.uncompressed_size,
+            disk_number_start: 0,
+            internal_attributes: 0,
+            external_attributes: 0,
+            local_header_offset: offset,
+            filename: local.filename,
+            extra: local.extra,
+            comment: &[],
+        }
+    }
+
+    /// Size of the record in bytes.
+    pub fn size(&self) -> usize {
+        46 + self.filename.len() + self.extra.len() + self.comment.len()
+    }
+
+    /// Parse the record at the beginning of `bytes`.
+    ///
+    /// Returns `None` if the signature differs or `bytes` is too short.
+    pub fn from_bytes(bytes: &'a [u8]) -> Option<Self> {
+        let mut fields = Fields::new(bytes, 0);
+        if fields.u32()? != CENTRAL_DIRECTORY_HEADER_SIGNATURE {
+            return None;
+        }
+        let version_made_by = fields.u16()?;
+        let version_needed = fields.u16()?;
+        let flags = fields.u16()?;
+        let method = fields.u16()?;
+        let timestamp = fields.u32()?;
+        let crc32 = fields.u32()?;
+        let compressed_size = fields.u32()?;
+        let uncompressed_size = fields.u32()?;
+        let filename_length = fields.u16()? as usize;
+        let extra_length = fields.u16()? as usize;
+        let comment_length = fields.u16()? as usize;
+        let disk_number_start = fields.u16()?;
+        let internal_attributes = fields.u16()?;
+        let external_attributes = fields.u32()?;
+        let local_header_offset = fields.u32()?;
+        Some(CentralDirectoryHeader {
+            version_made_by,
+            version_needed,
+            flags,
+            method,
+            timestamp,
+            crc32,
+            compressed_size,
+            uncompressed_size,
+            disk_number_start,
+            internal_attributes,
+            external_attributes,
+            local_header_offset,
+            filename: fields.bytes(filename_length)?,
+            extra: fields.bytes(extra_length)?,
+            comment: fields.bytes(comment_length)?,
+        })
+    }
+
+    /// Serialize the record.
+    ///
+    /// Fails with [`Error::IntError`](crate::Error::IntError) if the name, the extra field
+    /// or the comment is longer than 65535 bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut record = Vec::with_capacity(self.size());
+        record.extend_from_slice(&CENTRAL_DIRECTORY_HEADER_SIGNATURE.to_le_bytes());
+        record.extend_from_slice(&self.version_made_by.to_le_bytes());
+        record.extend_from_slice(&self.version_needed.to_le_bytes());
+        record.extend_from_slice(&self.flags.to_le_bytes());
+        record.extend_from_slice(&self.method.to_le_bytes());
+        record.extend_from_slice(&self.timestamp.to_le_bytes());
+        record.extend_from_slice(&self.crc32.to_le_bytes());
+        record.extend_from_slice(&self.compressed_size.to_le_bytes());
+        record.extend_from_slice(&self.uncompressed_size.to_le_bytes());
+        record.extend_from_slice(&u16::try_from(self.filename.len())?.to_le_bytes());
+        record.extend_from_slice(&u16::try_from(self.extra.len())?.to_le_bytes());
+        record.extend_from_slice(&u16::try_from(self.comment.len())?.to_le_bytes());
+        record.extend_from_slice(&self.disk_number_start.to_le_bytes());
+        record.extend_from_slice(&self.internal_attributes.to_le_bytes());
+        record.extend_from_slice(&self.external_attributes.to_le_bytes());
+        record.extend_from_slice(&self.local_header_offset.to_le_bytes());
+        record.extend_from_slice(self.filename);
+        record.extend_from_slice(self.extra);
+        record.extend_from_slice(self.comment);
+        Ok(record)
+    }
+
+    /// Write the record to `output`.
+    pub fn write_to<W: Write>(&self, output: &mut W) -> Result<()> {
+        output.write_all(&self.to_bytes()?)?;
+        Ok(())
+    }
+}
+
+/// Zip64 end of central directory record (PK0606) of a single disk archive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Zip64EndOfCentralDirectory {
+    pub version_made_by: u16,
+    pub version_needed: u16,
+    pub entries: u64,
+    pub central_directory_size: u64,
+    pub central_directory_offset: u64,
+}
+
+impl Zip64EndOfCentralDirectory {
+    /// Size of the record in bytes.
+    pub fn size(&self) -> usize {
+        56
+    }
+
+    /// Parse the record at the beginning of `bytes`.
+    ///
+    /// Extensible data sector is ignored.
+    /// Returns `None` if the signature differs or `bytes` is too short.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let mut fields = Fields::new(bytes, 0);
+        if fields.u32()? != ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE {
+            return None;
+        }
+        fields.u64()?;
+        let version_made_by = fields.u16()?;
+        let version_needed = fields.u16()?;
+        fields.bytes(16)?;
+        Some(Zip64EndOfCentralDirectory {
+            version_made_by,
+            version_needed,
+            entries: fields.u64()?,
+            central_directory_size: fields.u64()?,
+            central_directory_offset: fields.u64()?,
+        })
+    }
+
+    /// Serialize the record.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut record = Vec::with_capacity(self.size());
+        record.extend_from_slice(&ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes());
+        record.extend_from_slice(&44u64.to_le_bytes());
+        record.extend_from_slice(&self.version_made_by.to_le_bytes());
+        record.extend_from_slice(&self.version_needed.to_le_bytes());
+        record.extend_from_slice(&0u32.to_le_bytes());
+        record.extend_from_slice(&0u32.to_le_bytes());
+        record.extend_from_slice(&self.entries.to_le_bytes());
+        record.extend_from_slice(&self.entries.to_le_bytes());
+        record.extend_from_slice(&self.central_directory_size.to_le_bytes());
+        record.extend_from_slice(&self.central_directory_offset.to_le_bytes());
+        Ok(record)
+    }
+
+    /// Write the record to `output`.
+    pub fn write_to<W: Write>(&self, output: &mut W) -> Result<()> {
+        output.write_all(&self.to_bytes()?)?;
+        Ok(())
+    }
+}
+
+/// Zip64 end of central directory locator (PK0607) of a single disk archive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Zip64EndOfCentralDirectoryLocator {
+    /// Offset of [`Zip64EndOfCentralDirectory`].
+    pub offset: u64,
+}
+
+impl Zip64EndOfCentralDirectoryLocator {
+    /// Size of the record in bytes.
+    pub fn size(&self) -> usize {
+        20
+    }
+
+    /// Parse the record at the beginning of `bytes`.
+    ///
+    /// Returns `None` if the signature differs or `bytes` is too short.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let mut fields = Fields::new(bytes, 0);
+        if fields.u32()? != ZIP64_END_OF_CENTRAL_DIRECTORY_LOCATOR_SIGNATURE {
+            return None;
+        }
+        fields.u32()?;
+        Some(Zip64EndOfCentralDirectoryLocator {
+            offset: fields.u64()?,
+        })
+    }
+
+    /// Serialize the record.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut record = Vec::with_capacity(self.size());
+        record.extend_from_slice(&ZIP64_END_OF_CENTRAL_DIRECTORY_LOCATOR_SIGNATURE.to_le_bytes());
+        record.extend_from_slice(&0u32.to_le_bytes());
+        record.extend_from_slice(&self.offset.to_le_bytes());
+        record.extend_from_slice(&1u32.to_le_bytes());
+        Ok(record)
+    }
+
+    /// Write the record to `output`.
+    pub fn write_to<W: Write>(&self, output: &mut W) -> Result<()> {
+        output.write_all(&self.to_bytes()?)?;
+        Ok(())
+    }
+}
+
+/// End of central directory record (PK0506) of a single disk archive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EndOfCentralDirectory<'a> {
+    /// Number of entries, 0xFFFF if it is in Zip64 end of central directory record.
+    pub entries: u16,
+    pub central_directory_size: u32,
+    pub central_directory_offset: u32,
+    pub comment: &'a [u8],
+}
+
+impl<'a> EndOfCentralDirectory<'a> {
+    /// Size of the record in bytes.
+    pub fn size(&self) -> usize {
+        22 + self.comment.len()
+    }
+
+    /// Parse the record at the beginning of `bytes`.
+    ///
+    /// Returns `None` if the signature differs or `bytes` is too short.
+    pub fn from_bytes(bytes: &'a [u8]) -> Option<Self> {
+        let mut fields = Fields::new(bytes, 0);
+        if fields.u32()? != END_OF_CENTRAL_DIRECTORY_SIGNATURE {
+            return None;
+        }
+        fields.bytes(4)?;
+        let entries = fields.u16()?;
+        fields.u16()?;
+        let central_directory_size = fields.u32()?;
+        let central_directory_offset = fields.u32()?;
+        let comment_length = fields.u16()? as usize;
+        Some(EndOfCentralDirectory {
+            entries,
+            central_directory_size,
+            central_directory_offset,
+            comment: fields.bytes(comment_length)?,
+        })
+    }
+
+    /// Serialize the record.
+    ///
+    /// Fails with [`Error::IntError`](crate::Error::IntError) if the comment
+    /// is longer than 65535 bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut record = Vec::with_capacity(self.size());
+        record.extend_from_slice(&END_OF_CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes());
+        record.extend_from_slice(&0u32.to_le_bytes());
+        record.extend_from_slice(&self.entries.to_le_bytes());
+        record.extend_from_slice(&self.entries.to_le_bytes());
+        record.extend_from_slice(&self.central_directory_size.to_le_bytes());
+        record.extend_from_slice(&self.central_directory_offset.to_le_bytes());
+        record.extend_from_slice(&u16::try_from(self.comment.len())?.to_le_bytes());
+        record.extend_from_slice(self.comment);
+        Ok(record)
+    }
+
+    /// Write the record to `output`.
+    pub fn write_to<W: Write>(&self, output: &mut W) -> Result<()> {
+        output.write_all(&self.to_bytes()?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        CentralDirectoryHeader, DataDescriptor, EndOfCentralDirectory, LocalFileHeader,
+        Zip64EndOfCentralDirectory, Zip64EndOfCentralDirectoryLocator,
+    };
+    use crate::{verify, Error, Level, ZipArchiveBuilder, CRC32};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn it_works() {
+        let mut crc = CRC32::default();
+        crc.write(b"content");
+        let mut buf = Vec::new();
+        let local = LocalFileHeader {
+            version_needed: 10,
+            flags: 2048,
+            method: 0,
+            timestamp: 0x42B404E3,
+            crc32: crc.finish(),
+            compressed_size: 7,
+            uncompressed_size: 7,
+            filename: b"a.txt",
+            extra: &[],
+        };
+        local.write_to(&mut buf).unwrap();
+        assert_eq!(buf.len(), local.size());
+        buf.extend_from_slice(b"content");
+        let top = buf.len();
+        let central = CentralDirectoryHeader::from_local(&local, 0);
+        central.write_to(&mut buf).unwrap();
+        EndOfCentralDirectory {
+            entries: 1,
+            central_directory_size: (buf.len() - top) as u32,
+            central_directory_offset: top as u32,
+            comment: &[],
+        }
+        .write_to(&mut buf)
+        .unwrap();
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+
+        let mut expected = Vec::new();
+        ZipArchiveBuilder::new()
+            .modified(UNIX_EPOCH + Duration::from_secs(1369010347))
+            .build(&mut expected)
+            .add_entry("a.txt", b"content", Level::Raw)
+            .unwrap()
+            .finish()
+            .unwrap();
+        assert_eq!(buf[..top], expected[..top]);
+
+        let long = vec![b'a'; 65536];
+        let local = LocalFileHeader {
+            filename: &long,
+            ..local
+        };
+        assert!(matches!(
+            local.write_to(&mut Vec::new()),
+            Err(Error::IntError(_))
+        ));
+    }
+
+    #[test]
+    fn round_trip() {
+        let local = LocalFileHeader {
+            version_needed: 20,
+            flags: 2056,
+            method: 8,
+            timestamp: 0x519970AB,
+            crc32: 0x12345678,
+            compressed_size: 100,
+            uncompressed_size: 200,
+            filename: b"dir/file.txt",
+            extra: &[0x55, 0x54, 1, 0, 0],
+        };
+        let bytes = local.to_bytes().unwrap();
+        assert_eq!(bytes.len(), local.size());
+        assert_eq!(LocalFileHeader::from_bytes(&bytes), Some(local));
+        assert_eq!(LocalFileHeader::from_bytes(&bytes[..bytes.len() - 1]), None);
+
+        let central = CentralDirectoryHeader {
+            version_made_by: 0x031E,
+            external_attributes: 0o100644 << 16,
+            internal_attributes: 1,
+            comment: b"comment",
+            ..CentralDirectoryHeader::from_local(&local, 1234)
+        };
+        let bytes = central.to_bytes().unwrap();
+        assert_eq!(bytes.len(), central.size());
+        assert_eq!(CentralDirectoryHeader::from_bytes(&bytes), Some(central));
+        assert_eq!(CentralDirectoryHeader::from_bytes(&bytes[4..]), None);
+
+        for &zip64 in [false, true].iter() {
+            let descriptor = DataDescriptor {
+                crc32: 0x12345678,
+                compressed_size: 100,
+                uncompressed_size: 200,
+                zip64,
+            };
+            let bytes = descriptor.to_bytes().unwrap();
+            assert_eq!(bytes.len(), descriptor.size());
+            assert_eq!(DataDescriptor::from_bytes(&bytes, zip64), Some(descriptor));
+            assert_eq!(
+                DataDescriptor::from_bytes(&bytes[4..], zip64),
+                Some(descriptor)
+            );
+        }
+        let descriptor = DataDescriptor {
+            crc32: 0,
+            compressed_size: 1 << 32,
+            uncompressed_size: 1 << 32,
+            zip64: false,
+        };
+        assert!(descriptor.to_bytes().is_err());
+
+        let record = Zip64EndOfCentralDirectory {
+            version_made_by: 45,
+            version_needed: 45,
+            entries: 70000,
+            central_directory_size: 1 << 33,
+            central_directory_offset: 1 << 34,
+        };
+        let bytes = record.to_bytes().unwrap();
+        assert_eq!(bytes.len(), record.size());
+        assert_eq!(Zip64EndOfCentralDirectory::from_bytes(&bytes), Some(record));
+
+        let locator = Zip64EndOfCentralDirectoryLocator { offset: 1 << 35 };
+        let bytes = locator.to_bytes().unwrap();
+        assert_eq!(bytes.len(), locator.size());
+        assert_eq!(
+            Zip64EndOfCentralDirectoryLocator::from_bytes(&bytes),
+            Some(locator)
+        );
+
+        let ending = EndOfCentralDirectory {
+            entries: 3,
+            central_directory_size: 150,
+            central_directory_offset: 300,
+            comment: b"archive comment",
+        };
+        let bytes = ending.to_bytes().unwrap();
+        assert_eq!(bytes.len(), ending.size());
+        assert_eq!(EndOfCentralDirectory::from_bytes(&bytes), Some(ending));
+        assert_eq!(
+            EndOfCentralDirectory::from_bytes(&bytes[..bytes.len() - 1]),
+            None
+        );
+    }
+}
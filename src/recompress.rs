@@ -0,0 +1,132 @@
+use crate::verify::read_entries;
+use crate::{ArchiveSummary, DateTime, EntryOptions, Error, Metadata, Result, Unicode, ZipArchive};
+use std::io::{Read, Write};
+
+/// Rewrite an archive with options for each name given by `policy`.
+///
+/// See [`ZipArchive::add_archive`].
+///
+/// ```
+/// # use zip_builder::{recompress, ExtensionPolicy, Level, ZipArchive};
+/// # let mut stored = Vec::new();
+/// # ZipArchive::new(&mut stored)
+/// #     .add_entry("a.txt", &[b'a'; 1000], Level::Raw)?
+/// #     .finish()?;
+/// let policy = ExtensionPolicy::new(Level::High).set("png", Level::Raw);
+/// let mut output = Vec::new();
+/// recompress(stored.as_slice(), &mut output, |name| policy.level(name).into())?;
+/// assert!(output.len() < stored.len());
+/// # Ok::<(), zip_builder::Error>(())
+/// ```
+pub fn recompress<R, W, F>(input: R, output: &mut W, policy: F) -> Result<ArchiveSummary>
+where
+    R: Read,
+    W: Write,
+    F: FnMut(&str) -> EntryOptions,
+{
+    ZipArchive::new(output).add_archive(input, policy)?.finish()
+}
+
+impl<'a, T: Write + ?Sized + 'a> ZipArchive<'a, T> {
+    /// Add entries of an existing archive with options for each name given by `policy`.
+    ///
+    /// Contents are decompressed and compressed again by the options.
+    /// Names, UTF-8 flags, modification times and external attributes are kept unless
+    /// the options set them, so [`EntryOptions::modified`] with a fixed time makes the archive
+    /// reproducible. Extra fields and comments are not copied. Fails with
+    /// [`Error::InvalidArchive`] if the archive is inconsistent, or has encrypted entries
+    /// or entries of methods which [`verify`](crate::verify) cannot decode.
+    pub fn add_archive<R, F>(mut self, input: R, policy: F) -> Result<Self>
+    where
+        R: Read,
+        F: FnMut(&str) -> EntryOptions,
+    {
+        let result = self.push_archive(input, policy);
+        self.or_poison(result)
+    }
+
+    fn push_archive<R, F>(&mut self, mut input: R, mut policy: F) -> Result<()>
+    where
+        R: Read,
+        F: FnMut(&str) -> EntryOptions,
+    {
+        self.check_poisoned()?;
+        let mut buf = Vec::new();
+        input.read_to_end(&mut buf)?;
+        let entries = read_entries(&buf).ok_or(Error::InvalidArchive)?;
+        for (header, content) in entries {
+            let name = String::from_utf8_lossy(&header.filename);
+            let unicode = if header.flags & 2048 != 0 {
+                Unicode::Always
+            } else {
+                Unicode::Never
+            };
+            let mut original = EntryOptions::default().unicode(unicode);
+            if let Some(time) = DateTime::from_dos_time(header.timestamp) {
+                original = original.modified(time.system_time());
+            }
+            let options = policy(&name).inherit(&original);
+            let mut metadata = Metadata::default();
+            metadata.raw_name = self.raw_name(&header.filename);
+            let host = (header.version_made_by >> 8) as u8;
+            metadata.set_attributes(host, header.external_attributes);
+            self.push_entry_with(&name, &content, options, &metadata)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::recompress;
+    use crate::{verify, DateTime, EntryOptions, Error, Level, ZipArchive};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn it_works() {
+        let mut original = Vec::new();
+        ZipArchive::new(&mut original)
+            .add_entry(
+                "stored.txt",
+                &[b'a'; 1000],
+                EntryOptions::new(Level::Raw)
+                    .modified(UNIX_EPOCH + Duration::from_secs(1608905122)),
+            )
+            .unwrap()
+            .add_entry_bytes(b"caf\xe9.txt", b"latin-1", Level::High)
+            .unwrap()
+            .finish()
+            .unwrap();
+
+        let fixed = "1980-01-01".parse::<DateTime>().unwrap().system_time();
+        let mut output = Vec::new();
+        let summary = recompress(original.as_slice(), &mut output, |name| {
+            if name == "stored.txt" {
+                EntryOptions::new(Level::High)
+            } else {
+                EntryOptions::new(Level::Raw).modified(fixed)
+            }
+        })
+        .unwrap();
+        assert_eq!(verify(output.as_slice()).unwrap(), vec![]);
+        assert!(summary.entries[0].compressed_size < 1000);
+        assert_eq!(summary.entries[1].compressed_size, 7);
+        // Method, time and name of the local file headers.
+        assert_eq!(&output[8..10], &8u16.to_le_bytes());
+        assert_eq!(&output[10..14], &original[10..14]);
+        let second = summary.entries[1].offset as usize;
+        assert_eq!(&output[second + 8..second + 10], &0u16.to_le_bytes());
+        assert_eq!(
+            &output[second + 10..second + 14],
+            &0x210000u32.to_le_bytes()
+        );
+        assert_eq!(&output[second + 30..second + 38], b"caf\xe9.txt");
+
+        let broken = &original[..original.len() - 1];
+        let mut output = Vec::new();
+        assert!(matches!(
+            recompress(broken, &mut output, |_| Level::Raw.into()),
+            Err(Error::InvalidArchive)
+        ));
+    }
+}
@@ -0,0 +1,312 @@
+use std::collections::VecDeque;
+use std::io::{Error, ErrorKind, Result, Write};
+use std::thread;
+use std::time::Duration;
+
+/// Function resuming the inner writer after a failure, see [`RetryWriter::reconnect`].
+type Reconnect<W> = Box<dyn FnMut(&mut W) -> Result<u64> + Send>;
+
+/// How [`RetryWriter`] retries failed writes.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    attempts: u32,
+    backoff: Duration,
+    max_backoff: Duration,
+    replay: usize,
+    transient: fn(&Error) -> bool,
+}
+
+impl RetryPolicy {
+    /// Try each write up to `attempts` times, sleeping `backoff` after the first failure
+    /// and doubling it after each following failure up to 30 seconds.
+    pub fn new(attempts: u32, backoff: Duration) -> RetryPolicy {
+        RetryPolicy {
+            attempts: attempts.max(1),
+            backoff,
+            max_backoff: Duration::from_secs(30),
+            replay: 0,
+            transient: is_transient,
+        }
+    }
+
+    /// Limit the doubled backoff to `max_backoff`.
+    pub fn max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// Keep the last `bytes` written in a ring buffer, to replay them after a reconnection.
+    pub fn replay(mut self, bytes: usize) -> Self {
+        self.replay = bytes;
+        self
+    }
+
+    /// Retry errors for which `transient` returns `true`,
+    /// instead of [`is_transient`](RetryPolicy::is_transient).
+    pub fn transient(mut self, transient: fn(&Error) -> bool) -> Self {
+        self.transient = transient;
+        self
+    }
+
+    /// Default classification of transient errors: `WouldBlock`, `Interrupted`, `TimedOut`,
+    /// `BrokenPipe`, `ConnectionReset` and `ConnectionAborted`.
+    pub fn is_transient(error: &Error) -> bool {
+        is_transient(error)
+    }
+}
+
+fn is_transient(error: &Error) -> bool {
+    matches!(
+        error.kind(),
+        ErrorKind::WouldBlock
+            | ErrorKind::Interrupted
+            | ErrorKind::TimedOut
+            | ErrorKind::BrokenPipe
+            | ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted
+    )
+}
+
+/// Writer which retries writes and flushes to the inner writer failing with transient errors.
+///
+/// Writes which fail do not accept any byte, so retrying them is safe for most writers.
+/// For connections which lose bytes already accepted when they break, set the
+/// [`reconnect`](RetryWriter::reconnect) function, which reopens the connection and returns
+/// the number of bytes the peer has received, and bytes after it are replayed from the ring
+/// buffer of [`RetryPolicy::replay`]. Each write sleeps at most the sum of the backoffs.
+///
+/// ```rust
+/// use std::time::Duration;
+/// use zip_builder::{Level, RetryPolicy, RetryWriter, ZipArchive};
+///
+/// let policy = RetryPolicy::new(5, Duration::from_millis(100));
+/// let mut output = RetryWriter::new(Vec::new(), policy);
+/// ZipArchive::new(&mut output)
+///     .add_entry("file.txt", b"content", Level::Default)?
+///     .finish()?;
+/// let buf = output.into_inner();
+/// # Ok::<(), zip_builder::Error>(())
+/// ```
+pub struct RetryWriter<W: Write> {
+    inner: W,
+    policy: RetryPolicy,
+    reconnect: Option<Reconnect<W>>,
+    /// Last bytes written, up to the replay size of the policy.
+    ring: VecDeque<u8>,
+    written: u64,
+    retries: u64,
+}
+
+impl<W: Write> RetryWriter<W> {
+    /// Create a writer over `inner` retrying by `policy`.
+    pub fn new(inner: W, policy: RetryPolicy) -> RetryWriter<W> {
+        RetryWriter {
+            inner,
+            ring: VecDeque::with_capacity(policy.replay),
+            policy,
+            reconnect: None,
+            written: 0,
+            retries: 0,
+        }
+    }
+
+    /// Call `reconnect` before each retry to reopen the inner writer.
+    ///
+    /// It returns the number of bytes the peer has received. The bytes written after it are
+    /// written again, and the retry fails with `UnexpectedEof` if they are no longer in the
+    /// ring buffer.
+    pub fn reconnect<F>(mut self, reconnect: F) -> Self
+    where
+        F: FnMut(&mut W) -> Result<u64> + Send + 'static,
+    {
+        self.reconnect = Some(Box::new(reconnect));
+        self
+    }
+
+    /// Number of retries so far.
+    pub fn retries(&self) -> u64 {
+        self.retries
+    }
+
+    /// Number of bytes written so far.
+    pub fn written(&self) -> u64 {
+        self.written
+    }
+
+    /// Reference to the inner writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Unwrap the inner writer.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    /// Run `operation` until it succeeds, fails with a permanent error or runs out of attempts.
+    fn retry<R>(&mut self, mut operation: impl FnMut(&mut W) -> Result<R>) -> Result<R> {
+        let mut backoff = self.policy.backoff;
+        let mut attempt = 1;
+        loop {
+            let resumed = if attempt > 1 { self.resume() } else { Ok(()) };
+            let error = match resumed.and_then(|()| operation(&mut self.inner)) {
+                Ok(result) => return Ok(result),
+                Err(error) => error,
+            };
+            if attempt >= self.policy.attempts || !(self.policy.transient)(&error) {
+                return Err(error);
+            }
+            thread::sleep(backoff);
+            backoff = (backoff * 2).min(self.policy.max_backoff);
+            attempt += 1;
+            self.retries += 1;
+        }
+    }
+
+    /// Reopen the inner writer and replay lost bytes, before a retry.
+    fn resume(&mut self) -> Result<()> {
+        let reconnect = match &mut self.reconnect {
+            Some(reconnect) => reconnect,
+            _ => return Ok(()),
+        };
+        let received = reconnect(&mut self.inner)?;
+        let lost = self.written.checked_sub(received).ok_or_else(|| {
+            Error::new(ErrorKind::InvalidData, "the peer received unwritten bytes")
+        })?;
+        if lost > self.ring.len() as u64 {
+            return Err(Error::new(
+                ErrorKind::UnexpectedEof,
+                "lost bytes are no longer in the ring buffer",
+            ));
+        }
+        let start = self.ring.len() - lost as usize;
+        self.inner.write_all(&self.ring.make_contiguous()[start..])
+    }
+
+    /// Keep written bytes in the ring buffer.
+    fn record(&mut self, bytes: &[u8]) {
+        self.written += bytes.len() as u64;
+        let capacity = self.policy.replay;
+        let bytes = &bytes[bytes.len().saturating_sub(capacity)..];
+        let excess = (self.ring.len() + bytes.len()).saturating_sub(capacity);
+        self.ring.drain(..excess);
+        self.ring.extend(bytes);
+    }
+}
+
+impl<W: Write> Write for RetryWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let written = self.retry(|inner| inner.write(buf))?;
+        self.record(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.retry(|inner| inner.flush())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{RetryPolicy, RetryWriter};
+    use crate::{verify, Level, ZipArchive};
+    use std::io::{ErrorKind, Write};
+    use std::time::Duration;
+
+    /// Writer failing every fourth call, which loses the last `lost` bytes when it fails.
+    struct Flaky {
+        buf: Vec<u8>,
+        calls: usize,
+        lost: usize,
+    }
+
+    impl Write for Flaky {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.calls += 1;
+            if self.calls == 4 {
+                self.calls = 0;
+                let len = self.buf.len().saturating_sub(self.lost);
+                self.buf.truncate(len);
+                return Err(ErrorKind::BrokenPipe.into());
+            }
+            self.buf.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn it_works() {
+        fn build<W: Write>(output: &mut W) {
+            ZipArchive::new(output)
+                .add_entry("a.txt", &[b'a'; 1000], Level::Default)
+                .unwrap()
+                .add_entry("b.txt", b"b", Level::Raw)
+                .unwrap()
+                .finish()
+                .unwrap();
+        }
+        let mut expected = Vec::new();
+        build(&mut expected);
+        let policy = RetryPolicy::new(2, Duration::ZERO);
+        let flaky = Flaky {
+            buf: Vec::new(),
+            calls: 0,
+            lost: 0,
+        };
+        let mut output = RetryWriter::new(flaky, policy.clone());
+        build(&mut output);
+        assert!(output.retries() > 0);
+        assert_eq!(output.written(), expected.len() as u64);
+        assert_eq!(output.into_inner().buf, expected);
+
+        let flaky = Flaky {
+            buf: Vec::new(),
+            calls: 0,
+            lost: 10,
+        };
+        let mut output = RetryWriter::new(flaky, RetryPolicy::new(3, Duration::ZERO).replay(16))
+            .reconnect(|flaky: &mut Flaky| Ok(flaky.buf.len() as u64));
+        build(&mut output);
+        let buf = output.into_inner().buf;
+        assert_eq!(buf, expected);
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+
+        let mut output = RetryWriter::new(Vec::new(), policy.clone().replay(4))
+            .reconnect(|_: &mut Vec<u8>| Ok(0));
+        output.write_all(b"abcdef").unwrap();
+        let error = output.resume().unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+
+        let mut output = RetryWriter::new(Vec::new(), policy.replay(4));
+        output.write_all(b"abc").unwrap();
+        output.write_all(b"defgh").unwrap();
+        assert_eq!(output.ring, b"efgh");
+    }
+
+    #[test]
+    fn permanent() {
+        struct Denied(usize);
+        impl Write for Denied {
+            fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+                self.0 += 1;
+                Err(ErrorKind::PermissionDenied.into())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+        let mut output = RetryWriter::new(Denied(0), RetryPolicy::new(5, Duration::ZERO));
+        assert!(output.write(b"a").is_err());
+        assert_eq!(output.retries(), 0);
+        let mut output = RetryWriter::new(
+            Denied(0),
+            RetryPolicy::new(3, Duration::ZERO).transient(|_| true),
+        );
+        assert!(output.write(b"a").is_err());
+        assert_eq!(output.into_inner().0, 3);
+    }
+}
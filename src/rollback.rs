@@ -0,0 +1,168 @@
+use crate::{EntryOptions, Result, ZipArchive, ZipState};
+use std::fs::File;
+use std::io;
+use std::io::{Cursor, Seek, SeekFrom, Write};
+
+/// Output which can be cut at a position, needed to roll back a failed entry.
+pub trait Truncate: Write + Seek {
+    /// Discard data after `len` bytes from the start.
+    fn truncate(&mut self, len: u64) -> io::Result<()>;
+}
+
+impl Truncate for File {
+    fn truncate(&mut self, len: u64) -> io::Result<()> {
+        self.set_len(len)
+    }
+}
+
+impl Truncate for Cursor<Vec<u8>> {
+    fn truncate(&mut self, len: u64) -> io::Result<()> {
+        self.get_mut().truncate(len as usize);
+        Ok(())
+    }
+}
+
+impl Truncate for Cursor<&mut Vec<u8>> {
+    fn truncate(&mut self, len: u64) -> io::Result<()> {
+        self.get_mut().truncate(len as usize);
+        Ok(())
+    }
+}
+
+impl<'a, T: Truncate + ?Sized + 'a> ZipArchive<'a, T> {
+    /// Add a entry, or restore the archive exactly as it was if it fails.
+    ///
+    /// The position of the output is recorded before the entry, and on error
+    /// the output is rewound and truncated there, so the call can be retried.
+    /// The error is returned in any case. If the rollback also fails,
+    /// the archive is poisoned like [`push_entry`](ZipArchive::push_entry).
+    pub fn push_entry_or_rollback<O: Into<EntryOptions>>(
+        &mut self,
+        name: &str,
+        content: &[u8],
+        options: O,
+    ) -> Result<()> {
+        self.check_poisoned()?;
+        let position = self.output.stream_position()?;
+        let (count, offset, total_size) = (self.entries.len(), self.offset, self.total_size);
+        let folded = self.folded_names.len();
+        self.hold_signed();
+        let error = match self.push_entry(name, content, options) {
+            Ok(()) => {
+                self.commit_signed();
+                return Ok(());
+            }
+            Err(error) => error,
+        };
+        self.discard_signed();
+        if self.state == ZipState::Processing {
+            self.output.seek(SeekFrom::Start(position))?;
+            self.output.truncate(position)?;
+            self.entries.truncate(count);
+            self.digests.retain(|_, index| *index < count);
+            if self.folded_names.len() > folded {
+                self.forget_case(name);
+            }
+            self.offset = offset;
+            self.total_size = total_size;
+            self.state = ZipState::Breathe;
+        }
+        Err(error)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Truncate;
+    use crate::Signer;
+    use crate::{verify, Error, Level, ZipArchive};
+    use std::io::{Cursor, Seek, SeekFrom, Write};
+
+    struct Unstable {
+        cursor: Cursor<Vec<u8>>,
+        failures: usize,
+    }
+
+    impl Write for Unstable {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            if buf.len() > 100 && self.failures > 0 {
+                self.failures -= 1;
+                self.cursor.write_all(&buf[..50])?;
+                return Err(std::io::ErrorKind::Other.into());
+            }
+            self.cursor.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Seek for Unstable {
+        fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+            self.cursor.seek(pos)
+        }
+    }
+
+    impl Truncate for Unstable {
+        fn truncate(&mut self, len: u64) -> std::io::Result<()> {
+            self.cursor.truncate(len)
+        }
+    }
+
+    #[test]
+    fn it_works() {
+        let mut output = Unstable {
+            cursor: Cursor::new(Vec::new()),
+            failures: 1,
+        };
+        let mut zip = ZipArchive::new(&mut output).deduplicate();
+        zip.push_entry_or_rollback("first.txt", b"first", Level::Raw)
+            .unwrap();
+        let large = [b'a'; 1000];
+        assert!(matches!(
+            zip.push_entry_or_rollback("large.txt", &large, Level::Raw),
+            Err(Error::Entry { source, .. }) if matches!(*source, Error::IoError(_))
+        ));
+        zip.push_entry_or_rollback("large.txt", &large, Level::Raw)
+            .unwrap();
+        let summary = zip.finish().unwrap();
+        assert_eq!(summary.entries.len(), 2);
+        let buf = output.cursor.into_inner();
+        assert_eq!(summary.size as usize, buf.len());
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+    }
+
+    /// Signer whose signature is the signed bytes themselves.
+    #[derive(Default)]
+    struct Recorder(Vec<u8>);
+
+    impl Signer for Recorder {
+        fn update(&mut self, bytes: &[u8]) {
+            self.0.extend_from_slice(bytes);
+        }
+
+        fn finish(&mut self) -> Vec<u8> {
+            std::mem::take(&mut self.0)
+        }
+    }
+
+    #[test]
+    fn signed() {
+        let mut output = Unstable {
+            cursor: Cursor::new(Vec::new()),
+            failures: 1,
+        };
+        let mut zip = ZipArchive::new(&mut output).signer(Recorder::default());
+        zip.push_entry_or_rollback("first.txt", b"first", Level::Raw)
+            .unwrap();
+        let large = [b'a'; 1000];
+        assert!(zip
+            .push_entry_or_rollback("large.txt", &large, Level::Raw)
+            .is_err());
+        zip.push_entry_or_rollback("large.txt", &large, Level::Raw)
+            .unwrap();
+        let summary = zip.finish().unwrap();
+        assert_eq!(summary.signature.unwrap(), output.cursor.into_inner());
+    }
+}
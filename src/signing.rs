@@ -0,0 +1,236 @@
+use crate::ZipArchive;
+use std::io::{IoSlice, Result, Write};
+
+/// Receiver of the archive bytes to compute a signature.
+///
+/// Set it by [`ZipArchive::signer`]. Every byte written to the output is given to
+/// [`update`](Signer::update) in order, and [`finish`](Signer::finish) returns the signature
+/// set to [`ArchiveSummary::signature`](crate::ArchiveSummary::signature).
+///
+/// Bytes of an entry which can still be rolled back, by
+/// [`push_entry_or_rollback`](ZipArchive::push_entry_or_rollback) or
+/// [`EntryWriter::rewind`](crate::EntryWriter::rewind), are held in memory and given only
+/// when the entry is kept, so the signature covers exactly the bytes left in the output.
+pub trait Signer {
+    /// Called with the next bytes of the archive.
+    fn update(&mut self, bytes: &[u8]);
+
+    /// Called once after the last byte is given, returns the signature.
+    fn finish(&mut self) -> Vec<u8>;
+
+    /// Write the signature as the archive comment. `false` by default.
+    ///
+    /// Then the signature covers the archive up to the comment length field of
+    /// end of central directory record, and the comment set by
+    /// [`ZipArchiveBuilder::comment`](crate::ZipArchiveBuilder::comment) is replaced.
+    fn embed_in_comment(&self) -> bool {
+        false
+    }
+}
+
+/// Signer of the archive with bytes held back while an entry can be rolled back.
+pub(crate) struct Signing<'a> {
+    signer: Box<dyn Signer + Send + 'a>,
+    /// Bytes of the entry in progress, given to the signer when it is committed.
+    pending: Option<Vec<u8>>,
+}
+
+impl<'a> Signing<'a> {
+    fn update(&mut self, bytes: &[u8]) {
+        match &mut self.pending {
+            Some(pending) => pending.extend_from_slice(bytes),
+            None => self.signer.update(bytes),
+        }
+    }
+
+    pub(crate) fn finish(&mut self) -> Vec<u8> {
+        self.signer.finish()
+    }
+
+    pub(crate) fn embed_in_comment(&self) -> bool {
+        self.signer.embed_in_comment()
+    }
+}
+
+/// Writer giving written bytes to the signer.
+///
+/// Bytes are collected in the batch buffer instead of the output if it is given.
+pub(crate) struct Signed<'s, 'a, T: Write + ?Sized> {
+    output: &'s mut T,
+    batch: Option<&'s mut Vec<u8>>,
+    signer: Option<&'s mut Signing<'a>>,
+}
+
+impl<'s, 'a, T: Write + ?Sized> Signed<'s, 'a, T> {
+    pub(crate) fn new(
+        output: &'s mut T,
+        batch: &'s mut Option<Vec<u8>>,
+        signer: &'s mut Option<Signing<'a>>,
+    ) -> Self {
+        Signed {
+            output,
+            batch: batch.as_mut(),
+            signer: signer.as_mut(),
+        }
+    }
+}
+
+impl<T: Write + ?Sized> Write for Signed<'_, '_, T> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let written = match self.batch.as_mut() {
+            Some(batch) => {
+                batch.extend_from_slice(buf);
+                buf.len()
+            }
+            None => self.output.write(buf)?,
+        };
+        if let Some(signer) = self.signer.as_mut() {
+            signer.update(&buf[..written]);
+        }
+        Ok(written)
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> Result<usize> {
+        let written = match self.batch.as_mut() {
+            Some(batch) => {
+                bufs.iter().for_each(|buf| batch.extend_from_slice(buf));
+                bufs.iter().map(|buf| buf.len()).sum()
+            }
+            None => self.output.write_vectored(bufs)?,
+        };
+        if let Some(signer) = self.signer.as_mut() {
+            let mut rest = written;
+            for buf in bufs {
+                let length = buf.len().min(rest);
+                signer.update(&buf[..length]);
+                rest -= length;
+            }
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.output.flush()
+    }
+}
+
+impl<'a, T: Write + ?Sized + 'a> ZipArchive<'a, T> {
+    /// Give the bytes of the archive to `signer` as they are written.
+    ///
+    /// Set it before adding entries, since bytes written before are not given to it.
+    /// Bytes removed by [`push_entry_or_rollback`](ZipArchive::push_entry_or_rollback)
+    /// are not given either, see [`Signer`].
+    ///
+    /// ```rust
+    /// use zip_builder::{Level, Signer, ZipArchive};
+    ///
+    /// #[derive(Default)]
+    /// struct Length(u64);
+    ///
+    /// impl Signer for Length {
+    ///     fn update(&mut self, bytes: &[u8]) {
+    ///         self.0 += bytes.len() as u64;
+    ///     }
+    ///
+    ///     fn finish(&mut self) -> Vec<u8> {
+    ///         self.0.to_le_bytes().to_vec()
+    ///     }
+    /// }
+    ///
+    /// let mut buf = Vec::new();
+    /// let summary = ZipArchive::new(&mut buf)
+    ///     .signer(Length::default())
+    ///     .add_entry("file.txt", b"content", Level::Default)?
+    ///     .finish()?;
+    /// assert_eq!(summary.signature, Some((buf.len() as u64).to_le_bytes().to_vec()));
+    /// # Ok::<(), zip_builder::Error>(())
+    /// ```
+    pub fn signer<S: Signer + Send + 'a>(mut self, signer: S) -> Self {
+        self.signer = Some(Signing {
+            signer: Box::new(signer),
+            pending: None,
+        });
+        self
+    }
+
+    /// Hold back bytes from the signer until the entry to be written is committed.
+    pub(crate) fn hold_signed(&mut self) {
+        if let Some(signing) = &mut self.signer {
+            signing.pending = Some(Vec::new());
+        }
+    }
+
+    /// Give the bytes held back to the signer, since they stay in the output.
+    pub(crate) fn commit_signed(&mut self) {
+        if let Some(signing) = &mut self.signer {
+            if let Some(pending) = signing.pending.take() {
+                signing.signer.update(&pending);
+            }
+        }
+    }
+
+    /// Forget the bytes held back, since they are removed from the output.
+    pub(crate) fn discard_signed(&mut self) {
+        if let Some(signing) = &mut self.signer {
+            signing.pending = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Signer;
+    use crate::{sha256::SHA256, Level, ZipArchive, ZipArchiveBuilder};
+
+    #[derive(Default)]
+    struct Digest(SHA256, bool);
+
+    impl Signer for Digest {
+        fn update(&mut self, bytes: &[u8]) {
+            self.0.write(bytes);
+        }
+
+        fn finish(&mut self) -> Vec<u8> {
+            self.0.finish().to_vec()
+        }
+
+        fn embed_in_comment(&self) -> bool {
+            self.1
+        }
+    }
+
+    fn sha256(bytes: &[u8]) -> Vec<u8> {
+        let mut hasher = SHA256::default();
+        hasher.write(bytes);
+        hasher.finish().to_vec()
+    }
+
+    #[test]
+    fn it_works() {
+        let mut buf = Vec::new();
+        let summary = ZipArchive::new(&mut buf)
+            .signer(Digest::default())
+            .add_entry("file1.txt", b"content", Level::Default)
+            .unwrap()
+            .add_entry("file2.txt", b"content", Level::Raw)
+            .unwrap()
+            .finish()
+            .unwrap();
+        assert_eq!(summary.signature, Some(sha256(&buf)));
+
+        let mut buf = Vec::new();
+        let summary = ZipArchiveBuilder::new()
+            .comment("replaced")
+            .build(&mut buf)
+            .signer(Digest(SHA256::default(), true))
+            .add_entry("file.txt", b"content", Level::Default)
+            .unwrap()
+            .finish()
+            .unwrap();
+        let signature = summary.signature.unwrap();
+        let (signed, comment) = buf.split_at(buf.len() - 32);
+        assert_eq!(comment, signature.as_slice());
+        assert_eq!(&signed[signed.len() - 2..], &32u16.to_le_bytes());
+        assert_eq!(signature, sha256(&signed[..signed.len() - 2]));
+    }
+}
@@ -0,0 +1,91 @@
+use std::fs::File;
+use std::io;
+use std::io::Read;
+use std::path::Path;
+
+/// Read a whole file, skipping holes of sparse files where the system can find them.
+///
+/// Holes are left as zeros of the allocated buffer without reading, so large
+/// sparse files like disk images are read only as much as they have data.
+pub(crate) fn read(path: &Path) -> io::Result<Vec<u8>> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len() as usize;
+    match read_data(&file, len)? {
+        Some(content) => Ok(content),
+        None => {
+            let mut content = Vec::with_capacity(len);
+            file.read_to_end(&mut content)?;
+            Ok(content)
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn read_data(file: &File, len: usize) -> io::Result<Option<Vec<u8>>> {
+    use std::os::unix::fs::FileExt;
+    use std::os::unix::io::AsRawFd;
+
+    const SEEK_DATA: i32 = 3;
+    const SEEK_HOLE: i32 = 4;
+    extern "C" {
+        fn lseek64(fd: i32, offset: i64, whence: i32) -> i64;
+    }
+    let seek = |offset: usize, whence: i32| -> io::Result<Option<usize>> {
+        // SAFETY: lseek64 only moves the file offset of the open descriptor.
+        match unsafe { lseek64(file.as_raw_fd(), offset as i64, whence) } {
+            -1 => match io::Error::last_os_error() {
+                // No data after the offset.
+                error if error.raw_os_error() == Some(6) => Ok(None),
+                error => Err(error),
+            },
+            position => Ok(Some(position as usize)),
+        }
+    };
+    let mut content = vec![0; len];
+    let mut position = 0;
+    while position < len {
+        let data = match seek(position, SEEK_DATA) {
+            Ok(Some(data)) if data < len => data,
+            Ok(_) => break,
+            // The file system does not support finding holes.
+            Err(error) if error.kind() == io::ErrorKind::InvalidInput => return Ok(None),
+            Err(error) => return Err(error),
+        };
+        let hole = seek(data, SEEK_HOLE)?.unwrap_or(len).min(len);
+        file.read_exact_at(&mut content[data..hole], data as u64)?;
+        position = hole;
+    }
+    Ok(Some(content))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_data(_file: &File, _len: usize) -> io::Result<Option<Vec<u8>>> {
+    Ok(None)
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs::OpenOptions;
+    use std::io::{Seek, SeekFrom, Write};
+
+    #[test]
+    fn it_works() {
+        let path = std::env::temp_dir().join("zip-builder-sparse");
+        let mut file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&path)
+            .unwrap();
+        file.write_all(b"head").unwrap();
+        file.seek(SeekFrom::Start(1 << 20)).unwrap();
+        file.write_all(b"middle").unwrap();
+        file.set_len(3 << 20).unwrap();
+        drop(file);
+        let content = super::read(&path).unwrap();
+        assert_eq!(content, std::fs::read(&path).unwrap());
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(content.len(), 3 << 20);
+        assert_eq!(&content[(1 << 20)..(1 << 20) + 6], b"middle");
+    }
+}
@@ -0,0 +1,180 @@
+use crate::{ArchiveSummary, EntryOptions, Result, ZipArchive};
+use std::borrow::Cow;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+
+/// Source of an entry given to [`ZipArchive::from_iter`] and [`ZipArchive::add_specs`].
+pub enum EntrySpec<'s> {
+    /// Content in memory, like [`add_entry`](ZipArchive::add_entry).
+    Bytes {
+        name: String,
+        content: Cow<'s, [u8]>,
+        options: EntryOptions,
+    },
+    /// File of the path, like [`add_file`](ZipArchive::add_file).
+    Path {
+        name: String,
+        path: PathBuf,
+        options: EntryOptions,
+    },
+    /// Content read to the end, like [`add_entry_concat`](ZipArchive::add_entry_concat).
+    Reader {
+        name: String,
+        reader: Box<dyn Read + 's>,
+        options: EntryOptions,
+    },
+}
+
+impl<'s> EntrySpec<'s> {
+    /// Entry of `content` with the default options.
+    pub fn bytes<C: Into<Cow<'s, [u8]>>>(name: &str, content: C) -> EntrySpec<'s> {
+        EntrySpec::Bytes {
+            name: name.to_string(),
+            content: content.into(),
+            options: EntryOptions::default(),
+        }
+    }
+
+    /// Entry of the file of `path` with the default options.
+    pub fn path<P: Into<PathBuf>>(name: &str, path: P) -> EntrySpec<'s> {
+        EntrySpec::Path {
+            name: name.to_string(),
+            path: path.into(),
+            options: EntryOptions::default(),
+        }
+    }
+
+    /// Entry of the content of `reader` with the default options.
+    pub fn reader<R: Read + 's>(name: &str, reader: R) -> EntrySpec<'s> {
+        EntrySpec::Reader {
+            name: name.to_string(),
+            reader: Box::new(reader),
+            options: EntryOptions::default(),
+        }
+    }
+
+    /// Set the options of the entry.
+    pub fn options<O: Into<EntryOptions>>(mut self, options: O) -> Self {
+        match &mut self {
+            EntrySpec::Bytes { options: old, .. }
+            | EntrySpec::Path { options: old, .. }
+            | EntrySpec::Reader { options: old, .. } => *old = options.into(),
+        }
+        self
+    }
+
+    /// Name of the entry.
+    pub fn name(&self) -> &str {
+        match self {
+            EntrySpec::Bytes { name, .. }
+            | EntrySpec::Path { name, .. }
+            | EntrySpec::Reader { name, .. } => name,
+        }
+    }
+}
+
+impl<'a, T: Write + ?Sized + 'a> ZipArchive<'a, T> {
+    /// Write an archive of the entries of `specs` to `output` and finish it.
+    ///
+    /// ```
+    /// # use zip_builder::{EntrySpec, Level, ZipArchive};
+    /// let mut zip = Vec::new();
+    /// let summary = ZipArchive::from_iter(
+    ///     &mut zip,
+    ///     vec![
+    ///         EntrySpec::bytes("a.txt", &b"content"[..]),
+    ///         EntrySpec::reader("b.txt", &b"read"[..]).options(Level::Raw),
+    ///     ],
+    /// )?;
+    /// assert_eq!(summary.entries.len(), 2);
+    /// # Ok::<(), zip_builder::Error>(())
+    /// ```
+    pub fn from_iter<'s, I>(output: &'a mut T, specs: I) -> Result<ArchiveSummary>
+    where
+        I: IntoIterator<Item = EntrySpec<'s>>,
+    {
+        ZipArchive::new(output).add_specs(specs)?.finish()
+    }
+
+    /// Add the entries of `specs` in order.
+    ///
+    /// Use [`ZipArchiveBuilder`](crate::ZipArchiveBuilder) to build the archive with options
+    /// before adding them.
+    pub fn add_specs<'s, I>(mut self, specs: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = EntrySpec<'s>>,
+    {
+        let result = self.push_specs(specs);
+        self.or_poison(result)
+    }
+
+    fn push_specs<'s, I>(&mut self, specs: I) -> Result<()>
+    where
+        I: IntoIterator<Item = EntrySpec<'s>>,
+    {
+        for spec in specs {
+            match spec {
+                EntrySpec::Bytes {
+                    name,
+                    content,
+                    options,
+                } => self.push_entry(&name, &content, options)?,
+                EntrySpec::Path {
+                    name,
+                    path,
+                    options,
+                } => self.push_file(&name, &path, options)?,
+                EntrySpec::Reader {
+                    name,
+                    reader,
+                    options,
+                } => self.push_entry_concat(&name, Some(reader), options)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::EntrySpec;
+    use crate::verify::read_entries;
+    use crate::{verify, Level, ZipArchive, ZipArchiveBuilder};
+
+    #[test]
+    fn it_works() {
+        let path = std::env::temp_dir().join(format!("zip-builder-spec-{}", std::process::id()));
+        std::fs::write(&path, b"file").unwrap();
+        let specs = vec![
+            EntrySpec::bytes("bytes.txt", b"bytes".to_vec()).options(Level::Raw),
+            EntrySpec::path("path.txt", &path),
+            EntrySpec::reader("reader.txt", &b"reader"[..]),
+        ];
+        assert_eq!(specs[1].name(), "path.txt");
+        let mut buf = Vec::new();
+        let summary = ZipArchive::from_iter(&mut buf, specs).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+        assert_eq!(summary.entries[0].compressed_size, 5);
+        let contents: Vec<_> = read_entries(&buf)
+            .unwrap()
+            .into_iter()
+            .map(|(header, content)| (String::from_utf8(header.filename).unwrap(), content))
+            .collect();
+        assert_eq!(
+            contents,
+            [
+                ("bytes.txt".to_string(), b"bytes".to_vec()),
+                ("path.txt".to_string(), b"file".to_vec()),
+                ("reader.txt".to_string(), b"reader".to_vec()),
+            ]
+        );
+
+        let mut buf = Vec::new();
+        let missing = EntrySpec::path("missing.txt", "/nonexistent/zip-builder");
+        assert!(ZipArchiveBuilder::new()
+            .build(&mut buf)
+            .add_specs(vec![EntrySpec::bytes("a.txt", &b"a"[..]), missing])
+            .is_err());
+    }
+}
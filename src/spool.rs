@@ -0,0 +1,199 @@
+use crate::{advance, EntryOptions, Metadata, Result, Signed, Stage, ZipArchive, ZipEntry};
+use std::fs::{File, OpenOptions};
+use std::io::{Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Counter making names of temporary files unique in the process.
+static SPOOLED: AtomicU64 = AtomicU64::new(0);
+
+/// Temporary file removed when dropped.
+pub(crate) struct TempFile {
+    /// Empty after the file is persisted.
+    path: PathBuf,
+    pub(crate) file: File,
+    pub(crate) len: u64,
+}
+
+impl TempFile {
+    pub(crate) fn create(dir: &Path) -> std::io::Result<TempFile> {
+        loop {
+            let path = dir.join(format!(
+                ".zip-builder-{}-{}.tmp",
+                std::process::id(),
+                SPOOLED.fetch_add(1, Ordering::Relaxed)
+            ));
+            match OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create_new(true)
+                .open(&path)
+            {
+                Ok(file) => return Ok(TempFile { path, file, len: 0 }),
+                Err(error) if error.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                Err(error) => return Err(error),
+            }
+        }
+    }
+}
+
+impl TempFile {
+    /// Rename the file to `path` instead of removing it.
+    pub(crate) fn persist(mut self, path: &Path) -> std::io::Result<()> {
+        std::fs::rename(&self.path, path)?;
+        self.path = PathBuf::new();
+        Ok(())
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        if !self.path.as_os_str().is_empty() {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// Compressed data in memory, or in a temporary file after it exceeds the threshold.
+pub(crate) enum Spooled {
+    Memory(Option<Vec<u8>>),
+    File(TempFile),
+}
+
+/// Writer keeping bytes in memory up to `threshold` and moving them to a file beyond it.
+struct Spool<'p> {
+    threshold: usize,
+    dir: &'p Path,
+    memory: Vec<u8>,
+    file: Option<TempFile>,
+}
+
+impl Write for Spool<'_> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.file.is_none() && self.memory.len() + buf.len() > self.threshold {
+            let mut file = TempFile::create(self.dir)?;
+            file.file.write_all(&self.memory)?;
+            file.len = self.memory.len() as u64;
+            self.memory = Vec::new();
+            self.file = Some(file);
+        }
+        match &mut self.file {
+            Some(file) => {
+                let written = file.file.write(buf)?;
+                file.len += written as u64;
+                Ok(written)
+            }
+            None => {
+                self.memory.extend_from_slice(buf);
+                Ok(buf.len())
+            }
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match &mut self.file {
+            Some(file) => file.file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<'a, T: Write + ?Sized + 'a> ZipArchive<'a, T> {
+    /// Compress the content, spooling the data to a temporary file if it is large.
+    pub(crate) fn spool(&self, options: &EntryOptions, content: &[u8]) -> Result<Spooled> {
+        let slice = self.config.time_slice.as_ref();
+        let (threshold, dir) = match &self.config.spool {
+            Some((threshold, dir)) => (*threshold, dir),
+            None => return Ok(Spooled::Memory(options.compress(content, slice)?)),
+        };
+        #[cfg(feature = "aes")]
+        if options.encrypted() {
+            return Ok(Spooled::Memory(options.compress(content, slice)?));
+        }
+        if options.placement().is_some() {
+            return Ok(Spooled::Memory(options.compress(content, slice)?));
+        }
+        let spool = Spool {
+            threshold,
+            dir,
+            memory: Vec::new(),
+            file: None,
+        };
+        Ok(match options.compress_to(spool, content, slice)? {
+            Some(Spool {
+                file: Some(file), ..
+            }) => Spooled::File(file),
+            Some(spool) => Spooled::Memory(Some(spool.memory)),
+            None => Spooled::Memory(None),
+        })
+    }
+
+    /// Write the entry with the compressed data in the temporary file.
+    pub(crate) fn write_spooled(
+        &mut self,
+        name: &str,
+        content: &[u8],
+        options: EntryOptions,
+        metadata: &Metadata,
+        digest: Option<[u8; 32]>,
+        mut spooled: TempFile,
+    ) -> Result<()> {
+        let mut entry = ZipEntry::new(name, content, &[], &options, self.offset);
+        entry.compressed_size = spooled.len;
+        entry.sha256 = digest;
+        metadata.apply(&mut entry);
+        let threshold = self.config.zip64_threshold;
+        let mut output = Signed::new(self.output, &mut self.batch, &mut self.signer);
+        let header = Self::pk0304(&mut output, &entry, &[], threshold)
+            .map_err(|error| error.in_entry(name, Stage::LocalHeader))?;
+        advance(&mut self.offset, header)?;
+        entry.data_offset = self.offset;
+        let data = |error: std::io::Error| crate::Error::from(error).in_entry(name, Stage::Data);
+        spooled.file.seek(SeekFrom::Start(0)).map_err(data)?;
+        let copied = std::io::copy(&mut spooled.file, &mut output).map_err(data)?;
+        advance(&mut self.offset, copied)?;
+        let descriptor = Self::pk0708(&mut output, &entry, threshold)
+            .map_err(|error| error.in_entry(name, Stage::Data))?;
+        advance(&mut self.offset, descriptor)?;
+        self.entries.push(entry);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{verify, Level, ZipArchiveBuilder};
+
+    #[test]
+    fn it_works() {
+        let dir = std::env::temp_dir().join(format!("zip-builder-spool-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let noise: Vec<u8> = (0..10000u32)
+            .scan(1u32, |state, _| {
+                *state = state.wrapping_mul(1103515245).wrapping_add(12345);
+                Some((*state >> 24) as u8)
+            })
+            .collect();
+        let mut buf = Vec::new();
+        let summary = ZipArchiveBuilder::new()
+            .spool(1000, &dir)
+            .memory_budget(100)
+            .build(&mut buf)
+            .add_entry("small.txt", &[b'a'; 1000], Level::Default)
+            .unwrap()
+            .add_entry("large.bin", &noise, Level::Default)
+            .unwrap()
+            .finish()
+            .unwrap();
+        let flags = |index: usize| {
+            let offset = summary.entries[index].offset as usize;
+            u16::from_le_bytes([buf[offset + 6], buf[offset + 7]])
+        };
+        assert_eq!(flags(0) & 8, 0);
+        assert_eq!(flags(1) & 8, 0);
+        assert!(summary.entries[1].compressed_size > 1000);
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+        std::fs::remove_dir(&dir).unwrap();
+    }
+}
@@ -0,0 +1,111 @@
+use crate::{
+    advance, CountingWriter, EntryOptions, Metadata, Result, Signed, Stage, ZipArchive, ZipEntry,
+};
+use std::io::Write;
+
+impl<'a, T: Write + ?Sized + 'a> ZipArchive<'a, T> {
+    /// Whether the entry is compressed directly to the output by the memory budget.
+    pub(crate) fn should_stream(&self, options: &EntryOptions, size: usize) -> bool {
+        if self.config.spool.is_some()
+            || options.placement().is_some()
+            || self
+                .config
+                .memory_budget
+                .is_none_or(|budget| size <= budget)
+        {
+            return false;
+        }
+        #[cfg(feature = "aes")]
+        if options.encrypted() {
+            return false;
+        }
+        // Zip64 of local file header must be decided by the uncompressed size alone,
+        // or the bound of the compressed size.
+        let size = size as u64;
+        let bound = size + (size >> 12) + 64;
+        let threshold = self.config.zip64_threshold;
+        options.method() != 0 && (size >= threshold || bound < threshold)
+    }
+
+    /// Write the entry with data descriptor compressing it directly to the output.
+    pub(crate) fn write_streamed(
+        &mut self,
+        name: &str,
+        content: &[u8],
+        options: EntryOptions,
+        metadata: &Metadata,
+        digest: Option<[u8; 32]>,
+    ) -> Result<()> {
+        let options = options.data_descriptor(true);
+        let mut entry = ZipEntry::new(name, content, &[], &options, self.offset);
+        entry.sha256 = digest;
+        metadata.apply(&mut entry);
+        let threshold = self.config.zip64_threshold;
+        let mut output = Signed::new(self.output, &mut self.batch, &mut self.signer);
+        let header = Self::pk0304(&mut output, &entry, &[], threshold)
+            .map_err(|error| error.in_entry(name, Stage::LocalHeader))?;
+        advance(&mut self.offset, header)?;
+        entry.data_offset = self.offset;
+        let counted = options
+            .compress_to(
+                CountingWriter::new(&mut output),
+                content,
+                self.config.time_slice.as_ref(),
+            )
+            .map_err(|error| error.in_entry(name, Stage::Data))?
+            .expect("stored entries are not streamed");
+        entry.compressed_size = counted.count();
+        advance(&mut self.offset, entry.compressed_size)?;
+        let descriptor = Self::pk0708(&mut output, &entry, threshold)
+            .map_err(|error| error.in_entry(name, Stage::Data))?;
+        advance(&mut self.offset, descriptor)?;
+        if entry.uncompressed_size > 0 {
+            self.poor_compression(name, content.len(), entry.compressed_size as usize);
+        }
+        self.entries.push(entry);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{verify, Level, ZipArchiveBuilder};
+
+    #[test]
+    fn it_works() {
+        let mut buf = Vec::new();
+        let summary = ZipArchiveBuilder::new()
+            .memory_budget(100)
+            .build(&mut buf)
+            .add_entry("small.txt", &[b'a'; 100], Level::Default)
+            .unwrap()
+            .add_entry("large.txt", &[b'b'; 1000], Level::Default)
+            .unwrap()
+            .add_entry("raw.txt", &[b'c'; 1000], Level::Raw)
+            .unwrap()
+            .finish()
+            .unwrap();
+        let flags = |index: usize| {
+            let offset = summary.entries[index].offset as usize;
+            u16::from_le_bytes([buf[offset + 6], buf[offset + 7]])
+        };
+        assert_eq!(flags(0) & 8, 0);
+        assert_eq!(flags(1) & 8, 8);
+        assert_eq!(flags(2) & 8, 0);
+        assert!(summary.entries[1].compressed_size < 100);
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+
+        let mut buf = Vec::new();
+        ZipArchiveBuilder::new()
+            .memory_budget(0)
+            .zip64_threshold(500)
+            .build(&mut buf)
+            .add_entry("zip64.txt", &[b'a'; 1000], Level::Default)
+            .unwrap()
+            .add_entry("window.txt", &[b'b'; 490], Level::Default)
+            .unwrap()
+            .finish()
+            .unwrap();
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+    }
+}
@@ -0,0 +1,260 @@
+use crate::{DosAttributes, EntryOptions, Error, Level, Metadata, Result, Stage, ZipArchive};
+use std::io::{ErrorKind, Read, Write};
+use std::time::{Duration, UNIX_EPOCH};
+
+impl<'a, T: Write + ?Sized + 'a> ZipArchive<'a, T> {
+    /// Add entries of a tar stream, such as an artifact of CI, in the order of the tar.
+    ///
+    /// Modification times and Unix permissions of the tar are stored as
+    /// [`MetadataPolicy::Full`](crate::MetadataPolicy::Full) stores them of files.
+    /// Directories and files are added and symbolic links are added as Unix symbolic links,
+    /// other kinds like devices are skipped. Names which are not UTF-8 are replaced.
+    /// Hard links share the data of the entry of their target, and fail with
+    /// [`Error::Entry`] if the target is not written before, such as deferred by
+    /// [`place_first`](crate::ZipArchiveBuilder::place_first).
+    ///
+    /// The tar is read entry by entry. Files larger than
+    /// [`memory_budget`](crate::ZipArchiveBuilder::memory_budget) are written like
+    /// [`start_entry`](ZipArchive::start_entry), without reading them into memory.
+    ///
+    /// ```
+    /// # use zip_builder::{Level, ZipArchive};
+    /// let mut tar = tar::Builder::new(Vec::new());
+    /// let mut header = tar::Header::new_gnu();
+    /// header.set_size(5);
+    /// header.set_mode(0o644);
+    /// header.set_mtime(1608905123);
+    /// header.set_cksum();
+    /// tar.append_data(&mut header, "hello.txt", &b"hello"[..])?;
+    /// let tar = tar.into_inner()?;
+    ///
+    /// let mut zip = Vec::new();
+    /// ZipArchive::new(&mut zip)
+    ///     .add_tar(tar.as_slice(), Level::Default)?
+    ///     .finish()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn add_tar<R: Read, O: Into<EntryOptions>>(mut self, tar: R, options: O) -> Result<Self> {
+        let result = self.push_tar(tar, options.into());
+        self.or_poison(result)
+    }
+
+    fn push_tar<R: Read>(&mut self, tar: R, options: EntryOptions) -> Result<()> {
+        let mut archive = tar::Archive::new(tar);
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let mut name = String::from_utf8_lossy(&entry.path_bytes()).into_owned();
+            let source = |error: std::io::Error| Error::from(error).in_entry(&name, Stage::Source);
+            let header = entry.header();
+            let kind = header.entry_type();
+            let mode = header.mode().map_err(source)? & 0o7777;
+            // Some writers leave the owner empty.
+            let uid = header.uid().unwrap_or(0) as u32;
+            let gid = header.gid().unwrap_or(0) as u32;
+            let mut metadata = Metadata::default();
+            let mut options = options;
+            if let Ok(mtime) = header.mtime() {
+                let modified = UNIX_EPOCH + Duration::from_secs(mtime);
+                metadata.set_modified(modified);
+                options = options.modified(modified);
+            }
+            let content = if kind.is_dir() {
+                if !name.ends_with('/') {
+                    name.push('/');
+                }
+                metadata.set_unix(0o040000 | mode, uid, gid);
+                options = options
+                    .level(Level::Raw)
+                    .dos_attributes(DosAttributes::DIRECTORY);
+                Vec::new()
+            } else if kind.is_symlink() {
+                metadata.set_unix(0o120000 | mode, uid, gid);
+                options = options.level(Level::Raw);
+                match entry.link_name_bytes() {
+                    Some(target) => target.into_owned(),
+                    None => continue,
+                }
+            } else if kind.is_hard_link() {
+                let target = entry.link_name_bytes();
+                let target = target.as_deref().map(String::from_utf8_lossy);
+                match target.and_then(|target| self.written(&target, options)) {
+                    Some(index) => self.push_shared(&name, index, options)?,
+                    None => {
+                        let error = std::io::Error::new(
+                            ErrorKind::NotFound,
+                            "target of the hard link is not written",
+                        );
+                        return Err(source(error));
+                    }
+                }
+                continue;
+            } else if kind.is_file() || kind.is_contiguous() {
+                metadata.set_unix(0o100000 | mode, uid, gid);
+                let size = entry.size();
+                if self
+                    .config
+                    .memory_budget
+                    .is_some_and(|budget| size > budget as u64)
+                {
+                    if self.overdue(&name) {
+                        continue;
+                    }
+                    let mut writer = self.start_entry_with(&name, options, &metadata)?;
+                    let mut buf = vec![0; 1 << 16];
+                    let mut read = 0;
+                    loop {
+                        let len = match entry.read(&mut buf) {
+                            Ok(0) => break,
+                            Ok(len) => len,
+                            Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+                            Err(error) => return Err(source(error)),
+                        };
+                        read += len as u64;
+                        writer
+                            .write_all(&buf[..len])
+                            .map_err(|error| Error::from(error).in_entry(&name, Stage::Data))?;
+                    }
+                    if read != size {
+                        return Err(source(ErrorKind::UnexpectedEof.into()));
+                    }
+                    writer.finish()?;
+                    continue;
+                }
+                // The size in the header is not trusted.
+                let mut content = Vec::with_capacity(size.min(1 << 20) as usize);
+                entry.read_to_end(&mut content).map_err(source)?;
+                if content.len() as u64 != size {
+                    return Err(source(ErrorKind::UnexpectedEof.into()));
+                }
+                content
+            } else {
+                continue;
+            };
+            self.push_entry_with(&name, &content, options, &metadata)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::verify::read_entries;
+    use crate::{verify, Error, Level, Stage, ZipArchive, ZipArchiveBuilder};
+
+    fn header(kind: tar::EntryType, size: u64, mode: u32) -> tar::Header {
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(kind);
+        header.set_size(size);
+        header.set_mode(mode);
+        header.set_mtime(1608905123);
+        header.set_cksum();
+        header
+    }
+
+    #[test]
+    fn it_works() {
+        let mut tar = tar::Builder::new(Vec::new());
+        let mut dir = header(tar::EntryType::Directory, 0, 0o755);
+        tar.append_data(&mut dir, "dir", &[][..]).unwrap();
+        let mut file = header(tar::EntryType::Regular, 7, 0o600);
+        tar.append_data(&mut file, "dir/file.txt", &b"content"[..])
+            .unwrap();
+        let mut link = header(tar::EntryType::Symlink, 0, 0o777);
+        tar.append_link(&mut link, "dir/link", "file.txt").unwrap();
+        let mut hard = header(tar::EntryType::Link, 0, 0o600);
+        tar.append_link(&mut hard, "dir/hard.txt", "dir/file.txt")
+            .unwrap();
+        let mut fifo = header(tar::EntryType::Fifo, 0, 0o644);
+        tar.append_data(&mut fifo, "fifo", &[][..]).unwrap();
+        let tar = tar.into_inner().unwrap();
+
+        let mut buf = Vec::new();
+        let summary = ZipArchive::new(&mut buf)
+            .add_tar(tar.as_slice(), Level::Default)
+            .unwrap()
+            .finish()
+            .unwrap();
+        let names: Vec<_> = summary
+            .entries
+            .iter()
+            .map(|entry| &entry.name[..])
+            .collect();
+        assert_eq!(names, ["dir/", "dir/file.txt", "dir/link", "dir/hard.txt"]);
+        assert_eq!(summary.entries[2].uncompressed_size, 8);
+        assert_eq!(summary.entries[3].offset, summary.entries[1].offset);
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+        // DOS time of 2020-12-25 14:05:23 UTC.
+        assert_eq!(&buf[10..14], &1369010347u32.to_le_bytes());
+        let attributes = |name: &[u8]| {
+            let position = buf
+                .windows(4 + 42 + name.len())
+                .position(|window| window.starts_with(b"PK\x01\x02") && window.ends_with(name))
+                .unwrap();
+            let offset = position + 38;
+            u32::from_le_bytes([
+                buf[offset],
+                buf[offset + 1],
+                buf[offset + 2],
+                buf[offset + 3],
+            ])
+        };
+        assert_eq!(attributes(b"dir/") >> 16, 0o040755);
+        assert_eq!(attributes(b"dir/file.txt") >> 16, 0o100600);
+        assert_eq!(attributes(b"dir/link") >> 16, 0o120777);
+    }
+
+    #[test]
+    fn large() {
+        let mut tar = tar::Builder::new(Vec::new());
+        let mut file = header(tar::EntryType::Regular, 1000, 0o600);
+        tar.append_data(&mut file, "large.txt", &[b'l'; 1000][..])
+            .unwrap();
+        let tar = tar.into_inner().unwrap();
+        let mut buf = Vec::new();
+        let summary = ZipArchiveBuilder::new()
+            .memory_budget(100)
+            .build(&mut buf)
+            .add_tar(tar.as_slice(), Level::Default)
+            .unwrap()
+            .finish()
+            .unwrap();
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+        assert_eq!(summary.entries[0].uncompressed_size, 1000);
+        assert_eq!(read_entries(&buf).unwrap()[0].1, [b'l'; 1000]);
+        // Data descriptor follows the data.
+        assert_eq!(buf[6] & 8, 8);
+
+        // The size in the header is larger than the tar.
+        let mut tar = header(tar::EntryType::Regular, 1 << 46, 0o600)
+            .as_bytes()
+            .to_vec();
+        tar.extend_from_slice(&[b'x'; 1024]);
+        let mut buf = Vec::new();
+        let result = ZipArchive::new(&mut buf).add_tar(tar.as_slice(), Level::Default);
+        assert!(matches!(
+            result,
+            Err(Error::Entry {
+                stage: Stage::Source,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn missing_hard_link() {
+        let mut tar = tar::Builder::new(Vec::new());
+        let mut hard = header(tar::EntryType::Link, 0, 0o600);
+        tar.append_link(&mut hard, "hard.txt", "missing.txt")
+            .unwrap();
+        let tar = tar.into_inner().unwrap();
+        let mut buf = Vec::new();
+        let result = ZipArchive::new(&mut buf).add_tar(tar.as_slice(), Level::Default);
+        assert!(matches!(
+            result,
+            Err(Error::Entry {
+                stage: Stage::Source,
+                ..
+            })
+        ));
+    }
+}
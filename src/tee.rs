@@ -0,0 +1,90 @@
+use std::io::{Error, Result, Write};
+
+/// Writer which writes the same data to multiple sinks.
+///
+/// Each write is written entirely to every sink in order.
+/// Once a sink fails, the error names the index of the sink and
+/// all following writes fail, so the sinks never silently diverge.
+///
+/// ```rust
+/// use zip_builder::{Level, Tee, ZipArchive};
+///
+/// let mut tee = Tee::new(vec![Vec::new(), Vec::new()]);
+/// ZipArchive::new(&mut tee)
+///     .add_entry("file.txt", b"content", Level::Default)?
+///     .finish()?;
+/// let sinks = tee.into_inner();
+/// assert_eq!(sinks[0], sinks[1]);
+/// # Ok::<(), zip_builder::Error>(())
+/// ```
+pub struct Tee<W: Write> {
+    sinks: Vec<W>,
+    failed: Option<usize>,
+}
+
+impl<W: Write> Tee<W> {
+    /// Create a writer over `sinks`.
+    pub fn new(sinks: Vec<W>) -> Tee<W> {
+        Tee {
+            sinks,
+            failed: None,
+        }
+    }
+
+    /// Index of the sink which failed, if any.
+    pub fn failed(&self) -> Option<usize> {
+        self.failed
+    }
+
+    /// Unwrap the sinks.
+    pub fn into_inner(self) -> Vec<W> {
+        self.sinks
+    }
+
+    fn each<F: FnMut(&mut W) -> Result<()>>(&mut self, mut f: F) -> Result<()> {
+        if let Some(index) = self.failed {
+            return Err(Error::other(format!("sink {} has failed before", index)));
+        }
+        for (index, sink) in self.sinks.iter_mut().enumerate() {
+            if let Err(error) = f(sink) {
+                self.failed = Some(index);
+                return Err(Error::new(
+                    error.kind(),
+                    format!("sink {}: {}", index, error),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for Tee<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.each(|sink| sink.write_all(buf))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.each(|sink| sink.flush())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Tee;
+    use std::io::{Cursor, Write};
+
+    #[test]
+    fn it_works() {
+        let mut small = [0u8; 4];
+        let mut tee: Tee<Box<dyn Write>> = Tee::new(vec![
+            Box::new(Vec::new()),
+            Box::new(Cursor::new(&mut small[..])),
+        ]);
+        tee.write_all(b"abcd").unwrap();
+        let error = tee.write_all(b"e").unwrap_err();
+        assert_eq!(error.to_string(), "sink 1: failed to write whole buffer");
+        assert_eq!(tee.failed(), Some(1));
+        assert!(tee.write_all(b"f").is_err());
+    }
+}
@@ -0,0 +1,100 @@
+use std::io::{Result, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Writer which limits bandwidth to the inner writer by a token bucket.
+///
+/// Tokens of bytes are refilled at `bytes_per_second` up to `burst`,
+/// and writes sleep until tokens are available.
+///
+/// ```rust
+/// use zip_builder::{Level, RateLimited, ZipArchive};
+///
+/// let mut output = RateLimited::new(Vec::new(), 1 << 20, 64 << 10);
+/// ZipArchive::new(&mut output)
+///     .add_entry("file.txt", b"content", Level::Default)?
+///     .finish()?;
+/// let buf = output.into_inner();
+/// # Ok::<(), zip_builder::Error>(())
+/// ```
+pub struct RateLimited<W: Write> {
+    inner: W,
+    bytes_per_second: u64,
+    burst: u64,
+    tokens: u64,
+    refilled: Instant,
+}
+
+impl<W: Write> RateLimited<W> {
+    /// Create a writer of `bytes_per_second` which allows bursts of `burst` bytes.
+    pub fn new(inner: W, bytes_per_second: u64, burst: u64) -> RateLimited<W> {
+        assert!(bytes_per_second > 0, "rate must be positive");
+        let burst = burst.max(1);
+        RateLimited {
+            inner,
+            bytes_per_second,
+            burst,
+            tokens: burst,
+            refilled: Instant::now(),
+        }
+    }
+
+    /// Unwrap the inner writer.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    fn refill(&mut self) {
+        let elapsed = self.refilled.elapsed();
+        let tokens = elapsed.as_nanos() * self.bytes_per_second as u128 / 1_000_000_000;
+        if tokens > 0 {
+            self.tokens = (self.tokens as u128 + tokens).min(self.burst as u128) as u64;
+            // Keep the remainder which is not enough for a token.
+            let used = tokens * 1_000_000_000 / self.bytes_per_second as u128;
+            self.refilled += Duration::from_nanos(used as u64);
+            if self.tokens == self.burst {
+                self.refilled = Instant::now();
+            }
+        }
+    }
+}
+
+impl<W: Write> Write for RateLimited<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        self.refill();
+        while self.tokens == 0 {
+            // Wait for tokens of the whole write, or the burst if it is shorter.
+            let wanted = (buf.len() as u64).min(self.burst) as u128;
+            let wait = wanted * 1_000_000_000 / self.bytes_per_second as u128 + 1;
+            thread::sleep(Duration::from_nanos(wait as u64));
+            self.refill();
+        }
+        let len = buf.len().min(self.tokens as usize);
+        let written = self.inner.write(&buf[..len])?;
+        self.tokens -= written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::RateLimited;
+    use std::io::Write;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn it_works() {
+        let mut output = RateLimited::new(Vec::new(), 10_000, 1_000);
+        let start = Instant::now();
+        output.write_all(&[0; 3_000]).unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(190));
+        assert_eq!(output.into_inner().len(), 3_000);
+    }
+}
@@ -1,5 +1,9 @@
+use std::convert::TryFrom;
 use std::default::Default;
+use std::fmt::{Display, Formatter};
 use std::iter::Iterator;
+use std::str::FromStr;
+use std::time::Duration;
 use std::time::SystemTime;
 use std::time::UNIX_EPOCH;
 
@@ -7,19 +11,69 @@ struct EpochTime(u64);
 
 impl EpochTime {
     fn new(st: &SystemTime) -> EpochTime {
-        EpochTime(match st.duration_since(UNIX_EPOCH) {
-            Ok(n) => n.as_secs(),
-            Err(_) => 0,
+        EpochTime::rounded(st, TimeRounding::Truncate)
+    }
+
+    fn rounded(st: &SystemTime, rounding: TimeRounding) -> EpochTime {
+        let (seconds, fraction) = match st.duration_since(UNIX_EPOCH) {
+            Ok(n) => (n.as_secs(), n.subsec_nanos() > 0),
+            Err(_) => (0, false),
+        };
+        let odd = seconds % 2 == 1;
+        EpochTime(match rounding {
+            TimeRounding::Truncate => seconds,
+            TimeRounding::HalfEven if odd && !fraction && seconds % 4 == 1 => seconds - 1,
+            TimeRounding::HalfEven if odd => seconds + 1,
+            TimeRounding::HalfEven => seconds,
+            TimeRounding::Ceiling if odd => seconds + 1,
+            TimeRounding::Ceiling if fraction => seconds + 2,
+            TimeRounding::Ceiling => seconds,
         })
     }
 }
 
+/// Rounding of modification time to 2 seconds, the resolution of DOS time.
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum TimeRounding {
+    /// Round down, as MS-DOS does.
+    Truncate,
+    /// Round to the nearest, and odd seconds to multiple of 4 seconds.
+    HalfEven,
+    /// Round up, so that times are never earlier than the source.
+    Ceiling,
+}
+
 impl Default for EpochTime {
     fn default() -> Self {
         EpochTime::new(&SystemTime::now())
     }
 }
 
+/// Handling of modification time after 2107, which DOS time cannot represent.
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum TimeOverflow {
+    /// Record the last of DOS time, 2107-12-31 23:59:58, and the exact time
+    /// in NTFS extra field (0x000A).
+    Clamp,
+    /// Fail with [`Error::TimeOutOfRange`](crate::Error::TimeOutOfRange).
+    Reject,
+}
+
+/// The last year DOS time can represent.
+pub(crate) const LAST_YEAR_OF_DOS_TIME: u16 = 2107;
+
+/// Date and time in UTC, from 1970 to 9999.
+///
+/// It is parsed from a subset of ISO 8601 such as `2024-01-01T00:00:00Z`,
+/// to give deterministic modification time.
+///
+/// ```rust
+/// use zip_builder::DateTime;
+///
+/// let time: DateTime = "2024-01-01T09:00:00+09:00".parse()?;
+/// assert_eq!(time.unix_time(), 1704067200);
+/// # Ok::<(), zip_builder::ParseDateTimeError>(())
+/// ```
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct DateTime {
     year: u16,
@@ -31,12 +85,101 @@ pub struct DateTime {
 }
 
 impl DateTime {
+    /// Current time.
     pub fn now() -> DateTime {
         DateTime::from(&EpochTime::default())
     }
 
+    /// Time of `st` rounded to 2 seconds. Times before 1970 are 1970-01-01T00:00:00Z.
+    pub fn from_system_time_rounded(st: &SystemTime, rounding: TimeRounding) -> DateTime {
+        DateTime::from(&EpochTime::rounded(st, rounding))
+    }
+
+    /// Time of seconds from 1970-01-01T00:00:00Z.
+    pub fn from_unix_time(seconds: u64) -> DateTime {
+        DateTime::from(&EpochTime(seconds))
+    }
+
+    /// Decode DOS time in lower 16 bits and DOS date in upper 16 bits.
+    ///
+    /// Returns `None` if a field is out of range, such as zero written for times before 1980.
+    pub fn from_dos_time(dos_time: u32) -> Option<DateTime> {
+        let time = DateTime {
+            year: (dos_time >> 25) as u16 + 1980,
+            month: (dos_time >> 21 & 0xF) as u8,
+            day: (dos_time >> 16 & 0x1F) as u8,
+            hour: (dos_time >> 11 & 0x1F) as u8,
+            minute: (dos_time >> 5 & 0x3F) as u8,
+            second: (dos_time & 0x1F) as u8 * 2,
+        };
+        if !(1..=12).contains(&time.month)
+            || time.day == 0
+            || time.day as u16 > days_in_months(time.year)[time.month as usize - 1]
+            || time.hour >= 24
+            || time.minute >= 60
+            || time.second >= 60
+        {
+            return None;
+        }
+        Some(time)
+    }
+
+    /// Year.
+    pub fn year(&self) -> u16 {
+        self.year
+    }
+
+    /// Month from 1 to 12.
+    pub fn month(&self) -> u8 {
+        self.month
+    }
+
+    /// Day of the month from 1.
+    pub fn day(&self) -> u8 {
+        self.day
+    }
+
+    /// Hour from 0 to 23.
+    pub fn hour(&self) -> u8 {
+        self.hour
+    }
+
+    /// Minute from 0 to 59.
+    pub fn minute(&self) -> u8 {
+        self.minute
+    }
+
+    /// Second from 0 to 59.
+    pub fn second(&self) -> u8 {
+        self.second
+    }
+
+    /// Seconds from 1970-01-01T00:00:00Z.
+    pub fn unix_time(&self) -> u64 {
+        self.seconds_from_1601() - SECONDS_FROM_1601_TO_EPOCH
+    }
+
+    fn seconds_from_1601(&self) -> u64 {
+        let table = days_in_months(self.year);
+        let days_of_months: u16 = table[..self.month as usize - 1].iter().sum();
+        let years = (self.year - 1601) as u64;
+        let days = years * 365 + years / 4 - years / 100 + years / 400;
+        let days = days + (days_of_months + self.day as u16 - 1) as u64;
+        ((days * 24 + self.hour as u64) * 60 + self.minute as u64) * 60 + self.second as u64
+    }
+
+    /// Time as [`SystemTime`].
+    pub fn system_time(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.unix_time())
+    }
+
+    /// DOS time in lower 16 bits and DOS date in upper 16 bits.
+    ///
+    /// Zero before 1980, and 2107-12-31 23:59:58 after 2107.
     pub fn dos_time(&self) -> u32 {
-        if self.year >= 1980 {
+        if self.year > LAST_YEAR_OF_DOS_TIME {
+            (127 << 25) | (12 << 21) | (31 << 16) | (23 << 11) | (59 << 5) | 29
+        } else if self.year >= 1980 {
             ((self.year - 1980) as u32) << 25
                 | (self.month as u32).wrapping_shl(21)
                 | ((self.day as u32) << 16)
@@ -55,57 +198,191 @@ impl Default for DateTime {
     }
 }
 
-fn year_from_days(days: u64) -> (u16, u16) {
-    if days > 10957 {
-        let mut year = 0;
-        let mut days = days - 10957; // 10957 is days from epoch to millennium
-        for (days_in_a_year, years) in [
-            (400 * 365 + 97, 400),
-            (100 * 365 + 24, 100),
-            (4 * 365 + 1, 4),
-            (365, 1),
-        ]
-        .iter()
-        {
-            year += days / days_in_a_year * years;
-            days %= days_in_a_year;
-        }
-        ((year + 2000) as u16, days as u16)
-    } else {
-        let mut year = 0;
-        let mut days = days + 3653;
-        for (days_in_a_year, years) in [(4 * 365 + 1, 4), (365, 1)].iter() {
-            year += days / days_in_a_year * years;
-            days %= days_in_a_year;
-        }
-        ((year + 1960) as u16, (days + 1) as u16)
+impl From<DateTime> for SystemTime {
+    fn from(time: DateTime) -> SystemTime {
+        time.system_time()
     }
 }
 
-const fn is_leap_year(year: u16) -> bool {
-    (year % 4 == 0) & ((year % 100 != 0) | (year % 400 == 0))
+/// Format as `YYYY-MM-DDThh:mm:ssZ`, which [`FromStr`] parses back.
+impl Display for DateTime {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+            self.year(),
+            self.month(),
+            self.day(),
+            self.hour(),
+            self.minute(),
+            self.second()
+        )
+    }
 }
 
-const DAYS_IN_YEAR: [u16; 12] = [31, 28, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31];
-const DAYS_IN_YEAR_OF_LEAP_YEAR: [u16; 12] = [31, 29, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31];
+/// Error of parsing [`DateTime`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseDateTimeError(());
 
-fn month_from_days(mut days: u16, is_leap: bool) -> (u8, u8) {
-    (if is_leap {
-        DAYS_IN_YEAR
-    } else {
-        DAYS_IN_YEAR_OF_LEAP_YEAR
-    })
-    .iter()
-    .enumerate()
-    .find_map(|(num, cum)| {
-        if *cum > days {
-            Some(((num + 1) as u8, days as u8))
+impl Display for ParseDateTimeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid date and time")
+    }
+}
+
+impl std::error::Error for ParseDateTimeError {}
+
+/// Number of exactly `digits` decimal digits at the beginning of `s`.
+fn number(s: &[u8], digits: usize) -> Option<u32> {
+    let digits = s.get(..digits)?;
+    digits.iter().try_fold(0, |number, &digit| {
+        if digit.is_ascii_digit() {
+            Some(number * 10 + (digit - b'0') as u32)
         } else {
-            days -= cum;
             None
         }
     })
-    .unwrap()
+}
+
+/// Seconds east of UTC by the time zone designator.
+fn time_zone(s: &[u8]) -> Option<i64> {
+    match s {
+        b"" | b"Z" | b"z" => Some(0),
+        [sign @ (b'+' | b'-'), hour @ .., b':', _, _] if hour.len() == 2 => {
+            let hour = number(hour, 2)? as i64;
+            let minute = number(&s[4..], 2)? as i64;
+            if hour >= 24 || minute >= 60 {
+                return None;
+            }
+            let offset = (hour * 60 + minute) * 60;
+            Some(if *sign == b'+' { offset } else { -offset })
+        }
+        _ => None,
+    }
+}
+
+impl FromStr for DateTime {
+    type Err = ParseDateTimeError;
+
+    /// Parse `YYYY-MM-DD`, `YYYY-MM-DDThh:mm:ss` and the latter with fraction of a second
+    /// and time zone designator (`Z` or `±hh:mm`).
+    ///
+    /// Date without time is midnight. Time without time zone is in UTC.
+    /// Fraction of a second is truncated.
+    fn from_str(s: &str) -> Result<DateTime, ParseDateTimeError> {
+        let bytes = s.as_bytes();
+        let parse = || {
+            if bytes.get(4) != Some(&b'-') || bytes.get(7) != Some(&b'-') {
+                return None;
+            }
+            let year = number(bytes, 4)? as u16;
+            let month = number(&bytes[5..], 2)? as u8;
+            let day = number(&bytes[8..], 2)? as u8;
+            let (time, rest) = match bytes.get(10) {
+                None => ((0, 0, 0), &b""[..]),
+                Some(b'T' | b't' | b' ') => {
+                    let time = bytes.get(11..)?;
+                    if time.get(2) != Some(&b':') || time.get(5) != Some(&b':') {
+                        return None;
+                    }
+                    let hour = number(time, 2)? as u8;
+                    let minute = number(&time[3..], 2)? as u8;
+                    let second = number(&time[6..], 2)? as u8;
+                    let mut rest = &time[8..];
+                    if let [b'.' | b',', fraction @ ..] = rest {
+                        let digits = fraction.iter().take_while(|c| c.is_ascii_digit()).count();
+                        if digits == 0 {
+                            return None;
+                        }
+                        rest = &fraction[digits..];
+                    }
+                    ((hour, minute, second), rest)
+                }
+                Some(_) => return None,
+            };
+            let offset = time_zone(rest)?;
+            let (hour, minute, second) = time;
+            let table = days_in_months(year);
+            if year < 1601
+                || !(1..=12).contains(&month)
+                || day == 0
+                || day as u16 > table[month as usize - 1]
+                || hour >= 24
+                || minute >= 60
+                || second >= 60
+            {
+                return None;
+            }
+            let local = DateTime {
+                year,
+                month,
+                day,
+                hour,
+                minute,
+                second,
+            };
+            let seconds = local.seconds_from_1601() as i64 - offset;
+            let seconds = seconds.checked_sub(SECONDS_FROM_1601_TO_EPOCH as i64)?;
+            let time = DateTime::from(&EpochTime(u64::try_from(seconds).ok()?));
+            Some(time).filter(|time| time.year <= 9999)
+        };
+        parse().ok_or(ParseDateTimeError(()))
+    }
+}
+
+/// 1601 is the first year of 400 years before epoch.
+const DAYS_FROM_1601_TO_EPOCH: u64 = 134774;
+const SECONDS_FROM_1601_TO_EPOCH: u64 = DAYS_FROM_1601_TO_EPOCH * 86400;
+
+/// Year and 0-based day of the year.
+fn year_from_days(days: u64) -> (u16, u16) {
+    let mut year = 1601;
+    let mut days = days + DAYS_FROM_1601_TO_EPOCH;
+    for (days_in_years, years, limit) in [
+        (400 * 365 + 97, 400, u64::MAX),
+        (100 * 365 + 24, 100, 3),
+        (4 * 365 + 1, 4, u64::MAX),
+        (365, 1, 3),
+    ]
+    .iter()
+    {
+        // The last day of 100 years and 4 years is in the leap year.
+        let count = (days / days_in_years).min(*limit);
+        year += count * years;
+        days -= count * days_in_years;
+    }
+    (year as u16, days as u16)
+}
+
+const fn is_leap_year(year: u16) -> bool {
+    year.is_multiple_of(4) & (!year.is_multiple_of(100) | year.is_multiple_of(400))
+}
+
+const DAYS_IN_MONTHS: [u16; 12] = [31, 28, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31];
+const DAYS_IN_MONTHS_OF_LEAP_YEAR: [u16; 12] = [31, 29, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31];
+
+const fn days_in_months(year: u16) -> [u16; 12] {
+    if is_leap_year(year) {
+        DAYS_IN_MONTHS_OF_LEAP_YEAR
+    } else {
+        DAYS_IN_MONTHS
+    }
+}
+
+/// Month and day of 0-based day of `year`.
+fn month_from_days(mut days: u16, year: u16) -> (u8, u8) {
+    days_in_months(year)
+        .iter()
+        .enumerate()
+        .find_map(|(num, cum)| {
+            if *cum > days {
+                Some(((num + 1) as u8, (days + 1) as u8))
+            } else {
+                days -= cum;
+                None
+            }
+        })
+        .unwrap()
 }
 
 impl From<&EpochTime> for DateTime {
@@ -117,22 +394,23 @@ impl From<&EpochTime> for DateTime {
         let hour = (rest % 24) as u8;
         let rest = rest / 24;
         let (year, days) = year_from_days(rest);
-        let (month, days) = month_from_days(days, is_leap_year(year));
+        let (month, days) = month_from_days(days, year);
         DateTime {
-            year: year,
-            month: month,
+            year,
+            month,
             day: days,
-            hour: hour,
-            minute: minute,
-            second: second,
+            hour,
+            minute,
+            second,
         }
     }
 }
 
 #[cfg(test)]
 mod test {
-    use super::{DateTime, EpochTime};
+    use super::{DateTime, EpochTime, ParseDateTimeError, TimeRounding};
     use std::convert::From;
+    use std::time::{Duration, UNIX_EPOCH};
 
     fn time_test(dt: &DateTime, et: u64, dos_time: u32) {
         assert_eq!(*dt, DateTime::from(&EpochTime(et)));
@@ -168,14 +446,14 @@ mod test {
         time_test(
             &DateTime {
                 year: 1998,
-                month: 2,
-                day: 1,
+                month: 1,
+                day: 31,
                 hour: 19,
                 minute: 5,
                 second: 2,
             },
             886273502,
-            608278689,
+            608147617,
         );
         time_test(
             &DateTime {
@@ -202,4 +480,165 @@ mod test {
             1369010347,
         );
     }
+
+    fn date_time(year: u16, month: u8, day: u8, hour: u8, minute: u8, second: u8) -> DateTime {
+        DateTime {
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+        }
+    }
+
+    fn rounded(seconds: u64, nanos: u32, rounding: TimeRounding) -> DateTime {
+        let time = UNIX_EPOCH + Duration::new(seconds, nanos);
+        DateTime::from_system_time_rounded(&time, rounding)
+    }
+
+    #[test]
+    fn rounding() {
+        // 2020-12-25 14:05:59
+        let odd = 1608905159;
+        assert_eq!(
+            rounded(odd, 0, TimeRounding::Truncate).dos_time(),
+            date_time(2020, 12, 25, 14, 5, 58).dos_time()
+        );
+        assert_eq!(
+            rounded(odd, 0, TimeRounding::Ceiling),
+            date_time(2020, 12, 25, 14, 6, 0)
+        );
+        assert_eq!(
+            rounded(odd, 0, TimeRounding::HalfEven),
+            date_time(2020, 12, 25, 14, 6, 0)
+        );
+        assert_eq!(
+            rounded(odd - 2, 0, TimeRounding::HalfEven),
+            date_time(2020, 12, 25, 14, 5, 56)
+        );
+        assert_eq!(
+            rounded(odd - 2, 1, TimeRounding::HalfEven),
+            date_time(2020, 12, 25, 14, 5, 58)
+        );
+        assert_eq!(
+            rounded(odd - 1, 500_000_000, TimeRounding::HalfEven),
+            date_time(2020, 12, 25, 14, 5, 58)
+        );
+        assert_eq!(
+            rounded(odd - 1, 1, TimeRounding::Ceiling),
+            date_time(2020, 12, 25, 14, 6, 0)
+        );
+        assert_eq!(
+            rounded(odd - 1, 0, TimeRounding::Ceiling),
+            date_time(2020, 12, 25, 14, 5, 58)
+        );
+
+        // Carries over the end of February.
+        for (seconds, (year, month, day), expected) in [
+            (1582934399, (2020, 2, 28), date_time(2020, 2, 29, 0, 0, 0)),
+            (1583020799, (2020, 2, 29), date_time(2020, 3, 1, 0, 0, 0)),
+            (1614556799, (2021, 2, 28), date_time(2021, 3, 1, 0, 0, 0)),
+            (951782399, (2000, 2, 28), date_time(2000, 2, 29, 0, 0, 0)),
+            (951868799, (2000, 2, 29), date_time(2000, 3, 1, 0, 0, 0)),
+            (825638399, (1996, 2, 29), date_time(1996, 3, 1, 0, 0, 0)),
+            (1609459199, (2020, 12, 31), date_time(2021, 1, 1, 0, 0, 0)),
+        ]
+        .iter()
+        {
+            assert_eq!(
+                rounded(*seconds, 0, TimeRounding::Truncate),
+                date_time(*year, *month, *day, 23, 59, 59)
+            );
+            assert_eq!(rounded(*seconds, 0, TimeRounding::Ceiling), *expected);
+            assert_eq!(rounded(*seconds, 0, TimeRounding::HalfEven), *expected);
+        }
+    }
+
+    #[test]
+    fn parse() {
+        let parse = |s: &str| s.parse::<DateTime>().map(|time| time.unix_time());
+        assert_eq!(parse("2024-01-01T00:00:00Z"), Ok(1704067200));
+        assert_eq!(parse("2024-01-01T00:00:00"), Ok(1704067200));
+        assert_eq!(parse("2024-01-01"), Ok(1704067200));
+        assert_eq!(parse("2024-01-01 09:00:00+09:00"), Ok(1704067200));
+        assert_eq!(parse("2023-12-31T23:30:00.999-00:30"), Ok(1704067200));
+        assert_eq!(parse("2020-12-25T14:05:23Z"), Ok(1608905123));
+        assert_eq!(parse("2000-02-29T12:00:00Z"), Ok(951825600));
+        assert_eq!(parse("1970-01-01T00:00:00Z"), Ok(0));
+        for invalid in [
+            "",
+            "2024-1-01",
+            "2024-01-01T",
+            "2024-01-01T24:00:00Z",
+            "2024-01-01T00:60:00Z",
+            "2024-01-01T00:00:00.Z",
+            "2024-01-01T00:00:00+0900",
+            "2024-13-01",
+            "2023-02-29",
+            "2024-02-30",
+            "1969-12-31T23:59:59Z",
+            "1970-01-01T00:00:00+00:01",
+            "9999-12-31T23:59:59-00:01",
+        ]
+        .iter()
+        {
+            assert_eq!(parse(invalid), Err(ParseDateTimeError(())), "{}", invalid);
+        }
+
+        let time: DateTime = "2020-12-25T14:05:23Z".parse().unwrap();
+        assert_eq!(time.to_string(), "2020-12-25T14:05:23Z");
+        assert_eq!(time.dos_time(), 1369010347);
+        assert_eq!(
+            DateTime::from_system_time_rounded(&time.system_time(), TimeRounding::Truncate),
+            time
+        );
+    }
+
+    #[test]
+    fn every_day_of_dos_time() {
+        let first = "1980-01-01".parse::<DateTime>().unwrap().unix_time();
+        let last = "2107-12-31".parse::<DateTime>().unwrap().unix_time();
+        let mut previous: Option<DateTime> = None;
+        let mut leap_days = 0;
+        for seconds in (first..=last).step_by(86400) {
+            let seconds = seconds + 86399;
+            let time = DateTime::from_unix_time(seconds);
+            assert_eq!(time.unix_time(), seconds);
+            assert_eq!((time.hour(), time.minute(), time.second()), (23, 59, 59));
+            let dos_time = time.dos_time();
+            let decoded = DateTime::from_dos_time(dos_time).unwrap();
+            assert_eq!(decoded.unix_time(), seconds - 1);
+            assert_eq!(decoded.dos_time(), dos_time);
+            if let Some(previous) = previous {
+                let (year, month, day) = (previous.year(), previous.month(), previous.day());
+                let next = if time.day() != 1 {
+                    (year, month, day + 1)
+                } else if time.month() != 1 {
+                    assert_eq!(day as u16, super::days_in_months(year)[month as usize - 1]);
+                    (year, month + 1, 1)
+                } else {
+                    assert_eq!((month, day), (12, 31));
+                    (year + 1, 1, 1)
+                };
+                assert_eq!((time.year(), time.month(), time.day()), next);
+            }
+            if (time.month(), time.day()) == (2, 29) {
+                leap_days += 1;
+            }
+            previous = Some(time);
+        }
+        // 2100 is not a leap year.
+        assert_eq!(leap_days, 31);
+        assert_eq!(DateTime::from_dos_time(0), None);
+        assert_eq!(
+            DateTime::from_dos_time(0x00210000).map(|time| time.unix_time()),
+            Some(315532800)
+        );
+        assert!(DateTime::from_dos_time(0x285D0000).is_some());
+        // 2001-02-29, 24:00 and 60 seconds.
+        for &invalid in [0x2A5D0000, 0x0021C000, 0x0021001E].iter() {
+            assert_eq!(DateTime::from_dos_time(invalid), None);
+        }
+    }
 }
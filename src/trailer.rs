@@ -0,0 +1,94 @@
+use crate::raw::EndOfCentralDirectory;
+use crate::{ArchiveSummary, Result, ZipArchive};
+use std::io::Write;
+
+/// Signature after the length of the trailer at the end of the comment.
+const TRAILER_SIGNATURE: &[u8; 4] = b"ZBTR";
+
+impl<'a, T: Write + ?Sized + 'a> ZipArchive<'a, T> {
+    /// Finish the archive with `trailer` of application metadata, like a build ID,
+    /// at the end of the archive comment.
+    ///
+    /// The comment is followed by the trailer, its length in 2 bytes and `ZBTR`, which must
+    /// fit in 65535 bytes or [`Error::CommentTooLong`](crate::Error::CommentTooLong) is returned.
+    /// [`find_trailer`] locates it in the archive. A signature embedded in the comment
+    /// replaces it like the comment.
+    ///
+    /// ```
+    /// # use zip_builder::{find_trailer, Level, ZipArchive};
+    /// let mut buf = Vec::new();
+    /// ZipArchive::new(&mut buf)
+    ///     .add_entry("a.txt", b"a", Level::Default)?
+    ///     .finish_with_trailer(b"build-1234")?;
+    /// assert_eq!(find_trailer(&buf), Some(&b"build-1234"[..]));
+    /// # Ok::<(), zip_builder::Error>(())
+    /// ```
+    pub fn finish_with_trailer(mut self, trailer: &[u8]) -> Result<ArchiveSummary> {
+        self.check_poisoned()?;
+        let mut framed = trailer.to_vec();
+        framed.extend_from_slice(&(trailer.len().min(u16::MAX as usize) as u16).to_le_bytes());
+        framed.extend_from_slice(TRAILER_SIGNATURE);
+        self.trailer = Some(framed);
+        self.write_ending()
+    }
+
+    /// Comment of the archive followed by the trailer if any.
+    pub(crate) fn comment(&self) -> Vec<u8> {
+        let mut comment = self.config.comment.as_bytes().to_vec();
+        comment.extend_from_slice(self.trailer.as_deref().unwrap_or_default());
+        comment
+    }
+}
+
+/// Locate the trailer written by [`ZipArchive::finish_with_trailer`] in `archive`.
+///
+/// `None` if the archive has no end of central directory record or no trailer.
+pub fn find_trailer(archive: &[u8]) -> Option<&[u8]> {
+    let last = archive.len().checked_sub(22)?;
+    let comment = (last.saturating_sub(u16::MAX as usize)..=last)
+        .rev()
+        .find_map(|position| {
+            let record = EndOfCentralDirectory::from_bytes(&archive[position..])?;
+            Some(record.comment).filter(|_| position + record.size() == archive.len())
+        })?;
+    let rest = comment.strip_suffix(TRAILER_SIGNATURE)?;
+    let (rest, length) = rest.split_at(rest.len().checked_sub(2)?);
+    let length = u16::from_le_bytes([length[0], length[1]]) as usize;
+    rest.get(rest.len().checked_sub(length)?..)
+}
+
+#[cfg(test)]
+mod test {
+    use super::find_trailer;
+    use crate::{verify, Error, Level, ZipArchive, ZipArchiveBuilder};
+
+    #[test]
+    fn it_works() {
+        let mut buf = Vec::new();
+        ZipArchiveBuilder::new()
+            .comment("comment")
+            .build(&mut buf)
+            .add_entry("a.txt", b"a", Level::Default)
+            .unwrap()
+            .finish_with_trailer(b"\x00build\x06\x00ZBTR")
+            .unwrap();
+        assert_eq!(find_trailer(&buf), Some(&b"\x00build\x06\x00ZBTR"[..]));
+        assert!(buf.windows(7).any(|window| window == b"comment"));
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+
+        let mut buf = Vec::new();
+        ZipArchive::new(&mut buf).finish_with_trailer(b"").unwrap();
+        assert_eq!(find_trailer(&buf), Some(&b""[..]));
+
+        let mut buf = Vec::new();
+        ZipArchive::new(&mut buf).finish().unwrap();
+        assert_eq!(find_trailer(&buf), None);
+        assert_eq!(find_trailer(b"short"), None);
+
+        let result = ZipArchive::new(&mut Vec::new()).finish_with_trailer(&[0; 65530]);
+        assert!(matches!(
+            result,
+            Err(Error::CommentTooLong { length: 65536 })
+        ));
+    }
+}
@@ -0,0 +1,61 @@
+use crate::crc32::CRC32;
+use crate::ZipArchive;
+use std::io::Write;
+
+impl<'a, T: Write + ?Sized + 'a> ZipArchive<'a, T> {
+    /// Whether the entry has the same CRC-32 and size as in the previous archive.
+    pub(crate) fn unchanged(&self, name: &str, content: &[u8]) -> bool {
+        if self.config.previous.is_empty() {
+            return false;
+        }
+        match self.config.previous.get(&*self.prefixed(name)) {
+            Some(&(checksum, size)) => {
+                size == content.len() as u64 && checksum == CRC32::checksum(content)
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{verify, Level, ZipArchive, ZipArchiveBuilder};
+
+    #[test]
+    fn it_works() {
+        let mut buf = Vec::new();
+        let previous = ZipArchive::new(&mut buf)
+            .add_entry("same.txt", b"same", Level::Default)
+            .unwrap()
+            .add_entry("changed.txt", b"old", Level::Default)
+            .unwrap()
+            .add_entry("removed.txt", b"removed", Level::Default)
+            .unwrap()
+            .finish()
+            .unwrap();
+        let mut buf = Vec::new();
+        let summary = ZipArchiveBuilder::new()
+            .update_of(
+                previous
+                    .entries
+                    .iter()
+                    .map(|entry| (entry.name.clone(), entry.checksum, entry.uncompressed_size)),
+            )
+            .build(&mut buf)
+            .add_entry("same.txt", b"same", Level::Default)
+            .unwrap()
+            .add_entry("changed.txt", b"new", Level::Default)
+            .unwrap()
+            .add_entries(vec![("added.txt", &b"added"[..], Level::Default)])
+            .unwrap()
+            .finish()
+            .unwrap();
+        let names: Vec<_> = summary
+            .entries
+            .iter()
+            .map(|entry| &entry.name[..])
+            .collect();
+        assert_eq!(names, ["changed.txt", "added.txt"]);
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+    }
+}
@@ -0,0 +1,536 @@
+use crate::crc32::CRC32;
+use crate::raw::{
+    CentralDirectoryHeader, DataDescriptor, EndOfCentralDirectory, Fields, LocalFileHeader,
+    Zip64EndOfCentralDirectory, Zip64EndOfCentralDirectoryLocator,
+    CENTRAL_DIRECTORY_HEADER_SIGNATURE, LOCAL_FILE_HEADER_SIGNATURE,
+    ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE,
+};
+use crate::Result;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::io::Read;
+
+/// Represent inconsistency found by [`verify`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Inconsistency {
+    /// End of central directory record (PK0506) is not found.
+    MissingEndOfCentralDirectory,
+    /// Central directory lies outside of the archive.
+    CentralDirectoryOutOfRange,
+    /// Number of entries recorded in PK0506 differs from the central directory.
+    EntryCountMismatch { recorded: u64, found: u64 },
+    /// Expected record signature is not found at the offset.
+    BadSignature { offset: usize, expected: u32 },
+    /// A field of local file header (PK0304) differs from central directory header (PK0102).
+    HeaderMismatch { name: String, field: &'static str },
+    /// Entry data lies outside of the archive.
+    DataOutOfRange { name: String },
+    /// Compression method is not known by the verifier.
+    UnsupportedMethod { name: String, method: u16 },
+    /// Compressed data can not be decoded.
+    CorruptData { name: String, message: String },
+    /// Decoded size differs from recorded uncompressed size.
+    SizeMismatch {
+        name: String,
+        recorded: u64,
+        actual: u64,
+    },
+    /// CRC-32 of decoded data differs from recorded checksum.
+    ChecksumMismatch {
+        name: String,
+        recorded: u32,
+        actual: u32,
+    },
+}
+
+impl Display for Inconsistency {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingEndOfCentralDirectory => {
+                write!(f, "end of central directory record is not found")
+            }
+            Self::CentralDirectoryOutOfRange => write!(f, "central directory is out of range"),
+            Self::EntryCountMismatch { recorded, found } => write!(
+                f,
+                "{} entries are recorded but {} entries are found",
+                recorded, found
+            ),
+            Self::BadSignature { offset, expected } => {
+                write!(f, "signature {:#010x} is not found at {}", expected, offset)
+            }
+            Self::HeaderMismatch { name, field } => {
+                write!(
+                    f,
+                    "{}: {} differs between local and central header",
+                    name, field
+                )
+            }
+            Self::DataOutOfRange { name } => write!(f, "{}: data is out of range", name),
+            Self::UnsupportedMethod { name, method } => {
+                write!(f, "{}: unsupported compression method {}", name, method)
+            }
+            Self::CorruptData { name, message } => write!(f, "{}: {}", name, message),
+            Self::SizeMismatch {
+                name,
+                recorded,
+                actual,
+            } => write!(f, "{}: size {} is recorded but {}", name, recorded, actual),
+            Self::ChecksumMismatch {
+                name,
+                recorded,
+                actual,
+            } => write!(
+                f,
+                "{}: checksum {:#010x} is recorded but {:#010x}",
+                name, recorded, actual
+            ),
+        }
+    }
+}
+
+pub(crate) struct Header {
+    pub(crate) version_made_by: u16,
+    pub(crate) flags: u16,
+    pub(crate) method: u16,
+    pub(crate) timestamp: u32,
+    pub(crate) checksum: u32,
+    pub(crate) compressed_size: u64,
+    pub(crate) uncompressed_size: u64,
+    pub(crate) external_attributes: u32,
+    pub(crate) filename: Vec<u8>,
+    zip64: bool,
+}
+
+/// Replace fields marked as `0xFFFFFFFF` with values of Zip64 extended information.
+///
+/// Returns whether the extended information is present.
+fn apply_zip64(extra: &[u8], values: &mut [&mut u64]) -> Option<bool> {
+    let mut found = false;
+    let mut fields = Fields::new(extra, 0);
+    while fields.pos < extra.len() {
+        let id = fields.u16()?;
+        let len = fields.u16()? as usize;
+        let data = fields.bytes(len)?;
+        if id == 1 {
+            found = true;
+            let mut data = Fields::new(data, 0);
+            for value in values
+                .iter_mut()
+                .filter(|value| ***value == u32::MAX as u64)
+            {
+                **value = data.u64()?;
+            }
+        }
+    }
+    Some(found)
+}
+
+fn find_end_of_central_directory(buf: &[u8]) -> Option<(usize, EndOfCentralDirectory<'_>)> {
+    let last = buf.len().checked_sub(22)?;
+    let first = last.saturating_sub(0xFFFF);
+    (first..=last)
+        .rev()
+        .find_map(|pos| Some((pos, EndOfCentralDirectory::from_bytes(&buf[pos..])?)))
+}
+
+fn local_header(buf: &[u8], offset: usize) -> Option<(Header, usize)> {
+    let record = LocalFileHeader::from_bytes(buf.get(offset..)?)?;
+    let mut compressed_size = record.compressed_size as u64;
+    let mut uncompressed_size = record.uncompressed_size as u64;
+    let zip64 = apply_zip64(
+        record.extra,
+        &mut [&mut uncompressed_size, &mut compressed_size],
+    )?;
+    let header = Header {
+        version_made_by: 0,
+        flags: record.flags,
+        method: record.method,
+        timestamp: record.timestamp,
+        checksum: record.crc32,
+        compressed_size,
+        uncompressed_size,
+        external_attributes: 0,
+        filename: record.filename.to_vec(),
+        zip64,
+    };
+    Some((header, offset + record.size()))
+}
+
+fn central_header(record: &CentralDirectoryHeader) -> Option<(Header, usize)> {
+    let mut compressed_size = record.compressed_size as u64;
+    let mut uncompressed_size = record.uncompressed_size as u64;
+    let mut offset = record.local_header_offset as u64;
+    let zip64 = apply_zip64(
+        record.extra,
+        &mut [&mut uncompressed_size, &mut compressed_size, &mut offset],
+    )?;
+    let header = Header {
+        version_made_by: record.version_made_by,
+        flags: record.flags,
+        method: record.method,
+        timestamp: record.timestamp,
+        checksum: record.crc32,
+        compressed_size,
+        uncompressed_size,
+        external_attributes: record.external_attributes,
+        filename: record.filename.to_vec(),
+        zip64,
+    };
+    Some((header, offset as usize))
+}
+
+#[cfg_attr(not(feature = "ppmd"), allow(unused_variables))]
+pub(crate) fn decode(
+    method: u16,
+    data: &[u8],
+    size: u64,
+) -> std::result::Result<Option<Vec<u8>>, String> {
+    match method {
+        0 => Ok(Some(data.to_vec())),
+        8 => inflate::inflate_bytes(data).map(Some),
+        #[cfg(feature = "ppmd")]
+        98 if data.len() >= 2 => {
+            let parameter = u16::from_le_bytes([data[0], data[1]]);
+            let order = (parameter & 0xF) as u32 + 1;
+            let memory = ((parameter >> 4 & 0xFF) as u32 + 1) << 20;
+            let restore = ppmd_rust::RestoreMethod::from(parameter >> 12);
+            let decoder = ppmd_rust::Ppmd8Decoder::new(&data[2..], order, memory, restore)
+                .map_err(|error| format!("{:?}", error))?;
+            // The size is not trusted, so the buffer grows as the content is decoded.
+            let mut content = Vec::new();
+            decoder
+                .take(size)
+                .read_to_end(&mut content)
+                .map_err(|error| error.to_string())?;
+            if (content.len() as u64) < size {
+                return Err("unexpected end of PPMd data".to_string());
+            }
+            Ok(Some(content))
+        }
+        _ => Ok(None),
+    }
+}
+
+fn verify_entry(
+    buf: &[u8],
+    central: &Header,
+    offset: usize,
+    shared: bool,
+    problems: &mut Vec<Inconsistency>,
+) {
+    let name = String::from_utf8_lossy(&central.filename).into_owned();
+    let signature = offset.checked_add(4).and_then(|end| buf.get(offset..end));
+    if signature != Some(&LOCAL_FILE_HEADER_SIGNATURE.to_le_bytes()[..]) {
+        problems.push(Inconsistency::BadSignature {
+            offset,
+            expected: LOCAL_FILE_HEADER_SIGNATURE,
+        });
+        return;
+    }
+    let (mut local, data_offset) = match local_header(buf, offset) {
+        Some(header) => header,
+        None => {
+            problems.push(Inconsistency::DataOutOfRange { name });
+            return;
+        }
+    };
+    let data_end = data_offset.checked_add(central.compressed_size as usize);
+    if local.flags & 8 != 0 {
+        // CRC and sizes follow the data in data descriptor.
+        let descriptor = data_end
+            .and_then(|data_end| buf.get(data_end..))
+            .and_then(|bytes| DataDescriptor::from_bytes(bytes, local.zip64));
+        match descriptor {
+            Some(descriptor) => {
+                local.checksum = descriptor.crc32;
+                local.compressed_size = descriptor.compressed_size;
+                local.uncompressed_size = descriptor.uncompressed_size;
+            }
+            None => {
+                problems.push(Inconsistency::DataOutOfRange { name });
+                return;
+            }
+        }
+    }
+    // Deduplicated entries may differ in the language encoding flag of the name.
+    let flags = if shared { !2048 } else { !0 };
+    for (field, same) in [
+        ("flags", (local.flags ^ central.flags) & flags == 0),
+        ("method", local.method == central.method),
+        ("timestamp", local.timestamp == central.timestamp),
+        ("checksum", local.checksum == central.checksum),
+        (
+            "compressed size",
+            local.compressed_size == central.compressed_size,
+        ),
+        (
+            "uncompressed size",
+            local.uncompressed_size == central.uncompressed_size,
+        ),
+        // Deduplicated entries share a local header of another name.
+        ("filename", shared || local.filename == central.filename),
+    ]
+    .iter()
+    {
+        if !same {
+            problems.push(Inconsistency::HeaderMismatch {
+                name: name.clone(),
+                field,
+            });
+        }
+    }
+    let data = match data_end.and_then(|data_end| buf.get(data_offset..data_end)) {
+        Some(data) => data,
+        None => {
+            problems.push(Inconsistency::DataOutOfRange { name });
+            return;
+        }
+    };
+    if central.flags & 1 != 0 {
+        // Content of encrypted entries cannot be checked without password.
+        return;
+    }
+    let content = match decode(central.method, data, central.uncompressed_size) {
+        Ok(Some(content)) => content,
+        Ok(None) => {
+            problems.push(Inconsistency::UnsupportedMethod {
+                name,
+                method: central.method,
+            });
+            return;
+        }
+        Err(message) => {
+            problems.push(Inconsistency::CorruptData { name, message });
+            return;
+        }
+    };
+    let actual = content.len() as u64;
+    if actual != central.uncompressed_size {
+        problems.push(Inconsistency::SizeMismatch {
+            name: name.clone(),
+            recorded: central.uncompressed_size,
+            actual,
+        });
+    }
+    let mut hasher = CRC32::default();
+    hasher.write(&content);
+    if hasher.finish() != central.checksum {
+        problems.push(Inconsistency::ChecksumMismatch {
+            name,
+            recorded: central.checksum,
+            actual: hasher.finish(),
+        });
+    }
+}
+
+/// Records of central directory with offsets of their local file headers,
+/// and the number of entries recorded in end of central directory.
+///
+/// `None` if central directory cannot be found.
+fn central_directory(
+    buf: &[u8],
+    problems: &mut Vec<Inconsistency>,
+) -> Option<(Vec<(Header, usize)>, u64)> {
+    let (end, record) = match find_end_of_central_directory(buf) {
+        Some(end) => end,
+        None => {
+            problems.push(Inconsistency::MissingEndOfCentralDirectory);
+            return None;
+        }
+    };
+    let mut recorded = record.entries as u64;
+    let mut size = record.central_directory_size as u64;
+    let mut top = record.central_directory_offset as u64;
+    let mut limit = end;
+    let locator = end.checked_sub(20).and_then(|pos| {
+        Some((
+            pos,
+            Zip64EndOfCentralDirectoryLocator::from_bytes(&buf[pos..])?,
+        ))
+    });
+    if let Some((locator, Zip64EndOfCentralDirectoryLocator { offset })) = locator {
+        let offset = Some(offset as usize)
+            .filter(|&offset| offset < locator)
+            .unwrap_or(locator);
+        match Zip64EndOfCentralDirectory::from_bytes(&buf[offset..]) {
+            Some(record) => {
+                recorded = record.entries;
+                size = record.central_directory_size;
+                top = record.central_directory_offset;
+                limit = offset;
+            }
+            None => {
+                problems.push(Inconsistency::BadSignature {
+                    offset,
+                    expected: ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE,
+                });
+                return None;
+            }
+        }
+    }
+    let (top, size) = (top as usize, size as usize);
+    if top.checked_add(size).is_none_or(|bottom| bottom > limit) {
+        problems.push(Inconsistency::CentralDirectoryOutOfRange);
+        return None;
+    }
+    let directory = &buf[..top + size];
+    let mut position = top;
+    let mut records = Vec::new();
+    while position < top + size {
+        if !directory[position..].starts_with(&CENTRAL_DIRECTORY_HEADER_SIGNATURE.to_le_bytes()) {
+            problems.push(Inconsistency::BadSignature {
+                offset: position,
+                expected: CENTRAL_DIRECTORY_HEADER_SIGNATURE,
+            });
+            break;
+        }
+        let record = CentralDirectoryHeader::from_bytes(&directory[position..])
+            .and_then(|record| Some((central_header(&record)?, record.size())));
+        match record {
+            Some((record, length)) => {
+                records.push(record);
+                position += length;
+            }
+            None => {
+                problems.push(Inconsistency::CentralDirectoryOutOfRange);
+                break;
+            }
+        }
+    }
+    Some((records, recorded))
+}
+
+/// Headers and stored data of entries, for [`merge`](crate::merge).
+///
+/// `None` if the archive is inconsistent or an entry is encrypted.
+#[cfg(any(feature = "verify", test))]
+pub(crate) fn read_raw_entries(buf: &[u8]) -> Option<Vec<(Header, &[u8])>> {
+    let mut problems = Vec::new();
+    let (records, _) = central_directory(buf, &mut problems)?;
+    if !problems.is_empty() {
+        return None;
+    }
+    records
+        .into_iter()
+        .map(|(header, offset)| {
+            if header.flags & 1 != 0 {
+                return None;
+            }
+            let (_, data_offset) = local_header(buf, offset)?;
+            let data_end = data_offset.checked_add(header.compressed_size as usize)?;
+            let data = buf.get(data_offset..data_end)?;
+            Some((header, data))
+        })
+        .collect()
+}
+
+/// Headers and contents of entries, for [`add_archive`](crate::ZipArchive::add_archive).
+///
+/// `None` if the archive is inconsistent, or an entry is encrypted or of unsupported method.
+#[cfg(any(feature = "verify", test))]
+pub(crate) fn read_entries(buf: &[u8]) -> Option<Vec<(Header, Vec<u8>)>> {
+    read_raw_entries(buf)?
+        .into_iter()
+        .map(|(header, data)| {
+            let content = decode(header.method, data, header.uncompressed_size).ok()??;
+            let mut hasher = CRC32::default();
+            hasher.write(&content);
+            if hasher.finish() != header.checksum
+                || content.len() as u64 != header.uncompressed_size
+            {
+                return None;
+            }
+            Some((header, content))
+        })
+        .collect()
+}
+
+/// Re-parse an archive and report inconsistencies.
+///
+/// Local file headers, central directory and CRCs of all entries are checked.
+/// An empty vector means the archive is consistent.
+pub fn verify<R: Read>(mut reader: R) -> Result<Vec<Inconsistency>> {
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf)?;
+    let mut problems = Vec::new();
+    let (records, recorded) = match central_directory(&buf, &mut problems) {
+        Some(directory) => directory,
+        None => return Ok(problems),
+    };
+    let mut references = HashMap::new();
+    for (_, offset) in records.iter() {
+        *references.entry(*offset).or_insert(0) += 1;
+    }
+    for (header, offset) in records.iter() {
+        verify_entry(&buf, header, *offset, references[offset] > 1, &mut problems);
+    }
+    let found = records.len() as u64;
+    if found != recorded {
+        problems.push(Inconsistency::EntryCountMismatch { recorded, found });
+    }
+    Ok(problems)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{verify, verify_entry, Header, Inconsistency, LOCAL_FILE_HEADER_SIGNATURE};
+    use crate::{Level, ZipArchive};
+
+    fn archive() -> Vec<u8> {
+        let mut buf = Vec::new();
+        ZipArchive::new(&mut buf)
+            .add_entry("raw.txt", b"raw content", Level::Raw)
+            .unwrap()
+            .add_entry("deflate.txt", &[b'a'; 1000], Level::High)
+            .unwrap()
+            .finish()
+            .unwrap();
+        buf
+    }
+
+    #[test]
+    fn it_works() {
+        assert_eq!(verify(archive().as_slice()).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn detect_broken_data() {
+        let mut buf = archive();
+        buf[30 + 7] = b'R';
+        assert_eq!(
+            verify(buf.as_slice()).unwrap(),
+            vec![Inconsistency::ChecksumMismatch {
+                name: "raw.txt".to_string(),
+                recorded: 0xfdf1160d,
+                actual: 0x7b6d99da,
+            }]
+        );
+        assert_eq!(
+            verify(&buf[..buf.len() - 1]).unwrap(),
+            vec![Inconsistency::MissingEndOfCentralDirectory]
+        );
+
+        let central = Header {
+            version_made_by: 20,
+            flags: 0,
+            method: 0,
+            timestamp: 0,
+            checksum: 0,
+            compressed_size: 0,
+            uncompressed_size: 0,
+            external_attributes: 0,
+            filename: b"far.txt".to_vec(),
+            zip64: true,
+        };
+        let mut problems = Vec::new();
+        verify_entry(&buf, &central, usize::MAX - 1, false, &mut problems);
+        assert_eq!(
+            problems,
+            vec![Inconsistency::BadSignature {
+                offset: usize::MAX - 1,
+                expected: LOCAL_FILE_HEADER_SIGNATURE,
+            }]
+        );
+    }
+}
@@ -0,0 +1,458 @@
+use crate::crc32::CRC32;
+use crate::{
+    advance, CountingWriter, EntryOptions, Error, Metadata, Result, Signed, Stage, Truncate,
+    ZipArchive, ZipEntry, ZipState, SHA256,
+};
+use deflate::write::DeflateEncoder;
+use std::io::{SeekFrom, Write};
+
+/// Writer of the content of an entry started by [`ZipArchive::start_entry`].
+///
+/// It borrows the archive until [`finish`](EntryWriter::finish), so no other entry can be
+/// started, added or finished in the meantime, even from another thread.
+pub struct EntryWriter<'w, 'a, T: Write + ?Sized + 'a> {
+    /// `None` after finished.
+    sink: Option<Sink<'w, 'a, T>>,
+    name: String,
+    entry: ZipEntry,
+    hasher: CRC32,
+    /// SHA-256 of the content if [`digest`](ZipArchive::digest) is enabled.
+    digest: Option<SHA256>,
+    /// Whether the case of the name is recorded by this entry.
+    recorded: bool,
+}
+
+/// Handling of the data of an entry given up by [`EntryWriter::abort`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Abort {
+    /// Leave the data written so far as dead space, which the central directory does not list.
+    Skip,
+    /// Add the entry with the content written so far, as if it were complete.
+    Keep,
+    /// Leave the archive poisoned, so later calls fail with
+    /// [`Error::Poisoned`](crate::Error::Poisoned).
+    Poison,
+}
+
+enum Sink<'w, 'a, T: Write + ?Sized + 'a> {
+    Stored(CountingWriter<Output<'w, 'a, T>>),
+    Deflated(Box<DeflateEncoder<CountingWriter<Output<'w, 'a, T>>>>),
+}
+
+/// Writer of entry data to the output of the archive.
+struct Output<'w, 'a, T: Write + ?Sized + 'a> {
+    zip: &'w mut ZipArchive<'a, T>,
+}
+
+impl<'a, T: Write + ?Sized + 'a> Write for Output<'_, 'a, T> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let zip = &mut *self.zip;
+        Signed::new(zip.output, &mut zip.batch, &mut zip.signer).write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.zip.output.flush()
+    }
+}
+
+impl<'a, T: Write + ?Sized + 'a> ZipArchive<'a, T> {
+    /// Start an entry whose content is written to the returned [`EntryWriter`].
+    ///
+    /// The content is compressed directly to the output with data descriptor, so it need not
+    /// be in memory at once. The entry is deflated regardless of the codec, or stored with
+    /// [`Level::Raw`](crate::Level::Raw), and written immediately even while
+    /// [`place_first`](crate::ZipArchiveBuilder::place_first) defers other entries.
+    /// Fixed offsets, two-pass compression and the sniffer are not applied, and encrypted
+    /// entries fail with [`Error::EncryptedStream`]. As the sizes are unknown when the local
+    /// header is written, it always has the Zip64 extra field, and the data descriptor is Zip64.
+    ///
+    /// The writer borrows the archive, so interleaving entries is a compile error.
+    /// Dropping the writer without [`finish`](EntryWriter::finish) poisons the archive, and
+    /// an entry whose source fails can be given up with [`abort`](EntryWriter::abort) or
+    /// [`rewind`](EntryWriter::rewind) instead. If a [`signer`](ZipArchive::signer) is set,
+    /// the bytes of the entry are held in memory for it until the entry is finished or
+    /// aborted, so that rewound bytes are not signed.
+    ///
+    /// ```
+    /// # use zip_builder::{Level, ZipArchive};
+    /// # use std::io::Write;
+    /// let mut buf = Vec::new();
+    /// let mut zip = ZipArchive::new(&mut buf);
+    /// let mut writer = zip.start_entry("log.txt", Level::Default)?;
+    /// for line in 0..100 {
+    ///     writeln!(writer, "line {}", line)?;
+    /// }
+    /// writer.finish()?;
+    /// zip.finish()?;
+    /// # Ok::<(), zip_builder::Error>(())
+    /// ```
+    ///
+    /// ```compile_fail
+    /// # use zip_builder::{Level, ZipArchive};
+    /// let mut buf = Vec::new();
+    /// let mut zip = ZipArchive::new(&mut buf);
+    /// let first = zip.start_entry("first.txt", Level::Default)?;
+    /// let second = zip.start_entry("second.txt", Level::Default)?;
+    /// first.finish()?;
+    /// # Ok::<(), zip_builder::Error>(())
+    /// ```
+    pub fn start_entry<O: Into<EntryOptions>>(
+        &mut self,
+        name: &str,
+        options: O,
+    ) -> Result<EntryWriter<'_, 'a, T>> {
+        self.start_entry_with(name, options.into(), &Metadata::default())
+    }
+
+    /// Start an entry like [`start_entry`](ZipArchive::start_entry) with `metadata`.
+    pub(crate) fn start_entry_with(
+        &mut self,
+        name: &str,
+        options: EntryOptions,
+        metadata: &Metadata,
+    ) -> Result<EntryWriter<'_, 'a, T>> {
+        self.check_poisoned()?;
+        if self.overdue(name) {
+            return Err(Error::DeadlineExceeded {
+                name: name.to_string(),
+            });
+        }
+        self.check_limits(name, 0, None)?;
+        self.check_options(name, options)?;
+        let prefixed = self.prefixed(name).into_owned();
+        let options = self
+            .entry_options(&prefixed, options)
+            .deflated()
+            .data_descriptor(true);
+        #[cfg(feature = "aes")]
+        if options.encrypted() {
+            return Err(Error::EncryptedStream { name: prefixed });
+        }
+        self.state = ZipState::Processing;
+        let folded = self.folded_names.len();
+        self.record_case(name);
+        let recorded = self.folded_names.len() > folded;
+        let mut entry = ZipEntry::new(&prefixed, &[], &[], &options, self.offset);
+        entry.zip64 = true;
+        metadata.apply(&mut entry);
+        let threshold = self.config.zip64_threshold;
+        self.hold_signed();
+        let mut output = Signed::new(self.output, &mut self.batch, &mut self.signer);
+        let header = Self::pk0304(&mut output, &entry, &[], threshold)
+            .map_err(|error| error.in_entry(&prefixed, Stage::LocalHeader))?;
+        advance(&mut self.offset, header)?;
+        entry.data_offset = self.offset;
+        let digest = Some(SHA256::default()).filter(|_| self.config.digest);
+        let output = CountingWriter::new(Output { zip: self });
+        let sink = match options.method() {
+            0 => Sink::Stored(output),
+            _ => Sink::Deflated(Box::new(options.deflate_encoder(output))),
+        };
+        Ok(EntryWriter {
+            sink: Some(sink),
+            name: name.to_string(),
+            entry,
+            hasher: CRC32::default(),
+            digest,
+            recorded,
+        })
+    }
+}
+
+impl<'w, 'a, T: Write + ?Sized + 'a> EntryWriter<'w, 'a, T> {
+    /// Write the data descriptor and add the entry to the archive.
+    ///
+    /// Size limits of the archive are checked here. If they are exceeded, the entry is
+    /// skipped like [`Abort::Skip`] and the error is returned.
+    pub fn finish(mut self) -> Result<()> {
+        let zip = self.end_data()?;
+        zip.commit_signed();
+        let entry = self.entry;
+        if let Err(error) = zip.check_limits(&self.name, entry.uncompressed_size as usize, None) {
+            if self.recorded {
+                zip.forget_case(&self.name);
+            }
+            zip.state = ZipState::Breathe;
+            return Err(error);
+        }
+        zip.total_size += entry.uncompressed_size;
+        if entry.uncompressed_size > 0 {
+            zip.poor_compression(
+                &entry.filename,
+                entry.uncompressed_size as usize,
+                entry.compressed_size as usize,
+            );
+        }
+        if let Some(observer) = &zip.config.observer {
+            observer.on_entry_complete(&entry.info());
+        }
+        zip.entries.push(entry);
+        zip.place(&self.name)?;
+        if zip.config.auto_flush {
+            zip.output.flush()?;
+        }
+        zip.state = ZipState::Breathe;
+        Ok(())
+    }
+
+    /// Give up the entry after its source failed, handling the data written so far by `abort`.
+    ///
+    /// See [`rewind`](EntryWriter::rewind) to remove the data from outputs which can seek.
+    pub fn abort(mut self, abort: Abort) -> Result<()> {
+        match abort {
+            Abort::Skip => {
+                let zip = self.end_data()?;
+                zip.commit_signed();
+                if self.recorded {
+                    zip.forget_case(&self.name);
+                }
+                zip.state = ZipState::Breathe;
+                Ok(())
+            }
+            Abort::Keep => self.finish(),
+            Abort::Poison => Ok(()),
+        }
+    }
+
+    /// Finish the compressed data and the data descriptor, and return the archive.
+    ///
+    /// Errors leave the archive poisoned.
+    fn end_data(&mut self) -> Result<&'w mut ZipArchive<'a, T>> {
+        let name = &self.entry.filename;
+        let output = match self.sink.take().expect("finished only once") {
+            Sink::Stored(output) => output,
+            Sink::Deflated(encoder) => encoder
+                .finish()
+                .map_err(|error| Error::from(error).in_entry(name, Stage::Data))?,
+        };
+        self.entry.compressed_size = output.count();
+        self.entry.checksum = self.hasher.finish();
+        self.entry.sha256 = self.digest.as_ref().map(SHA256::finish);
+        let zip = output.into_inner().zip;
+        advance(&mut zip.offset, self.entry.compressed_size)?;
+        let threshold = zip.config.zip64_threshold;
+        let mut signed = Signed::new(zip.output, &mut zip.batch, &mut zip.signer);
+        let descriptor = ZipArchive::<T>::pk0708(&mut signed, &self.entry, threshold)
+            .map_err(|error| error.in_entry(name, Stage::Data))?;
+        advance(&mut zip.offset, descriptor)?;
+        Ok(zip)
+    }
+}
+
+impl<'w, 'a, T: Truncate + ?Sized + 'a> EntryWriter<'w, 'a, T> {
+    /// Give up the entry and restore the archive exactly as it was before
+    /// [`start_entry`](ZipArchive::start_entry), by rewinding and truncating the output.
+    ///
+    /// The bytes of the entry are not given to the [`signer`](ZipArchive::signer).
+    /// If the rollback fails, the archive is poisoned.
+    pub fn rewind(mut self) -> Result<()> {
+        let zip = self.end_data()?;
+        zip.discard_signed();
+        let written = zip.offset - self.entry.offset;
+        let position = zip.output.stream_position()? - written;
+        zip.output.seek(SeekFrom::Start(position))?;
+        zip.output.truncate(position)?;
+        zip.offset = self.entry.offset;
+        if self.recorded {
+            zip.forget_case(&self.name);
+        }
+        zip.state = ZipState::Breathe;
+        Ok(())
+    }
+}
+
+impl<'a, T: Write + ?Sized + 'a> Write for EntryWriter<'_, 'a, T> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = match self.sink.as_mut().expect("not finished") {
+            Sink::Stored(output) => output.write(buf)?,
+            Sink::Deflated(encoder) => encoder.write(buf)?,
+        };
+        self.hasher.write(&buf[..written]);
+        if let Some(digest) = &mut self.digest {
+            digest.write(&buf[..written]);
+        }
+        self.entry.uncompressed_size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self.sink.as_mut().expect("not finished") {
+            Sink::Stored(output) => output.flush(),
+            Sink::Deflated(encoder) => encoder.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Abort;
+    use crate::verify::read_entries;
+    use crate::{verify, Error, Level, Signer, ZipArchive, ZipArchiveBuilder, SHA256};
+    use std::io::{Cursor, Write};
+
+    #[test]
+    fn it_works() {
+        let mut buf = Vec::new();
+        let mut zip = ZipArchiveBuilder::new()
+            .root_prefix("root/")
+            .build(&mut buf);
+        zip.push_entry("first.txt", b"first", Level::Raw).unwrap();
+        for level in [Level::High, Level::Raw] {
+            let mut writer = zip.start_entry(&format!("{:?}.txt", level), level).unwrap();
+            for _ in 0..100 {
+                writer.write_all(b"streamed content\n").unwrap();
+            }
+            writer.finish().unwrap();
+        }
+        zip.start_entry("empty.txt", Level::Default)
+            .unwrap()
+            .finish()
+            .unwrap();
+        let summary = zip.finish().unwrap();
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+        let names: Vec<_> = summary
+            .entries
+            .iter()
+            .map(|entry| &entry.name[..])
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "root/first.txt",
+                "root/High.txt",
+                "root/Raw.txt",
+                "root/empty.txt"
+            ]
+        );
+        assert!(summary.entries[1].compressed_size < 1700);
+        assert_eq!(summary.entries[2].compressed_size, 1700);
+        let entries = read_entries(&buf).unwrap();
+        assert_eq!(entries[1].1, b"streamed content\n".repeat(100));
+        assert!(entries[3].1.is_empty());
+
+        let mut buf = Vec::new();
+        let mut zip = ZipArchiveBuilder::new()
+            .zip64_threshold(500)
+            .build(&mut buf);
+        for (name, size) in [("zip64.txt", 1000), ("small.txt", 10)] {
+            let mut writer = zip.start_entry(name, Level::Raw).unwrap();
+            writer.write_all(&vec![b'z'; size]).unwrap();
+            writer.finish().unwrap();
+        }
+        zip.finish().unwrap();
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+        // Version needed, length of the extra field and its header ID.
+        assert_eq!(buf[4..6], 45u16.to_le_bytes());
+        assert_eq!(buf[28..30], 20u16.to_le_bytes());
+        assert_eq!(buf[39..41], 1u16.to_le_bytes());
+        assert_eq!(read_entries(&buf).unwrap()[0].1, vec![b'z'; 1000]);
+
+        let mut buf = Vec::new();
+        let mut zip = ZipArchive::new(&mut buf).digest();
+        let mut writer = zip.start_entry("digest.txt", Level::Default).unwrap();
+        writer.write_all(b"streamed ").unwrap();
+        writer.write_all(b"content").unwrap();
+        writer.finish().unwrap();
+        let summary = zip.finish().unwrap();
+        let mut hasher = SHA256::default();
+        hasher.write(b"streamed content");
+        assert_eq!(summary.entries[0].sha256, Some(hasher.finish()));
+    }
+
+    #[test]
+    fn dropped() {
+        let mut buf = Vec::new();
+        let mut zip = ZipArchive::new(&mut buf);
+        drop(zip.start_entry("a.txt", Level::Default).unwrap());
+        assert!(matches!(
+            zip.push_entry("b.txt", b"b", Level::Default),
+            Err(Error::Poisoned)
+        ));
+        assert!(matches!(zip.finish(), Err(Error::Poisoned)));
+    }
+
+    #[test]
+    fn abort() {
+        let names = |buf: &[u8]| -> Vec<String> {
+            read_entries(buf)
+                .unwrap()
+                .into_iter()
+                .map(|(header, _)| String::from_utf8(header.filename).unwrap())
+                .collect()
+        };
+        for abort in [Abort::Skip, Abort::Keep] {
+            let mut buf = Vec::new();
+            let mut zip = ZipArchive::new(&mut buf);
+            let mut writer = zip.start_entry("broken.txt", Level::High).unwrap();
+            writer.write_all(b"partial").unwrap();
+            writer.abort(abort).unwrap();
+            zip.push_entry("Broken.txt", b"next", Level::Raw).unwrap();
+            zip.finish().unwrap();
+            assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+            match abort {
+                Abort::Skip => assert_eq!(names(&buf), ["Broken.txt"]),
+                _ => assert_eq!(names(&buf), ["broken.txt", "Broken.txt"]),
+            }
+        }
+
+        let mut buf = Vec::new();
+        let mut zip = ZipArchive::new(&mut buf);
+        let writer = zip.start_entry("a.txt", Level::Default).unwrap();
+        writer.abort(Abort::Poison).unwrap();
+        assert!(matches!(zip.finish(), Err(Error::Poisoned)));
+    }
+
+    #[test]
+    fn rewind() {
+        let mut expected = Vec::new();
+        ZipArchive::new(&mut expected)
+            .add_entry("a.txt", b"a", Level::Raw)
+            .unwrap()
+            .add_entry("b.txt", b"b", Level::Raw)
+            .unwrap()
+            .finish()
+            .unwrap();
+        let mut buf = Cursor::new(Vec::new());
+        let mut zip = ZipArchive::new(&mut buf);
+        zip.push_entry("a.txt", b"a", Level::Raw).unwrap();
+        let mut writer = zip.start_entry("b.txt", Level::High).unwrap();
+        writer.write_all(&[b'b'; 1000]).unwrap();
+        writer.rewind().unwrap();
+        zip.push_entry("b.txt", b"b", Level::Raw).unwrap();
+        zip.finish().unwrap();
+        assert_eq!(buf.into_inner(), expected);
+    }
+
+    /// Signer whose signature is the signed bytes themselves.
+    #[derive(Default)]
+    struct Recorder(Vec<u8>);
+
+    impl Signer for Recorder {
+        fn update(&mut self, bytes: &[u8]) {
+            self.0.extend_from_slice(bytes);
+        }
+
+        fn finish(&mut self) -> Vec<u8> {
+            std::mem::take(&mut self.0)
+        }
+    }
+
+    #[test]
+    fn signed() {
+        let mut buf = Cursor::new(Vec::new());
+        let mut zip = ZipArchive::new(&mut buf).signer(Recorder::default());
+        let mut writer = zip.start_entry("rewound.txt", Level::High).unwrap();
+        writer.write_all(&[b'r'; 1000]).unwrap();
+        writer.rewind().unwrap();
+        let mut writer = zip.start_entry("skipped.txt", Level::High).unwrap();
+        writer.write_all(b"skipped").unwrap();
+        writer.abort(Abort::Skip).unwrap();
+        let mut writer = zip.start_entry("kept.txt", Level::High).unwrap();
+        writer.write_all(b"kept").unwrap();
+        writer.finish().unwrap();
+        let summary = zip.finish().unwrap();
+        let buf = buf.into_inner();
+        assert_eq!(summary.signature.unwrap(), buf);
+        assert_eq!(verify(buf.as_slice()).unwrap(), vec![]);
+    }
+}